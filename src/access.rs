@@ -0,0 +1,75 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::hour_of_day;
+
+// Gesicherte Ebene: nur mit einem der Ausweise erreichbar. Innerhalb von `open_hours`
+// (von einschließlich, bis ausschließlich) ist sie für alle frei zugänglich.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecuredFloor {
+    pub floor: u8,
+    pub badges: Vec<u32>,
+    pub open_hours: Option<(u8, u8)>,
+}
+
+// Grund, aus dem ein Fahrbefehl abgelehnt wurde
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AccessDenied {
+    NoBadge { open_hours: Option<(u8, u8)> },
+    NotAuthorized,
+}
+
+impl fmt::Display for AccessDenied {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccessDenied::NoBadge { open_hours: None } => write!(f, "badge required"),
+            AccessDenied::NoBadge {
+                open_hours: Some((from, to)),
+            } => {
+                write!(f, "badge required outside {}:00-{}:00", from, to)
+            }
+            AccessDenied::NotAuthorized => write!(f, "badge not authorized"),
+        }
+    }
+}
+
+// Zutrittskontrolle für Fahrbefehle im Fahrkorb, gemeinsam genutzt über Arc
+pub struct AccessControl {
+    secured: Vec<SecuredFloor>,
+    start_hour: u8, // Uhrzeit beim Start der Simulation
+}
+
+impl AccessControl {
+    pub fn new(secured: Vec<SecuredFloor>, start_hour: u8) -> Self {
+        Self {
+            secured,
+            start_hour,
+        }
+    }
+
+    // Darf ein Fahrgast mit diesen Ausweisen zur Ebene fahren? `elapsed` ist die Zeit der Simulation seit ihrem Start
+    pub fn check(&self, floor: u8, badges: &[u32], elapsed: Duration) -> Result<(), AccessDenied> {
+        let Some(secured) = self.secured.iter().find(|secured| secured.floor == floor) else {
+            return Ok(());
+        };
+        let hour = hour_of_day(self.start_hour, elapsed);
+        if secured
+            .open_hours
+            .is_some_and(|(from, to)| from <= hour && hour < to)
+        {
+            return Ok(());
+        }
+        if badges.is_empty() {
+            return Err(AccessDenied::NoBadge {
+                open_hours: secured.open_hours,
+            });
+        }
+        if badges.iter().any(|badge| secured.badges.contains(badge)) {
+            Ok(())
+        } else {
+            Err(AccessDenied::NotAuthorized)
+        }
+    }
+}
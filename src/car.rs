@@ -0,0 +1,112 @@
+use crate::energy::EnergyModel;
+use crate::Door;
+
+// Bauform eines Fahrstuhls: bediente Ebenen, Anzahl übereinanderliegender Decks und ob er ein Lastenaufzug ist.
+// Die Position eines Fahrstuhls ist immer die Ebene des unteren Decks.
+#[derive(Debug, Clone)]
+pub struct ElevatorSpec {
+    pub served_floors: Vec<u8>, // Ebenen, die mindestens ein Deck erreicht
+    pub decks: u8,
+    pub energy: EnergyModel,
+    pub freight: bool, // Lastenaufzug für Transportwagen und Betten
+}
+
+impl ElevatorSpec {
+    pub fn single_deck(served_floors: Vec<u8>) -> Self {
+        Self {
+            served_floors,
+            decks: 1,
+            energy: EnergyModel::default(),
+            freight: false,
+        }
+    }
+
+    // Lastenaufzug mit einem Deck; nimmt auch Fahrgäste mit, wenn die `FreightPolicy` es erlaubt
+    pub fn freight_car(served_floors: Vec<u8>) -> Self {
+        Self {
+            freight: true,
+            ..Self::single_deck(served_floors)
+        }
+    }
+
+    // Doppeldecker, z. B. Lobby/Mezzanine oder gerade/ungerade Ebenen gleichzeitig
+    pub fn double_deck(served_floors: Vec<u8>) -> Self {
+        Self {
+            served_floors,
+            decks: 2,
+            energy: EnergyModel::double_deck(),
+            freight: false,
+        }
+    }
+
+    // Ein Halt ist nur gültig, wenn jedes Deck vor einer bedienten Ebene steht
+    pub fn can_stop(&self, position: u8) -> bool {
+        (0..self.decks).all(|deck| self.served_floors.contains(&(position + deck)))
+    }
+
+    // Alle gültigen Halte (Position des unteren Decks), von unten nach oben
+    pub fn stops(&self) -> Vec<u8> {
+        let mut stops: Vec<u8> = self
+            .served_floors
+            .iter()
+            .copied()
+            .filter(|&floor| self.can_stop(floor))
+            .collect();
+        stops.sort_unstable();
+        stops
+    }
+
+    // Position des Fahrstuhls, damit `deck` vor `floor` hält
+    pub fn position_for(&self, deck: u8, floor: u8) -> Option<u8> {
+        floor
+            .checked_sub(deck)
+            .filter(|&position| self.can_stop(position))
+    }
+
+    // Deck, mit dem man von `from` nach `to` fahren kann (ohne das Deck zu wechseln)
+    pub fn deck_between(&self, from: u8, to: u8) -> Option<u8> {
+        (0..self.decks).find(|&deck| {
+            self.position_for(deck, from).is_some() && self.position_for(deck, to).is_some()
+        })
+    }
+
+    pub fn deck_name(&self, deck: u8) -> &'static str {
+        match (self.decks, deck) {
+            (1, _) => "single deck",
+            (_, 0) => "lower deck",
+            _ => "upper deck",
+        }
+    }
+}
+
+// Ein Deck hat eine eigene Tür und eigene Passagiere
+pub struct Deck {
+    pub door: Door,
+    pub passengers: Vec<usize>, // Vektor für Passagier-IDs
+    pub load: u32,              // Belegter Platz in Einheiten, siehe `PassengerProfile::space`
+}
+
+impl Deck {
+    pub fn new() -> Self {
+        Self {
+            door: Door::new(),
+            passengers: Vec::new(),
+            load: 0,
+        }
+    }
+
+    pub fn board(&mut self, passenger_id: usize, space: u32) {
+        self.passengers.push(passenger_id);
+        self.load += space;
+    }
+
+    // Gibt `false` zurück, wenn der Passagier nicht in diesem Deck fährt
+    pub fn alight(&mut self, passenger_id: usize, space: u32) -> bool {
+        if !self.passengers.contains(&passenger_id) {
+            return false;
+        }
+        self.passengers.retain(|&id| id != passenger_id);
+        self.load = self.load.saturating_sub(space);
+        true
+    }
+}
@@ -0,0 +1,824 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+use crate::config::{format_duration, parse_duration, SimulationConfig};
+use crate::console;
+use crate::dashboard::Dashboard;
+use crate::golden;
+use crate::metrics::{Metrics, Summary};
+use crate::snapshot::Snapshot;
+use crate::stress;
+use crate::trace;
+use crate::Simulation;
+
+const USAGE: &str = "\
+Usage: elevator [COMMAND] [OPTIONS]
+
+Commands:
+  run                      Run one simulation (default without a command)
+  batch                    Run a parameter sweep, one simulation per combination and seed
+  replay TRACE.csv         Run the passengers recorded in a trace
+  compare STRATEGY...      Run the same traffic with different strategies; a strategy is a parking strategy
+                           or settings like freight_policy=mixed or parking=lobby,hall_call_limit=60s
+  restore SNAPSHOT.json    Continue a run from a snapshot until --duration (total simulated time). This is a
+                           replay, not a restore of the saved state: the run starts again from the settings,
+                           traffic and console inputs in the snapshot, is replayed up to the snapshot, must
+                           match the saved state there and then continues exactly as the original run would
+  console                  Run the simulation and type commands into it until 'quit', e.g.
+                           elevator console --set passengers=0 --set demo_script=false
+  golden [SCENARIO...]     Run the scenarios in --dir and compare events and metrics with their golden files;
+                           a scenario is NAME.toml with a fixed seed, optionally with the traffic in NAME.csv
+  stress                   Run random buildings, traffic, faults and timing jitter, one per seed, and check
+                           safety, deadlocks and that every passenger finishes; failing cases are shrunk
+                           to a minimal scenario in --dir
+  help                     Show this help
+
+Options:
+  --config FILE            Building and traffic settings (TOML), defaults to the example building
+  --seed N                 Seed for the random traffic (run, batch, compare), first seed of stress
+  --seeds N                Number of seeds per combination, counting up from --seed (batch, compare),
+                           number of cases (stress, defaults to 10)
+  --duration D             Simulated time, e.g. 90s, 15m or 8h
+  --set KEY=VALUE          Override a setting, e.g. --set passengers=10 (also for every stress case)
+  --sweep KEY=V1,V2,...    Values to sweep (batch), may be repeated
+  --record FILE            Write the generated traffic as a trace (run)
+  --jobs N                 Simulations running at the same time (batch, compare, golden, stress)
+  --format text|json|csv|events
+                           Output format, text prints the full report, events the event log and metrics
+                           (run, replay, restore); on stdout, the log of json, csv and events goes to stderr
+  --output FILE            Write json, csv or events output to FILE instead of stdout
+  --dashboard              Show shafts, cars and waiting passengers live in the terminal (run, replay, restore);
+                           keys: space pause, n step 1s, + and - simulation speed, q quit
+  --snapshot FILE          Save the simulation state and everything needed to replay it to FILE
+                           (run, replay, restore, console at 'quit')
+  --snapshot-at D          Simulated time of the snapshot, defaults to the end of the run
+  --dir DIR                Directory with the golden scenarios, defaults to golden (golden),
+                           for the minimal failing scenarios, defaults to stress-failures (stress)
+  --accept                 Write the new results into the golden files instead of comparing (golden)
+  --strict                 Fail the run if a safety invariant was violated (run, replay, restore, batch, compare);
+                           golden scenarios always run strict
+
+Parking strategies: stay, lobby, floor:N, spread-evenly, predicted-demand, time-of-day
+Settings: passengers, groups, group_size, arrival_window, start_hour, duration, seed, parking,
+          freight_policy, hall_call_limit, jitter, demo_script, watchdog, patience.stairs_floors,
+          patience.stairs_after, patience.give_up_after";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Json,
+    Csv,
+    Events,
+}
+
+#[derive(Default)]
+struct Options {
+    positional: Vec<String>,
+    config: Option<String>,
+    seed: Option<u64>,
+    seeds: Option<u64>,
+    duration: Option<String>,
+    sets: Vec<String>,
+    sweeps: Vec<(String, Vec<String>)>,
+    record: Option<String>,
+    jobs: Option<usize>,
+    format: Option<Format>,
+    output: Option<String>,
+    dashboard: bool,
+    snapshot: Option<String>,
+    snapshot_at: Option<String>,
+    dir: Option<String>,
+    accept: bool,
+    strict: bool,
+}
+
+// Ergebnis eines Laufs, auch Austauschformat zwischen `batch` und den einzelnen Läufen.
+// Ein fehlgeschlagener Lauf einer Parameterreihe hat keine Kennzahlen, nur den Fehler.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RunResult {
+    label: String,
+    seed: Option<u64>,
+    summary: Option<Summary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let (command, rest) = match args.first().map(String::as_str) {
+        None => ("run", args),
+        Some(arg) if arg.starts_with("--") => ("run", args),
+        Some(command) => (command, &args[1..]),
+    };
+    if matches!(command, "help" | "-h") || rest.iter().any(|arg| arg == "--help") {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    let options = parse_options(rest)?;
+    if options.dashboard && !matches!(command, "run" | "replay" | "restore") {
+        return Err("--dashboard only works with run, replay and restore".to_string());
+    }
+    if options.snapshot.is_some() && !matches!(command, "run" | "replay" | "restore" | "console") {
+        return Err("--snapshot only works with run, replay, restore and console".to_string());
+    }
+    if options.snapshot_at.is_some() && options.snapshot.is_none() {
+        return Err("--snapshot-at needs --snapshot FILE".to_string());
+    }
+    if options.snapshot_at.is_some() && command == "console" {
+        return Err(
+            "the console takes its snapshot at 'quit', --snapshot-at does not apply".to_string(),
+        );
+    }
+    if options.strict && !matches!(command, "run" | "replay" | "restore" | "batch" | "compare") {
+        return Err("--strict only works with run, replay, restore, batch and compare".to_string());
+    }
+    if options.format == Some(Format::Events) && !matches!(command, "run" | "replay" | "restore") {
+        return Err("--format events only works with run, replay and restore".to_string());
+    }
+    match command {
+        "run" => run_command(&options),
+        "replay" => replay_command(&options),
+        "restore" => restore_command(&options),
+        "batch" => batch_command(&options),
+        "compare" => compare_command(&options),
+        "console" => console_command(&options),
+        "golden" => golden_command(&options),
+        "stress" => stress_command(&options),
+        _ => Err(format!("unknown command '{}'\n\n{}", command, USAGE)),
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            options.positional.push(arg.clone());
+            continue;
+        }
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", arg))
+        };
+        let number = |text: String| {
+            text.parse::<u64>()
+                .map_err(|_| format!("{} expects a number, got '{}'", arg, text))
+        };
+        match arg.as_str() {
+            "--config" => options.config = Some(value()?),
+            "--seed" => options.seed = Some(number(value()?)?),
+            "--seeds" => options.seeds = Some(number(value()?)?.max(1)),
+            "--duration" => options.duration = Some(value()?),
+            "--set" => options.sets.push(value()?),
+            "--sweep" => {
+                let sweep = value()?;
+                let (key, values) = split_setting(&sweep)?;
+                options.sweeps.push((
+                    key.to_string(),
+                    values.split(',').map(str::to_string).collect(),
+                ));
+            }
+            "--record" => options.record = Some(value()?),
+            "--jobs" => options.jobs = Some(number(value()?)?.max(1) as usize),
+            "--format" => {
+                options.format = Some(match value()?.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    "events" => Format::Events,
+                    other => {
+                        return Err(format!(
+                            "unknown format '{}', expected text, json, csv or events",
+                            other
+                        ))
+                    }
+                })
+            }
+            "--output" => options.output = Some(value()?),
+            "--dashboard" => options.dashboard = true,
+            "--snapshot" => options.snapshot = Some(value()?),
+            "--snapshot-at" => options.snapshot_at = Some(value()?),
+            "--dir" => options.dir = Some(value()?),
+            "--accept" => options.accept = true,
+            "--strict" => options.strict = true,
+            _ => return Err(format!("unknown option '{}'\n\n{}", arg, USAGE)),
+        }
+    }
+    if options.output.is_some() && options.format.unwrap_or(Format::Text) == Format::Text {
+        return Err("--output needs --format json, csv or events".to_string());
+    }
+    Ok(options)
+}
+
+fn split_setting(setting: &str) -> Result<(&str, &str), String> {
+    setting
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", setting))
+}
+
+// Konfigurationsdatei, danach --duration, --seed und --set in dieser Reihenfolge
+fn load_config(options: &Options) -> Result<SimulationConfig, String> {
+    let config = match &options.config {
+        Some(path) => SimulationConfig::load(path)?,
+        None => SimulationConfig::default(),
+    };
+    override_config(config, options)
+}
+
+fn override_config(
+    mut config: SimulationConfig,
+    options: &Options,
+) -> Result<SimulationConfig, String> {
+    if let Some(duration) = &options.duration {
+        config.duration = parse_duration(duration)?;
+    }
+    if let Some(seed) = options.seed {
+        config.seed = Some(seed);
+    }
+    for setting in &options.sets {
+        let (key, value) = split_setting(setting)?;
+        config.set(key, value)?;
+    }
+    config.validate()?;
+    Ok(config)
+}
+
+fn run_command(options: &Options) -> Result<(), String> {
+    if let Some(extra) = options.positional.first() {
+        return Err(format!("unexpected argument '{}'", extra));
+    }
+    let mut config = load_config(options)?;
+    let seed = config.seed.unwrap_or_else(rand::random);
+    config.seed = Some(seed); // Für den Snapshot
+    let log = LogToStderr::start(options);
+    println!(
+        "Simulation: seed {}, {} passenger(s), {} group(s), duration {}, parking {}",
+        seed,
+        config.passengers,
+        config.groups,
+        format_duration(config.duration),
+        config.parking
+    );
+    let arrivals = trace::generate(&config, &config.specs(), &mut StdRng::seed_from_u64(seed));
+    if let Some(path) = &options.record {
+        trace::write_csv(path, &arrivals)?;
+        println!("Simulation: traffic recorded to {}", path);
+    }
+    let metrics = crate::simulate(
+        &config,
+        &arrivals,
+        None,
+        options.dashboard,
+        snapshot_to(&config, options)?,
+    )?;
+    drop(log);
+    report(options, &metrics, config.parking.clone(), Some(seed))
+}
+
+fn replay_command(options: &Options) -> Result<(), String> {
+    let [path] = options.positional.as_slice() else {
+        return Err("replay needs exactly one trace file".to_string());
+    };
+    let config = load_config(options)?;
+    let arrivals = trace::read_csv(path)?;
+    let log = LogToStderr::start(options);
+    println!(
+        "Simulation: replaying {} passenger(s) from {}, duration {}, parking {}",
+        arrivals.len(),
+        path,
+        format_duration(config.duration),
+        config.parking
+    );
+    let metrics = crate::simulate(
+        &config,
+        &arrivals,
+        None,
+        options.dashboard,
+        snapshot_to(&config, options)?,
+    )?;
+    drop(log);
+    report(options, &metrics, path.clone(), None)
+}
+
+// Gebäude, Verkehr und Startwert kommen aus dem Snapshot; --duration und --set gelten für den weiteren Lauf
+fn restore_command(options: &Options) -> Result<(), String> {
+    let [path] = options.positional.as_slice() else {
+        return Err("restore needs exactly one snapshot file".to_string());
+    };
+    if options.config.is_some()
+        || options.seed.is_some()
+        || options.record.is_some()
+        || !options.sets.is_empty()
+    {
+        return Err(
+            "restore takes building, settings and traffic from the snapshot, --config, --seed, --set and --record do not apply"
+                .to_string(),
+        );
+    }
+    let snapshot = Snapshot::load(path)?;
+    let config = override_config(snapshot.config.clone(), options)?;
+    if config.duration < snapshot.taken_at {
+        return Err(format!(
+            "--duration {} ends before the snapshot at {}",
+            format_duration(config.duration),
+            format_duration(snapshot.taken_at)
+        ));
+    }
+    let log = LogToStderr::start(options);
+    println!(
+        "Simulation: restoring {} at {}, {} passenger(s), duration {}, parking {}",
+        path,
+        format_duration(snapshot.taken_at),
+        snapshot.arrivals.len(),
+        format_duration(config.duration),
+        config.parking
+    );
+    let metrics = crate::simulate(
+        &config,
+        &snapshot.arrivals,
+        Some(&snapshot),
+        options.dashboard,
+        snapshot_to(&config, options)?,
+    )?;
+    drop(log);
+    report(options, &metrics, path.clone(), config.seed)
+}
+
+// --snapshot mit dem Zeitpunkt aus --snapshot-at, sonst am Ende des Laufs
+fn snapshot_to<'a>(
+    config: &SimulationConfig,
+    options: &'a Options,
+) -> Result<Option<(&'a str, Duration)>, String> {
+    let Some(path) = &options.snapshot else {
+        return Ok(None);
+    };
+    let at = match &options.snapshot_at {
+        Some(at) => parse_duration(at)?,
+        None => config.duration,
+    };
+    Ok(Some((path.as_str(), at)))
+}
+
+// Bericht oder maschinenlesbares Ergebnis eines einzelnen Laufs; mit --strict ein Fehler, wenn der
+// `InvariantMonitor` eine Verletzung gemeldet hat
+fn report(
+    options: &Options,
+    metrics: &Mutex<Metrics>,
+    label: String,
+    seed: Option<u64>,
+) -> Result<(), String> {
+    let metrics = metrics.lock().unwrap();
+    match options.format.unwrap_or(Format::Text) {
+        Format::Text => metrics.print_report(),
+        Format::Events => write_output(options, metrics.event_report())?,
+        format => write_results(
+            format,
+            options,
+            &[RunResult {
+                label,
+                seed,
+                summary: Some(metrics.summary()),
+                error: None,
+            }],
+        )?,
+    }
+    let violations = metrics.summary().invariant_violations;
+    if options.strict && violations > 0 {
+        return Err(format!(
+            "{} invariant violation(s), see the invariant report",
+            violations
+        ));
+    }
+    Ok(())
+}
+
+// Läuft bis `quit` statt für `--duration`, danach der Bericht wie bei `run`
+fn console_command(options: &Options) -> Result<(), String> {
+    if let Some(extra) = options.positional.first() {
+        return Err(format!("unexpected argument '{}'", extra));
+    }
+    let mut config = load_config(options)?;
+    let seed = config.seed.unwrap_or_else(rand::random);
+    config.seed = Some(seed); // Für den Snapshot
+    let arrivals = trace::generate(&config, &config.specs(), &mut StdRng::seed_from_u64(seed));
+    let dashboard = Dashboard::new(
+        config.floors,
+        &config.specs(),
+        &config
+            .elevators
+            .iter()
+            .map(|elevator| elevator.shaft)
+            .collect::<Vec<_>>(),
+    );
+    // Die Konsole steuert ein System, das in Echtzeit läuft
+    clock::configure(Some(1.0));
+    let simulation = Simulation::start(&config, &arrivals, dashboard)?;
+    console::run(&simulation);
+    if let Some(path) = &options.snapshot {
+        // Ohne --duration endet das Fortsetzen dort, wo die Konsole beendet wurde
+        clock::run_until(clock::now());
+        let mut snapshot = Snapshot::take(&simulation);
+        snapshot.config.duration = snapshot.taken_at;
+        snapshot.save(path)?;
+        println!(
+            "Simulation: snapshot at {:.1}s written to {}",
+            snapshot.taken_at.as_secs_f64(),
+            path
+        );
+    }
+    simulation.metrics.lock().unwrap().print_report();
+    Ok(())
+}
+
+// Regressionstest: jedes Szenario einmal ausführen und mit seiner Golden-Datei vergleichen
+fn golden_command(options: &Options) -> Result<(), String> {
+    let dir = options.dir.as_deref().unwrap_or("golden");
+    let scenarios = golden::discover(Path::new(dir), &options.positional)?;
+    golden::run(&scenarios, options.accept, options.jobs.unwrap_or(4))
+}
+
+// Zufällige Szenarien; ohne --seed mit einem zufälligen ersten Startwert, der zum Nachstellen ausgegeben wird
+fn stress_command(options: &Options) -> Result<(), String> {
+    if let Some(extra) = options.positional.first() {
+        return Err(format!("unexpected argument '{}'", extra));
+    }
+    if options.config.is_some() || options.duration.is_some() {
+        return Err(
+            "stress generates building and duration per case, --config and --duration do not apply"
+                .to_string(),
+        );
+    }
+    let sets = options
+        .sets
+        .iter()
+        .map(|setting| {
+            split_setting(setting).map(|(key, value)| (key.to_string(), value.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let first_seed = options.seed.unwrap_or_else(|| rand::random::<u32>() as u64);
+    let dir = options.dir.as_deref().unwrap_or("stress-failures");
+    stress::run(
+        first_seed,
+        options.seeds.unwrap_or(10),
+        &sets,
+        options.jobs.unwrap_or(4),
+        Path::new(dir),
+    )
+}
+
+fn batch_command(options: &Options) -> Result<(), String> {
+    if options.sweeps.is_empty() {
+        return Err("batch needs at least one --sweep KEY=V1,V2,...".to_string());
+    }
+    let results = run_sweep(options, sweep_combinations(&options.sweeps))?;
+    match options.format.unwrap_or(Format::Text) {
+        Format::Text => print_table(&results),
+        format => write_results(format, options, &results)?,
+    }
+    sweep_failures(&results)
+}
+
+// Jede Strategie ist eine eigene Kombination von Einstellungen; ein Name ohne `=` ist eine Parkstrategie
+fn compare_command(options: &Options) -> Result<(), String> {
+    if options.positional.len() < 2 {
+        return Err("compare needs at least two strategies".to_string());
+    }
+    let combinations = options
+        .positional
+        .iter()
+        .map(|strategy| strategy_settings(strategy))
+        .collect::<Result<Vec<_>, _>>()?;
+    let results = run_sweep(options, combinations)?;
+    match options.format.unwrap_or(Format::Text) {
+        Format::Text => {
+            print_table(&results);
+            print_comparison(&options.positional, &results);
+        }
+        format => write_results(format, options, &results)?,
+    }
+    sweep_failures(&results)
+}
+
+// "lobby" oder "parking=lobby,freight_policy=mixed"
+fn strategy_settings(strategy: &str) -> Result<Vec<(String, String)>, String> {
+    if !strategy.contains('=') {
+        return Ok(vec![("parking".to_string(), strategy.to_string())]);
+    }
+    strategy
+        .split(',')
+        .map(|setting| {
+            split_setting(setting).map(|(key, value)| (key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+// Alle Kombinationen der Werte einer Parameterreihe
+fn sweep_combinations(sweeps: &[(String, Vec<String>)]) -> Vec<Vec<(String, String)>> {
+    let mut combinations: Vec<Vec<(String, String)>> = vec![Vec::new()];
+    for (key, values) in sweeps {
+        combinations = combinations
+            .into_iter()
+            .flat_map(|combination| {
+                values.iter().map(move |value| {
+                    let mut combination = combination.clone();
+                    combination.push((key.clone(), value.clone()));
+                    combination
+                })
+            })
+            .collect();
+    }
+    combinations
+}
+
+// Nach der Ausgabe aller Ergebnisse: ein Fehler, wenn einzelne Läufe fehlgeschlagen sind
+fn sweep_failures(results: &[RunResult]) -> Result<(), String> {
+    let failed = results
+        .iter()
+        .filter(|result| result.error.is_some())
+        .count();
+    if failed > 0 {
+        return Err(format!(
+            "{} of {} simulation(s) failed",
+            failed,
+            results.len()
+        ));
+    }
+    Ok(())
+}
+
+// Jede Kombination läuft als eigener Prozess (`run --format json`), damit sich die Akteure
+// verschiedener Läufe nicht gegenseitig stören. Schlägt ein Lauf fehl, steht der Fehler in seinem
+// Ergebnis und die übrigen laufen weiter.
+fn run_sweep(
+    options: &Options,
+    combinations: Vec<Vec<(String, String)>>,
+) -> Result<Vec<RunResult>, String> {
+    // Konfiguration und Werte vorab prüfen, bevor Prozesse gestartet werden
+    let config = load_config(options)?;
+    for combination in &combinations {
+        let mut check = config.clone();
+        for (key, value) in combination {
+            check.set(key, value)?;
+        }
+    }
+
+    let first_seed = config.seed.unwrap_or(1);
+    let jobs: Vec<(Vec<(String, String)>, u64)> = combinations
+        .iter()
+        .flat_map(|combination| {
+            (0..options.seeds.unwrap_or(1))
+                .map(move |offset| (combination.clone(), first_seed + offset))
+        })
+        .collect();
+    eprintln!(
+        "Batch: {} simulation(s) of {} each",
+        jobs.len(),
+        format_duration(config.duration)
+    );
+
+    let executable =
+        std::env::current_exe().map_err(|error| format!("cannot find executable: {}", error))?;
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<RunResult>>> = Mutex::new(vec![None; jobs.len()]);
+    thread::scope(|scope| {
+        for _ in 0..options.jobs.unwrap_or(4).min(jobs.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some((combination, seed)) = jobs.get(index) else {
+                    break;
+                };
+                let result = run_child(&executable, options, combination, *seed, index)
+                    .unwrap_or_else(|error| {
+                        eprintln!("Batch: {}", error);
+                        RunResult {
+                            label: label(combination),
+                            seed: Some(*seed),
+                            summary: None,
+                            error: Some(error),
+                        }
+                    });
+                eprintln!("Batch: finished {}/{}", index + 1, jobs.len());
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+    Ok(results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every job ran"))
+        .collect())
+}
+
+fn label(combination: &[(String, String)]) -> String {
+    combination
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn run_child(
+    executable: &PathBuf,
+    options: &Options,
+    combination: &[(String, String)],
+    seed: u64,
+    index: usize,
+) -> Result<RunResult, String> {
+    let output_path =
+        std::env::temp_dir().join(format!("elevator-{}-{}.json", std::process::id(), index));
+    let mut command = Command::new(executable);
+    command.arg("run");
+    if let Some(config) = &options.config {
+        command.args(["--config", config]);
+    }
+    if let Some(duration) = &options.duration {
+        command.args(["--duration", duration]);
+    }
+    for setting in &options.sets {
+        command.args(["--set", setting]);
+    }
+    for (key, value) in combination {
+        command.args(["--set", &format!("{}={}", key, value)]);
+    }
+    if options.strict {
+        command.arg("--strict");
+    }
+    command
+        .args(["--seed", &seed.to_string(), "--format", "json", "--output"])
+        .arg(&output_path)
+        .stdout(Stdio::null());
+
+    let label = label(combination);
+    let output = command
+        .output()
+        .map_err(|error| format!("cannot start simulation {}: {}", label, error))?;
+    if !output.status.success() {
+        return Err(format!(
+            "simulation {} failed: {}",
+            label,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let text = fs::read_to_string(&output_path)
+        .map_err(|error| format!("cannot read result of {}: {}", label, error))?;
+    let _ = fs::remove_file(&output_path);
+    let mut results: Vec<RunResult> = serde_json::from_str(&text)
+        .map_err(|error| format!("invalid result of {}: {}", label, error))?;
+    let mut result = results
+        .pop()
+        .ok_or_else(|| format!("empty result of {}", label))?;
+    result.label = label;
+    Ok(result)
+}
+
+fn write_results(format: Format, options: &Options, results: &[RunResult]) -> Result<(), String> {
+    let text = match format {
+        Format::Json => {
+            serde_json::to_string_pretty(results).map_err(|error| error.to_string())? + "\n"
+        }
+        Format::Csv => {
+            let mut text = format!("label,seed,error,{}\n", Summary::CSV_HEADER);
+            for result in results {
+                text.push_str(&format!(
+                    "{},{},{},{}\n",
+                    result.label,
+                    result.seed.map_or(String::new(), |seed| seed.to_string()),
+                    result
+                        .error
+                        .as_deref()
+                        .unwrap_or("")
+                        .replace([',', '\n'], " "),
+                    result
+                        .summary
+                        .as_ref()
+                        .map_or(String::new(), Summary::csv_row)
+                ));
+            }
+            text
+        }
+        Format::Text | Format::Events => unreachable!("text and events are written per run"),
+    };
+    write_output(options, text)
+}
+
+// Geht das maschinenlesbare Ergebnis nach stdout, schreiben die Akteure ihr Protokoll solange nach stderr;
+// beim Drop schreibt stdout wieder dorthin, wohin es vorher schrieb
+struct LogToStderr {
+    saved_stdout: i32,
+}
+
+impl LogToStderr {
+    fn start(options: &Options) -> Option<Self> {
+        if options.output.is_some() || options.format.unwrap_or(Format::Text) == Format::Text {
+            return None;
+        }
+        let _ = io::stdout().flush();
+        // SAFETY: Nur die Standard-Dateideskriptoren dieses Prozesses
+        let saved_stdout = unsafe {
+            let saved_stdout = libc::dup(libc::STDOUT_FILENO);
+            libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO);
+            saved_stdout
+        };
+        Some(Self { saved_stdout })
+    }
+}
+
+impl Drop for LogToStderr {
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+        // SAFETY: `saved_stdout` stammt aus `dup` und wird nur hier geschlossen
+        unsafe {
+            libc::dup2(self.saved_stdout, libc::STDOUT_FILENO);
+            libc::close(self.saved_stdout);
+        }
+    }
+}
+
+// Nach --output oder auf stdout
+fn write_output(options: &Options, text: String) -> Result<(), String> {
+    match &options.output {
+        Some(path) => {
+            fs::write(path, text).map_err(|error| format!("cannot write {}: {}", path, error))
+        }
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+fn print_table(results: &[RunResult]) {
+    println!(
+        "{:<40} {:>6} {:>10} {:>9} {:>9} {:>9} {:>10}",
+        "label", "seed", "completed", "abandoned", "wait", "journey", "energy"
+    );
+    for result in results {
+        let Some(summary) = &result.summary else {
+            println!(
+                "{:<40} {:>6} failed: {}",
+                result.label,
+                result.seed.map_or("-".to_string(), |seed| seed.to_string()),
+                result.error.as_deref().unwrap_or("no result")
+            );
+            continue;
+        };
+        println!(
+            "{:<40} {:>6} {:>10} {:>9} {:>9} {:>9} {:>10.4}",
+            result.label,
+            result.seed.map_or("-".to_string(), |seed| seed.to_string()),
+            format!("{}/{}", summary.completed, summary.passengers),
+            summary.abandoned,
+            format_secs(summary.average_wait_including_abandoned_secs),
+            format_secs(summary.average_journey_secs),
+            summary.energy_kwh
+        );
+    }
+}
+
+// Mittelwerte je Strategie über alle erfolgreichen Läufe
+fn print_comparison(strategies: &[String], results: &[RunResult]) {
+    println!("===== Comparison =====");
+    for strategy in strategies {
+        let label = strategy_settings(strategy)
+            .map(|settings| label(&settings))
+            .unwrap_or_default();
+        let runs: Vec<&Summary> = results
+            .iter()
+            .filter(|result| result.label == label)
+            .filter_map(|result| result.summary.as_ref())
+            .collect();
+        let mean = |values: Vec<f64>| {
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        };
+        println!(
+            "{}: {} run(s), completed {:.1}, abandoned {:.1}, wait {}, journey {}, energy {:.4} kWh",
+            strategy,
+            runs.len(),
+            mean(runs.iter().map(|s| s.completed as f64).collect()).unwrap_or(0.0),
+            mean(runs.iter().map(|s| s.abandoned as f64).collect()).unwrap_or(0.0),
+            format_secs(mean(
+                runs.iter()
+                    .filter_map(|s| s.average_wait_including_abandoned_secs)
+                    .collect()
+            )),
+            format_secs(mean(runs.iter().filter_map(|s| s.average_journey_secs).collect())),
+            mean(runs.iter().map(|s| s.energy_kwh).collect()).unwrap_or(0.0)
+        );
+    }
+}
+
+fn format_secs(secs: Option<f64>) -> String {
+    secs.map_or("-".to_string(), |secs| format!("{:.1}s", secs))
+}
@@ -0,0 +1,606 @@
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::ops::{Add, Sub};
+use std::panic;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::{self, Duration};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Select, TryRecvError};
+
+// Uhr der Simulation. Jeder Akteur ist ein Teilnehmer dieser Uhr: es rechnet immer nur einer, die übrigen
+// warten in `sleep`, `recv_timeout` oder `wait`. Kann keiner weiter, springt die Uhr zur nächsten Frist.
+// Die Reihenfolge hängt so weder von der Wanduhr noch vom Betriebssystem ab; gleiche Eingaben ergeben
+// denselben Ablauf, und acht Stunden simulierter Zeit dauern nur so lange, wie das Rechnen braucht.
+// Mit `set_speed` läuft die Uhr gebremst mit, z. B. in Echtzeit für Anzeige und Konsole.
+
+// Hält ein Teilnehmer die Uhr so lange an der Wanduhr gemessen fest, hängt er; er wird abgehängt.
+// Voreinstellung, bis `set_watchdog` eine andere Zeit setzt
+const WATCHDOG: Duration = Duration::from_secs(10);
+// Längste Wartezeit der Uhr an der Wanduhr, damit Eingaben von außen schnell ankommen
+const POLL: Duration = Duration::from_millis(20);
+
+// Zeitpunkt auf der Uhr der Simulation, gezählt ab dem Start des Prozesses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(Duration);
+
+impl Instant {
+    pub fn now() -> Self {
+        Self(now())
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        now().saturating_sub(self.0)
+    }
+
+    pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        Self(self.0.saturating_add(duration))
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.saturating_duration_since(earlier)
+    }
+}
+
+// Bedingung, auf die ein Teilnehmer wartet, z. B. eine Nachricht in einem Kanal
+pub type Condition = Box<dyn Fn() -> bool + Send>;
+
+// Beendet den Thread eines Teilnehmers, den die Uhr abgehängt hat oder der abgebrochen wurde, bei seinem
+// nächsten Warten
+pub struct Cancelled;
+
+// Ein mit `spawn` gestarteter Teilnehmer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Participant(u64);
+
+impl Participant {
+    // Der Teilnehmer endet bei seinem nächsten Warten: sein Thread wird mit `Cancelled` abgewickelt, während er
+    // an der Reihe ist, damit auch das Aufräumen in der festen Reihenfolge geschieht
+    pub fn cancel(self) {
+        let clock = clock();
+        let mut state = clock.lock();
+        if let Some(slot) = state.slots.get_mut(&self.0) {
+            slot.cancelled = true;
+        }
+        clock.driver.notify_one();
+    }
+}
+
+struct Wait {
+    conditions: Vec<Condition>,
+    until: Option<Duration>,
+}
+
+struct Slot {
+    name: String,
+    wait: Option<Wait>, // `None`, solange der Teilnehmer rechnet
+    turn: Arc<Condvar>,
+    cancelled: bool, // Siehe `Participant::cancel`
+}
+
+impl Slot {
+    fn ready(&self, now: Duration) -> bool {
+        self.wait.as_ref().is_some_and(|wait| {
+            self.cancelled
+                || wait.until.is_some_and(|until| until <= now)
+                || wait.conditions.iter().any(|condition| condition())
+        })
+    }
+}
+
+struct State {
+    now: Duration,
+    limit: Duration,    // Weiter läuft die Uhr nicht, siehe `run_until`
+    speed: Option<f64>, // Simulierte Sekunden je Sekunde Wanduhr, `None` = so schnell wie möglich
+    paused: bool,
+    step_to: Option<Duration>, // Während der Pause bis hierher weiterlaufen
+    slots: BTreeMap<u64, Slot>,
+    next_id: u64,
+    running: Option<u64>,
+    last: u64, // Zuletzt an der Reihe; die Uhr vergibt reihum, damit keiner die anderen aushungert
+    held_since: time::Instant,
+    watchdog: Option<Duration>, // `None` = nie abhängen, z. B. auf langsamen Rechnern
+    idle: bool,                 // Alles bis `limit` ist erledigt
+}
+
+impl State {
+    // Nächster Teilnehmer nach `last`, der weiter kann
+    fn next_ready(&self) -> Option<u64> {
+        let after = self.slots.range(self.last + 1..);
+        let before = self.slots.range(..=self.last);
+        after
+            .chain(before)
+            .find(|(_, slot)| slot.ready(self.now))
+            .map(|(&id, _)| id)
+    }
+
+    fn next_deadline(&self) -> Option<Duration> {
+        self.slots
+            .values()
+            .filter_map(|slot| slot.wait.as_ref()?.until)
+            .min()
+    }
+
+    fn start(&mut self, id: u64) {
+        let slot = self.slots.get_mut(&id).unwrap();
+        slot.wait = None;
+        slot.turn.notify_one();
+        self.running = Some(id);
+        self.last = id;
+        self.held_since = time::Instant::now();
+        self.idle = false;
+    }
+}
+
+struct Clock {
+    state: Mutex<State>,
+    driver: Condvar, // Weckt die Vergabe
+    idle: Condvar,   // Weckt `run_until`
+}
+
+impl Clock {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Vergibt die Uhr an den nächsten Teilnehmer oder stellt sie vor; läuft in einem eigenen Thread
+    fn drive(&self) {
+        let mut state = self.lock();
+        loop {
+            if let Some(running) = state.running {
+                state = self
+                    .driver
+                    .wait_timeout(state, POLL)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .0;
+                let hung = state
+                    .watchdog
+                    .is_some_and(|watchdog| state.held_since.elapsed() >= watchdog);
+                if state.running == Some(running) && hung {
+                    let slot = state.slots.remove(&running).unwrap();
+                    println!(
+                        "Clock: {} has been computing for {:.1}s without waiting, detaching it",
+                        slot.name,
+                        state.held_since.elapsed().as_secs_f64()
+                    );
+                    state.running = None;
+                }
+                continue;
+            }
+            if let Some(id) = state.next_ready() {
+                state.start(id);
+                continue;
+            }
+            // Niemand kann weiter: die Uhr bis zur nächsten Frist vorstellen, höchstens bis `limit`
+            // Ohne Frist und ohne Grenze wartet die Uhr auf Eingaben von außen
+            let target = match state.next_deadline() {
+                Some(deadline) => deadline.min(state.limit),
+                None if state.limit < Duration::MAX => state.limit,
+                None => state.now,
+            };
+            if state.now >= target || (state.paused && state.step_to.is_none()) {
+                if state.now >= state.limit && !state.idle {
+                    state.idle = true;
+                    self.idle.notify_all();
+                }
+                state = self
+                    .driver
+                    .wait_timeout(state, POLL)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .0;
+                continue;
+            }
+            match (state.step_to, state.speed) {
+                (Some(step_to), _) if state.paused => {
+                    state.now = target.min(step_to);
+                    if state.now >= step_to {
+                        state.step_to = None;
+                    }
+                }
+                (_, None) => state.now = target,
+                (_, Some(speed)) => {
+                    let from = state.now;
+                    let started = time::Instant::now();
+                    let real = (target - from).div_f64(speed).min(POLL);
+                    state = self
+                        .driver
+                        .wait_timeout(state, real)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .0;
+                    // Früher geweckt, z. B. durch eine Eingabe: nur so weit wie die Wanduhr
+                    state.now = (from + started.elapsed().mul_f64(speed)).min(target);
+                }
+            }
+        }
+    }
+}
+
+fn clock() -> &'static Clock {
+    static CLOCK: OnceLock<Clock> = OnceLock::new();
+    CLOCK.get_or_init(|| {
+        thread::Builder::new()
+            .name("Clock".to_string())
+            .spawn(|| clock().drive())
+            .expect("Failed to spawn clock thread");
+        Clock {
+            state: Mutex::new(State {
+                now: Duration::ZERO,
+                limit: Duration::MAX,
+                speed: None,
+                paused: false,
+                step_to: None,
+                slots: BTreeMap::new(),
+                next_id: 0,
+                running: None,
+                last: 0,
+                held_since: time::Instant::now(),
+                watchdog: Some(WATCHDOG),
+                idle: false,
+            }),
+            driver: Condvar::new(),
+            idle: Condvar::new(),
+        }
+    })
+}
+
+thread_local! {
+    static CURRENT: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+// Meldet einen Teilnehmer an, der sofort an die Reihe kommen darf
+fn register(name: String) -> u64 {
+    let clock = clock();
+    let mut state = clock.lock();
+    state.next_id += 1;
+    let id = state.next_id;
+    state.slots.insert(
+        id,
+        Slot {
+            name,
+            wait: Some(Wait {
+                conditions: vec![Box::new(|| true)],
+                until: None,
+            }),
+            turn: Arc::new(Condvar::new()),
+            cancelled: false,
+        },
+    );
+    clock.driver.notify_one();
+    id
+}
+
+// Wartet, bis `id` an der Reihe ist; ein abgehängter oder abgebrochener Teilnehmer wird abgewickelt
+fn await_turn(mut state: MutexGuard<'_, State>, id: u64) -> MutexGuard<'_, State> {
+    let turn = match state.slots.get(&id) {
+        Some(slot) => Arc::clone(&slot.turn),
+        None => {
+            // Abgehängt: der Thread läuft ohne die Uhr weiter, bis er endet
+            drop(state);
+            panic::resume_unwind(Box::new(Cancelled));
+        }
+    };
+    while state.running != Some(id) {
+        state = turn
+            .wait(state)
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+    }
+    if state.slots.get(&id).is_some_and(|slot| slot.cancelled) {
+        // Das Abwickeln hält die Uhr, bis der `Guard` fällt
+        drop(state);
+        panic::resume_unwind(Box::new(Cancelled));
+    }
+    state
+}
+
+// Teilnahme des aktuellen Threads; beim Drop, auch beim Abwickeln nach einem Panic, ist der nächste dran
+pub struct Guard {
+    id: u64,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let clock = clock();
+        let mut state = clock.lock();
+        state.slots.remove(&self.id);
+        if state.running == Some(self.id) {
+            state.running = None;
+        }
+        clock.driver.notify_one();
+        CURRENT.with(|current| current.set(None));
+    }
+}
+
+fn enter(id: u64) -> Guard {
+    CURRENT.with(|current| current.set(Some(id)));
+    let guard = Guard { id };
+    let clock = clock();
+    drop(await_turn(clock.lock(), id));
+    guard
+}
+
+// Der aktuelle Thread nimmt teil, bis der `Guard` fällt, z. B. während der Aufbau der Simulation Akteure startet
+pub fn join(name: &str) -> Guard {
+    enter(register(name.to_string()))
+}
+
+// Startet einen Thread als Teilnehmer; angemeldet wird er sofort, damit die Reihenfolge feststeht
+pub fn spawn<F>(name: String, body: F) -> Participant
+where
+    F: FnOnce() + Send + 'static,
+{
+    let id = register(name.clone());
+    thread::Builder::new()
+        .name(name)
+        .spawn(move || {
+            let _guard = enter(id);
+            body();
+        })
+        .expect("Failed to spawn actor thread");
+    Participant(id)
+}
+
+// Ob ein Panic nur das Abwickeln eines abgehängten oder abgebrochenen Teilnehmers war
+pub fn is_cancelled(payload: &(dyn Any + Send)) -> bool {
+    payload.is::<Cancelled>()
+}
+
+pub fn now() -> Duration {
+    clock().lock().now
+}
+
+// Gibt die Uhr ab und wartet, bis eine der Bedingungen gilt oder `until` erreicht ist. Threads, die nicht
+// teilnehmen (Konsole, Tests), nehmen für die Dauer des Wartens teil.
+pub fn wait(conditions: Vec<Condition>, until: Option<Instant>) {
+    let clock = clock();
+    let (id, temporary) = match CURRENT.with(Cell::get) {
+        Some(id) => (id, None),
+        None => {
+            let id = register(format!("{:?}", thread::current().id()));
+            (id, Some(Guard { id }))
+        }
+    };
+    let mut state = clock.lock();
+    match state.slots.get_mut(&id) {
+        Some(slot) => {
+            slot.wait = Some(Wait {
+                conditions,
+                until: until.map(|until| until.0),
+            })
+        }
+        None => {
+            drop(state);
+            panic::resume_unwind(Box::new(Cancelled));
+        }
+    }
+    if state.running == Some(id) {
+        state.running = None;
+    }
+    clock.driver.notify_one();
+    drop(await_turn(state, id));
+    drop(temporary);
+}
+
+// Bedingung: im Kanal liegt eine Nachricht oder alle Sender sind weg
+pub fn ready<T: Send + 'static>(receiver: &Receiver<T>) -> Condition {
+    let receiver = receiver.clone();
+    Box::new(move || {
+        let mut select = Select::new();
+        select.recv(&receiver);
+        select.try_ready().is_ok()
+    })
+}
+
+pub fn sleep(duration: Duration) {
+    sleep_until(Instant::now() + duration);
+}
+
+pub fn sleep_until(until: Instant) {
+    while Instant::now() < until {
+        wait(Vec::new(), Some(until));
+    }
+}
+
+pub fn recv_timeout<T: Send + 'static>(
+    receiver: &Receiver<T>,
+    timeout: Duration,
+) -> Result<T, RecvTimeoutError> {
+    recv_deadline(receiver, Instant::now() + timeout)
+}
+
+pub fn recv_deadline<T: Send + 'static>(
+    receiver: &Receiver<T>,
+    until: Instant,
+) -> Result<T, RecvTimeoutError> {
+    loop {
+        match receiver.try_recv() {
+            Ok(message) => return Ok(message),
+            Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+            Err(TryRecvError::Empty) if Instant::now() >= until => {
+                return Err(RecvTimeoutError::Timeout)
+            }
+            Err(TryRecvError::Empty) => wait(vec![ready(receiver)], Some(until)),
+        }
+    }
+}
+
+// Gangart für den nächsten Lauf: `speed` simulierte Sekunden je Sekunde Wanduhr, `None` so schnell wie
+// möglich. Bis `run_until` steht die Uhr bei ihrem Stand, damit der Aufbau vor dem ersten Schritt fertig ist.
+pub fn configure(speed: Option<f64>) {
+    let clock = clock();
+    let mut state = clock.lock();
+    state.speed = speed;
+    state.limit = state.now;
+    clock.driver.notify_one();
+}
+
+pub fn speed() -> Option<f64> {
+    clock().lock().speed
+}
+
+pub fn set_speed(speed: Option<f64>) {
+    let clock = clock();
+    clock.lock().speed = speed;
+    clock.driver.notify_one();
+}
+
+// Wanduhrzeit, nach der ein Teilnehmer abgehängt wird, der die Uhr hält, ohne zu warten; `None` nie
+pub fn set_watchdog(watchdog: Option<Duration>) {
+    let clock = clock();
+    clock.lock().watchdog = watchdog;
+    clock.driver.notify_one();
+}
+
+pub fn paused() -> bool {
+    clock().lock().paused
+}
+
+pub fn set_paused(paused: bool) {
+    let clock = clock();
+    let mut state = clock.lock();
+    state.paused = paused;
+    state.step_to = None;
+    clock.driver.notify_one();
+}
+
+// Während der Pause um `duration` weiterlaufen, so schnell wie möglich
+pub fn step(duration: Duration) {
+    let clock = clock();
+    let mut state = clock.lock();
+    state.step_to = Some(state.now + duration);
+    clock.driver.notify_one();
+}
+
+// Lässt die Uhr bis `until` laufen, ohne selbst teilzunehmen
+pub fn set_limit(until: Duration) {
+    let clock = clock();
+    let mut state = clock.lock();
+    if state.limit != until {
+        state.limit = until;
+        state.idle = false;
+    }
+    clock.driver.notify_one();
+}
+
+// Ist alles bis `until` erledigt? Danach rechnet keiner mehr, bis die Grenze steigt
+pub fn reached(until: Duration) -> bool {
+    let state = clock().lock();
+    state.idle && state.now >= until
+}
+
+// Lässt die Uhr bis `until` laufen und wartet, bis alles bis dahin erledigt ist; danach steht die Simulation
+// still und lässt sich z. B. für einen Snapshot auslesen
+pub fn run_until(until: Duration) {
+    set_limit(until);
+    let clock = clock();
+    let mut state = clock.lock();
+    while !(state.idle && state.now >= until) {
+        state = clock
+            .idle
+            .wait(state)
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam_channel::unbounded;
+
+    // Die Uhr gibt es einmal je Prozess; die Tests dürfen sich nicht gegenseitig die Reihenfolge verstellen
+    fn serial() -> MutexGuard<'static, ()> {
+        static SERIAL: Mutex<()> = Mutex::new(());
+        SERIAL
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    #[test]
+    fn sleepers_wake_in_order_of_their_deadlines_without_wall_clock_time() {
+        let _serial = serial();
+        let started = time::Instant::now();
+        let begin = Instant::now();
+        let (woke_tx, woke_rx) = unbounded();
+        for (name, hours) in [("Late", 2), ("Early", 1)] {
+            let woke_tx = woke_tx.clone();
+            spawn(name.to_string(), move || {
+                sleep(Duration::from_secs(hours * 3600));
+                let _ = woke_tx.send((name, Instant::now()));
+            });
+        }
+        drop(woke_tx);
+        let woke: Vec<(&str, Instant)> = woke_rx.iter().collect();
+        assert_eq!(
+            woke.iter().map(|&(name, _)| name).collect::<Vec<_>>(),
+            ["Early", "Late"]
+        );
+        assert!(woke[1].1 - begin >= Duration::from_secs(2 * 3600));
+        // Zwei Stunden simulierter Zeit brauchen nur das Rechnen
+        assert!(started.elapsed() < Duration::from_secs(60));
+    }
+
+    #[test]
+    fn watchdog_detaches_a_participant_that_never_waits() {
+        let _serial = serial();
+        set_watchdog(Some(Duration::from_millis(100)));
+        let (woke_tx, woke_rx) = unbounded();
+        spawn("Busy".to_string(), || thread::sleep(Duration::from_secs(3)));
+        spawn("Sleeper".to_string(), move || {
+            sleep(Duration::from_secs(1));
+            let _ = woke_tx.send(());
+        });
+        // Ohne Abhängen käme der Schläfer erst nach den drei Sekunden an der Wanduhr wieder dran
+        let woke = woke_rx.recv_timeout(Duration::from_secs(2));
+        set_watchdog(Some(WATCHDOG));
+        assert!(woke.is_ok());
+    }
+
+    #[test]
+    fn without_watchdog_a_long_computation_keeps_the_clock() {
+        let _serial = serial();
+        set_watchdog(None);
+        let (done_tx, done_rx) = unbounded();
+        let busy_tx = done_tx.clone();
+        spawn("Busy".to_string(), move || {
+            thread::sleep(Duration::from_millis(500));
+            let _ = busy_tx.send("Busy");
+        });
+        spawn("Sleeper".to_string(), move || {
+            sleep(Duration::from_millis(1));
+            let _ = done_tx.send("Sleeper");
+        });
+        let order: Vec<&str> = done_rx.iter().collect();
+        set_watchdog(Some(WATCHDOG));
+        assert_eq!(order, ["Busy", "Sleeper"]);
+    }
+
+    #[test]
+    fn cancelled_participant_ends_at_its_next_wait() {
+        let _serial = serial();
+        let (woke_tx, woke_rx) = unbounded();
+        let participant = spawn("Sleeper".to_string(), move || {
+            sleep(Duration::from_secs(3600));
+            let _ = woke_tx.send(());
+        });
+        participant.cancel();
+        // Beim Abwickeln fällt der Sender, lange bevor der Schläfer aufwachen würde
+        assert_eq!(
+            recv_timeout(&woke_rx, Duration::from_secs(60)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+}
@@ -0,0 +1,548 @@
+use std::fs;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::access::SecuredFloor;
+use crate::car::ElevatorSpec;
+use crate::faults::FaultInjection;
+use crate::freight::FreightPolicy;
+use crate::parking::{ParkingPolicy, TimeOfDayRule};
+use crate::patience::Patience;
+
+// Gebäude und Verkehr einer Simulation, z. B. aus `building.toml`.
+// Fehlende Einträge übernehmen die Werte des Beispielgebäudes (`Default`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimulationConfig {
+    pub floors: u8,
+    pub sky_lobby: u8, // Ausweichebene im Brandfall und Umstiegsebene der Zonen
+    pub elevators: Vec<ElevatorConfig>,
+    pub passengers: usize,
+    pub groups: usize,
+    pub group_size: usize,
+    #[serde(with = "duration_format")]
+    pub arrival_window: Duration, // Ankünfte gleichmäßig über diesen Zeitraum verteilt, 0s = alle beim Start
+    pub start_hour: u8,
+    #[serde(with = "duration_format")]
+    pub duration: Duration,
+    pub seed: Option<u64>, // Ohne Angabe wird ein zufälliger Startwert gewählt und ausgegeben
+    pub parking: String,   // Parkstrategie, siehe `parking_policy`
+    pub freight_policy: FreightPolicy,
+    pub patience: PatienceConfig,
+    #[serde(with = "duration_format")]
+    pub hall_call_limit: Duration, // Länger unbedient gilt ein Hallenruf als Verletzung, siehe `InvariantMonitor`
+    pub min_separation: u8, // Mindestabstand in Ebenen zwischen Wagen im selben Schacht, siehe `Shaft`
+    pub secured_floors: Vec<SecuredFloor>,
+    pub tenant_share: f64, // Anteil der einzeln ankommenden Passagiere mit Mieterausweis im erzeugten Verkehr
+    pub faults: Vec<FaultInjection>, // Fehlerskript, zusätzlich zu den Fehlern des Beispiels
+    #[serde(with = "duration_format")]
+    pub jitter: Duration, // Zufällige Verzögerung der Fahrgäste bis zu diesem Wert, für Stresstests
+    pub demo_script: bool, // Betriebsarten, Stromausfall, Brandschutzübung und Fehler des Beispiels einspielen
+    #[serde(with = "duration_format")]
+    pub watchdog: Duration, // Wanduhrzeit, nach der ein Akteur ohne Warten als hängend gilt, 0s = nie, siehe `clock`
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ElevatorConfig {
+    pub floors: Vec<u8>,
+    pub decks: u8,
+    pub freight: bool,
+    pub shaft: Option<usize>, // Fahrstühle mit derselben Nummer teilen sich einen Schacht (TWIN)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PatienceConfig {
+    pub stairs_floors: u8,
+    #[serde(with = "duration_format")]
+    pub stairs_after: Duration,
+    #[serde(with = "optional_duration_format")]
+    pub give_up_after: Option<Duration>,
+}
+
+impl Default for SimulationConfig {
+    // Das Beispielgebäude: Shuttle Lobby/Mezzanine <-> Sky-Lobby, darunter ein Lastenaufzug,
+    // darüber ein Lokalfahrstuhl im selben Schacht
+    fn default() -> Self {
+        let floors = 8;
+        let sky_lobby = 4;
+        Self {
+            floors,
+            sky_lobby,
+            elevators: vec![
+                ElevatorConfig {
+                    floors: vec![0, 1, sky_lobby, sky_lobby + 1],
+                    decks: 2,
+                    ..ElevatorConfig::default()
+                },
+                ElevatorConfig {
+                    floors: (0..=sky_lobby).collect(),
+                    freight: true,
+                    shaft: Some(0),
+                    ..ElevatorConfig::default()
+                },
+                ElevatorConfig {
+                    floors: (sky_lobby..floors).collect(),
+                    shaft: Some(0),
+                    ..ElevatorConfig::default()
+                },
+            ],
+            passengers: 2,
+            groups: 1,
+            group_size: 3,
+            arrival_window: Duration::ZERO,
+            start_hour: 8,
+            duration: Duration::from_secs(60),
+            seed: None,
+            parking: "time-of-day".to_string(),
+            freight_policy: FreightPolicy::Separate,
+            patience: PatienceConfig::default(),
+            hall_call_limit: Duration::from_secs(120),
+            min_separation: 1,
+            // Die oberste Etage nur für Mieter, die Etage darunter tagsüber frei
+            secured_floors: vec![
+                SecuredFloor {
+                    floor: floors - 1,
+                    badges: vec![TENANT_BADGE],
+                    open_hours: None,
+                },
+                SecuredFloor {
+                    floor: floors - 2,
+                    badges: vec![TENANT_BADGE],
+                    open_hours: Some((7, 19)),
+                },
+            ],
+            tenant_share: 0.5,
+            faults: Vec::new(),
+            jitter: Duration::ZERO,
+            demo_script: true,
+            watchdog: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Default for ElevatorConfig {
+    fn default() -> Self {
+        Self {
+            floors: Vec::new(),
+            decks: 1,
+            freight: false,
+            shaft: None,
+        }
+    }
+}
+
+// Für ein bis zwei Ebenen nach 20 Sekunden die Treppe, nach 45 Sekunden aufgeben
+impl Default for PatienceConfig {
+    fn default() -> Self {
+        Self {
+            stairs_floors: 2,
+            stairs_after: Duration::from_secs(20),
+            give_up_after: Some(Duration::from_secs(45)),
+        }
+    }
+}
+
+// Ausweis der Mieter; im erzeugten Verkehr haben ihn ein Anteil `tenant_share` und alle Gruppen
+pub const TENANT_BADGE: u32 = 1001;
+
+impl SimulationConfig {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|error| format!("cannot read {}: {}", path, error))?;
+        let config: Self =
+            toml::from_str(&text).map_err(|error| format!("invalid config {}: {}", path, error))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.elevators.is_empty() {
+            return Err("at least one elevator is required".to_string());
+        }
+        if self.sky_lobby >= self.floors {
+            return Err(format!(
+                "sky_lobby {} is not below floors {}",
+                self.sky_lobby, self.floors
+            ));
+        }
+        if self.start_hour >= 24 {
+            return Err(format!(
+                "start_hour {} is not a time of day, expected 0 to 23",
+                self.start_hour
+            ));
+        }
+        for secured in &self.secured_floors {
+            if secured.floor >= self.floors {
+                return Err(format!(
+                    "secured floor {} is outside the building",
+                    secured.floor
+                ));
+            }
+            if let Some((from, to)) = secured.open_hours {
+                if from >= 24 || to > 24 {
+                    return Err(format!(
+                        "open hours {}-{} of floor {} are not times of day",
+                        from, to, secured.floor
+                    ));
+                }
+            }
+        }
+        for (id, elevator) in self.elevators.iter().enumerate() {
+            if elevator.decks == 0 || elevator.floors.is_empty() {
+                return Err(format!(
+                    "elevator {} needs at least one deck and one floor",
+                    id
+                ));
+            }
+            if let Some(&floor) = elevator.floors.iter().find(|&&floor| floor >= self.floors) {
+                return Err(format!(
+                    "elevator {} serves floor {} outside the building",
+                    id, floor
+                ));
+            }
+        }
+        if !(0.0..=1.0).contains(&self.tenant_share) {
+            return Err(format!(
+                "tenant_share must be between 0 and 1, got {}",
+                self.tenant_share
+            ));
+        }
+        if self.min_separation == 0 {
+            return Err("min_separation must be at least 1 floor".to_string());
+        }
+        if let Some(injection) = self
+            .faults
+            .iter()
+            .find(|injection| injection.elevator >= self.elevators.len())
+        {
+            return Err(format!(
+                "fault for elevator {} which does not exist",
+                injection.elevator
+            ));
+        }
+        // Das Skript schaltet Fahrstuhl 1 und 2 um und schickt den VIP-Fahrstuhl in Ebene 6
+        if self.demo_script && (self.elevators.len() < 3 || self.floors < 7) {
+            return Err(
+                "demo_script needs at least 3 elevators and 7 floors, set demo_script = false"
+                    .to_string(),
+            );
+        }
+        self.parking_policy()?;
+        Ok(())
+    }
+
+    // Überschreibt einen Eintrag, z. B. `--set passengers=10` oder ein Wert einer Parameterreihe
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = |error: String| format!("invalid value '{}' for {}: {}", value, key, error);
+        match key {
+            "passengers" => {
+                self.passengers = value.parse().map_err(|e| invalid(format!("{}", e)))?
+            }
+            "groups" => self.groups = value.parse().map_err(|e| invalid(format!("{}", e)))?,
+            "group_size" => {
+                self.group_size = value.parse().map_err(|e| invalid(format!("{}", e)))?
+            }
+            "arrival_window" => self.arrival_window = parse_duration(value).map_err(invalid)?,
+            "start_hour" => {
+                self.start_hour = value.parse().map_err(|e| invalid(format!("{}", e)))?
+            }
+            "duration" => self.duration = parse_duration(value).map_err(invalid)?,
+            "seed" => self.seed = Some(value.parse().map_err(|e| invalid(format!("{}", e)))?),
+            "parking" => self.parking = value.to_string(),
+            "freight_policy" => {
+                self.freight_policy = match value {
+                    "mixed" => FreightPolicy::Mixed,
+                    "separate" => FreightPolicy::Separate,
+                    _ => return Err(invalid("expected mixed or separate".to_string())),
+                }
+            }
+            "hall_call_limit" => self.hall_call_limit = parse_duration(value).map_err(invalid)?,
+            "min_separation" => {
+                self.min_separation = value.parse().map_err(|e| invalid(format!("{}", e)))?
+            }
+            "tenant_share" => {
+                self.tenant_share = value.parse().map_err(|e| invalid(format!("{}", e)))?
+            }
+            "jitter" => self.jitter = parse_duration(value).map_err(invalid)?,
+            "demo_script" => {
+                self.demo_script = value.parse().map_err(|e| invalid(format!("{}", e)))?
+            }
+            "watchdog" => self.watchdog = parse_duration(value).map_err(invalid)?,
+            "patience.stairs_floors" => {
+                self.patience.stairs_floors =
+                    value.parse().map_err(|e| invalid(format!("{}", e)))?
+            }
+            "patience.stairs_after" => {
+                self.patience.stairs_after = parse_duration(value).map_err(invalid)?
+            }
+            "patience.give_up_after" => {
+                self.patience.give_up_after = match value {
+                    "never" => None,
+                    _ => Some(parse_duration(value).map_err(invalid)?),
+                }
+            }
+            _ => return Err(format!("unknown setting '{}'", key)),
+        }
+        self.validate()
+    }
+
+    pub fn specs(&self) -> Vec<ElevatorSpec> {
+        self.elevators
+            .iter()
+            .map(|elevator| match elevator.decks {
+                1 if elevator.freight => ElevatorSpec::freight_car(elevator.floors.clone()),
+                1 => ElevatorSpec::single_deck(elevator.floors.clone()),
+                decks => ElevatorSpec {
+                    decks,
+                    freight: elevator.freight,
+                    ..ElevatorSpec::double_deck(elevator.floors.clone())
+                },
+            })
+            .collect()
+    }
+
+    pub fn patience(&self) -> Patience {
+        Patience {
+            stairs_floors: self.patience.stairs_floors,
+            stairs_after: self.patience.stairs_after,
+            give_up_after: self.patience.give_up_after,
+        }
+    }
+
+    // Strategienamen: stay, lobby, floor:N, spread-evenly, predicted-demand und time-of-day
+    // (morgens zur Lobby, abends in die oberen Etagen, sonst gleichmäßig verteilen)
+    pub fn parking_policy(&self) -> Result<ParkingPolicy, String> {
+        match self.parking.as_str() {
+            "stay" => Ok(ParkingPolicy::Stay),
+            "lobby" => Ok(ParkingPolicy::Lobby),
+            "spread-evenly" => Ok(ParkingPolicy::SpreadEvenly),
+            "predicted-demand" => Ok(ParkingPolicy::PredictedDemand),
+            "time-of-day" => Ok(ParkingPolicy::TimeOfDay(
+                vec![
+                    TimeOfDayRule {
+                        from_hour: 7,
+                        to_hour: 10,
+                        policy: ParkingPolicy::Lobby,
+                    },
+                    TimeOfDayRule {
+                        from_hour: 16,
+                        to_hour: 19,
+                        policy: ParkingPolicy::Floor(self.floors - 1),
+                    },
+                ],
+                Box::new(ParkingPolicy::SpreadEvenly),
+            )),
+            name => match name.strip_prefix("floor:").map(str::parse::<u8>) {
+                Some(Ok(floor)) if floor < self.floors => Ok(ParkingPolicy::Floor(floor)),
+                _ => Err(format!("unknown parking strategy '{}'", name)),
+            },
+        }
+    }
+}
+
+// Zeitangaben wie "90s", "1.5s", "15m", "8h" oder "1h30m"; eine Zahl ohne Einheit sind Sekunden.
+// Bruchteile werden exakt bis auf Nanosekunden übernommen, damit `format_duration` sie wieder ausgibt.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    if text.is_empty() {
+        return Err("empty duration".to_string());
+    }
+    let mut total: u128 = 0; // Nanosekunden
+    let mut number = String::new();
+    let mut unit_seen = false;
+    for c in text.chars() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            '-' => return Err(format!("negative duration '{}'", text)),
+            _ => return Err(format!("unexpected '{}' in duration '{}'", c, text)),
+        };
+        total += decimal_nanos(&number, text)? * unit;
+        number.clear();
+        unit_seen = true;
+    }
+    if !number.is_empty() {
+        if unit_seen {
+            return Err(format!("missing unit at the end of duration '{}'", text));
+        }
+        total = decimal_nanos(&number, text)?;
+    }
+    let secs = u64::try_from(total / 1_000_000_000)
+        .map_err(|_| format!("duration '{}' is too long", text))?;
+    Ok(Duration::new(secs, (total % 1_000_000_000) as u32))
+}
+
+// Dezimalzahl wie "12" oder "0.25" in Nanosekunden, ohne Rundungsfehler einer Gleitkommazahl
+fn decimal_nanos(number: &str, text: &str) -> Result<u128, String> {
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(format!("missing number in duration '{}'", text));
+    }
+    if fraction.contains('.') {
+        return Err(format!(
+            "invalid number '{}' in duration '{}'",
+            number, text
+        ));
+    }
+    let whole: u128 = if whole.is_empty() {
+        0
+    } else {
+        whole
+            .parse()
+            .map_err(|_| format!("duration '{}' is too long", text))?
+    };
+    let digits: String = fraction
+        .chars()
+        .chain("000000000".chars())
+        .take(9)
+        .collect();
+    let nanos: u128 = digits.parse().unwrap_or(0);
+    Ok(whole * 1_000_000_000 + nanos)
+}
+
+// Gegenstück zu `parse_duration`: "1h30m", "45s", "0.3s"; Bruchteile von Sekunden gehen nicht verloren
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let nanos = duration.subsec_nanos();
+    let (hours, minutes, seconds) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    let mut text = String::new();
+    if hours > 0 {
+        text.push_str(&format!("{}h", hours));
+    }
+    if minutes > 0 {
+        text.push_str(&format!("{}m", minutes));
+    }
+    if nanos > 0 {
+        let fraction = format!("{:09}", nanos);
+        text.push_str(&format!("{}.{}s", seconds, fraction.trim_end_matches('0')));
+    } else if seconds > 0 || text.is_empty() {
+        text.push_str(&format!("{}s", seconds));
+    }
+    text
+}
+
+// Uhrzeit nach `elapsed` auf der Uhr einer Simulation, die um `start_hour` Uhr beginnt
+pub fn hour_of_day(start_hour: u8, elapsed: Duration) -> u8 {
+    ((start_hour as u64 + elapsed.as_secs() / 3600) % 24) as u8
+}
+
+pub mod duration_format {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::format_duration(*duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        super::parse_duration(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+// "never" heißt: keine Grenze
+mod optional_duration_format {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_str(&super::format_duration(*duration)),
+            None => serializer.serialize_str("never"),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "never" => Ok(None),
+            text => super::parse_duration(text)
+                .map(Some)
+                .map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units_and_plain_seconds() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("15m"), Ok(Duration::from_secs(900)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("12"), Ok(Duration::from_secs(12)));
+        assert_eq!(parse_duration("0.3s"), Ok(Duration::from_millis(300)));
+        assert_eq!(parse_duration("0.25"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("1.5m"), Ok(Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for text in ["", "inf", "NaN", "-5", "-1s", "5x", "1h30", "s", "1..2s"] {
+            assert!(parse_duration(text).is_err(), "accepted '{}'", text);
+        }
+    }
+
+    #[test]
+    fn formats_without_losing_fractions() {
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+        assert_eq!(format_duration(Duration::from_secs(90)), "1m30s");
+        assert_eq!(format_duration(Duration::from_secs(3600)), "1h");
+        assert_eq!(format_duration(Duration::from_secs(3661)), "1h1m1s");
+        assert_eq!(format_duration(Duration::from_millis(300)), "0.3s");
+        assert_eq!(format_duration(Duration::from_millis(61_250)), "1m1.25s");
+    }
+
+    #[test]
+    fn durations_round_trip() {
+        for duration in [
+            Duration::ZERO,
+            Duration::from_millis(1),
+            Duration::from_millis(300),
+            Duration::from_millis(499),
+            Duration::from_nanos(1_000_000_007),
+            Duration::from_secs(8 * 3600 + 59),
+        ] {
+            assert_eq!(parse_duration(&format_duration(duration)), Ok(duration));
+        }
+    }
+
+    #[test]
+    fn validate_checks_start_hour_secured_floors_and_separation() {
+        let mut config = SimulationConfig::default();
+        assert!(config.validate().is_ok());
+        config.start_hour = 24;
+        assert!(config.validate().is_err());
+
+        let mut config = SimulationConfig::default();
+        config.secured_floors[0].floor = config.floors;
+        assert!(config.validate().is_err());
+
+        let mut config = SimulationConfig::default();
+        assert!(config.set("min_separation", "0").is_err());
+        assert!(config.set("min_separation", "2").is_ok());
+    }
+
+    #[test]
+    fn hour_of_day_wraps_at_midnight() {
+        assert_eq!(hour_of_day(8, Duration::ZERO), 8);
+        assert_eq!(hour_of_day(8, Duration::from_secs(3599)), 8);
+        assert_eq!(hour_of_day(23, Duration::from_secs(3600)), 0);
+        assert_eq!(hour_of_day(22, Duration::from_secs(27 * 3600)), 1);
+    }
+}
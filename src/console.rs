@@ -0,0 +1,360 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+use crate::dashboard::describe_summary;
+use crate::faults::Fault;
+use crate::profile::PassengerProfile;
+use crate::protocol::{
+    self, ControlCommand, ElevatorCommand, ElevatorId, ElevatorStatus, FloorCommand, FloorId,
+};
+use crate::trace::Arrival;
+use crate::{plan_arrival, Direction, Simulation};
+
+const HELP: &str = "\
+Commands:
+  call FLOOR up|down [accessible]          Press a hall button
+  car ID press FLOOR                       Press a button inside elevator ID
+  fail ID [FAULT]                          Inject a fault: out-of-service (default), door-jam, stuck, slow:FACTOR
+  fire on [FLOOR] | fire off               Raise the fire alarm (default floor 0) or reset it
+  spawn N [passengers] FROM->TO [PROFILE]  Add passengers; a floor is a number, lobby, sky or * for random
+  status                                   Show cars, waiting passengers and running metrics
+  help                                     Show this help
+  quit                                     End the simulation and print the report";
+
+// Eingabe, die das System verändert, mit dem Zeitpunkt auf der Uhr der Simulation; steht im Snapshot und
+// wird beim Fortsetzen zu diesem Zeitpunkt erneut eingegeben. Hinzugefügte Passagiere stehen stattdessen
+// mit ihrer Ankunft im Verkehr.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsoleInput {
+    pub at: Duration,
+    pub line: String,
+}
+
+// Eingabe in der Konsole, bereits gegen das Gebäude geprüft
+#[derive(Debug)]
+enum ConsoleCommand {
+    Call {
+        floor: u8,
+        direction: Direction,
+        accessible: bool,
+    },
+    CarCall {
+        elevator: usize,
+        floor: u8,
+    },
+    Fail {
+        elevator: usize,
+        fault: Fault,
+    },
+    Fire(Option<u8>), // Alarm in Ebene x, `None` stellt ihn zurück
+    Spawn {
+        count: usize,
+        from: Option<u8>, // `None`: zufällige Ebene
+        to: Option<u8>,
+        profile: PassengerProfile,
+    },
+    Status,
+    Help,
+    Quit,
+}
+
+// Liest Befehle von stdin und schickt sie in das laufende System, bis `quit` oder das Ende der Eingabe
+pub fn run(simulation: &Simulation) {
+    println!("Console: type 'help' for commands");
+    clock::set_limit(Duration::MAX);
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match parse(simulation, line) {
+            Ok(ConsoleCommand::Quit) => break,
+            Ok(command) => {
+                // Der Befehl wirkt zwischen zwei Schritten der Akteure, wie jede andere Nachricht
+                let _turn = clock::join("Console");
+                if let Err(error) = execute(simulation, command, line) {
+                    println!("Console: {}", error);
+                }
+            }
+            Err(error) => println!("Console: {}", error),
+        }
+    }
+    // Der Bericht zeigt den Stand beim Beenden
+    clock::set_limit(clock::now());
+}
+
+// Gibt eine gespeicherte Eingabe erneut ein; die Uhr steht dabei bei `input.at`
+pub fn replay(simulation: &Simulation, input: &ConsoleInput) -> Result<(), String> {
+    let _turn = clock::join("Console");
+    execute(simulation, parse(simulation, &input.line)?, &input.line)
+}
+
+fn parse(simulation: &Simulation, line: &str) -> Result<ConsoleCommand, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let floor = |word: &str| -> Result<u8, String> {
+        let floor = match word {
+            "lobby" => 0,
+            "sky" | "sky-lobby" => simulation.sky_lobby,
+            _ => word
+                .parse()
+                .map_err(|_| format!("invalid floor '{}'", word))?,
+        };
+        if floor >= simulation.floors {
+            return Err(format!(
+                "floor {} does not exist, the building has {} floors",
+                floor, simulation.floors
+            ));
+        }
+        Ok(floor)
+    };
+    let elevator = |word: &str| -> Result<usize, String> {
+        let id: usize = word
+            .parse()
+            .map_err(|_| format!("invalid elevator '{}'", word))?;
+        if id >= simulation.elevator_senders.len() {
+            return Err(format!("elevator {} does not exist", id));
+        }
+        Ok(id)
+    };
+    let random_or_floor = |word: &str| match word {
+        "*" => Ok(None),
+        _ => floor(word).map(Some),
+    };
+
+    match words.as_slice() {
+        ["call", at, direction, rest @ ..] => Ok(ConsoleCommand::Call {
+            floor: floor(at)?,
+            direction: match *direction {
+                "up" => Direction::Up,
+                "down" => Direction::Down,
+                other => {
+                    return Err(format!(
+                        "invalid direction '{}', expected up or down",
+                        other
+                    ))
+                }
+            },
+            accessible: match rest {
+                [] => false,
+                ["accessible"] => true,
+                _ => return Err(format!("unexpected '{}'", rest.join(" "))),
+            },
+        }),
+        ["car", id, "press", at] => Ok(ConsoleCommand::CarCall {
+            elevator: elevator(id)?,
+            floor: floor(at)?,
+        }),
+        ["fail", id, rest @ ..] => Ok(ConsoleCommand::Fail {
+            elevator: elevator(id)?,
+            fault: match rest {
+                [] | ["out-of-service"] => Fault::OutOfService,
+                ["door-jam"] => Fault::DoorJam,
+                ["stuck"] => Fault::StuckBetweenFloors,
+                [slow] if slow.starts_with("slow:") => {
+                    let factor: f64 = slow["slow:".len()..]
+                        .parse()
+                        .map_err(|_| format!("invalid slow factor in '{}'", slow))?;
+                    if factor < 1.0 {
+                        return Err("the slow factor must be at least 1".to_string());
+                    }
+                    Fault::Slow(factor)
+                }
+                _ => return Err(format!("unknown fault '{}'", rest.join(" "))),
+            },
+        }),
+        ["fire", "on"] => Ok(ConsoleCommand::Fire(Some(0))),
+        ["fire", "on", at] => Ok(ConsoleCommand::Fire(Some(floor(at)?))),
+        ["fire", "off"] => Ok(ConsoleCommand::Fire(None)),
+        ["spawn", count, rest @ ..] => {
+            let count: usize = count
+                .parse()
+                .map_err(|_| format!("invalid count '{}'", count))?;
+            let rest = match rest {
+                ["passengers" | "passenger", rest @ ..] => rest,
+                rest => rest,
+            };
+            let (trip, profile) = match rest {
+                [trip] => (trip, PassengerProfile::Standard),
+                [trip, profile] => (trip, profile.parse()?),
+                _ => return Err("expected spawn N [passengers] FROM->TO [PROFILE]".to_string()),
+            };
+            let (from, to) = trip
+                .split_once("->")
+                .ok_or_else(|| format!("expected FROM->TO, got '{}'", trip))?;
+            Ok(ConsoleCommand::Spawn {
+                count,
+                from: random_or_floor(from)?,
+                to: random_or_floor(to)?,
+                profile,
+            })
+        }
+        ["status"] => Ok(ConsoleCommand::Status),
+        ["help"] => Ok(ConsoleCommand::Help),
+        ["quit" | "exit"] => Ok(ConsoleCommand::Quit),
+        _ => Err(format!(
+            "unknown command '{}', type 'help' for commands",
+            line
+        )),
+    }
+}
+
+// Führt den Befehl aus; was das System verändert, merkt sich die Simulation für den Snapshot
+fn execute(simulation: &Simulation, command: ConsoleCommand, line: &str) -> Result<(), String> {
+    let recorded = matches!(
+        command,
+        ConsoleCommand::Call { .. }
+            | ConsoleCommand::CarCall { .. }
+            | ConsoleCommand::Fail { .. }
+            | ConsoleCommand::Fire(_)
+    );
+    let at = simulation.metrics.lock().unwrap().clock();
+    send(simulation, command)?;
+    if recorded {
+        simulation
+            .console_inputs
+            .lock()
+            .unwrap()
+            .push(ConsoleInput {
+                at,
+                line: line.to_string(),
+            });
+    }
+    Ok(())
+}
+
+// Schickt den Befehl über dieselben Kanäle, die auch Passagiere, Fehlerskript und Brandmelder nutzen,
+// und wartet bei Befehlen auf die Bestätigung
+fn send(simulation: &Simulation, command: ConsoleCommand) -> Result<(), String> {
+    let unreachable = |what: &str| format!("{} is not reachable", what);
+    match command {
+        ConsoleCommand::Call {
+            floor,
+            direction,
+            accessible,
+        } => {
+            let transmitter = simulation.floor_transmitter.read().unwrap()[&floor].clone();
+            protocol::send_and_confirm(
+                &transmitter,
+                FloorCommand::Request {
+                    direction,
+                    destination: None,
+                    accessible,
+                    load: PassengerProfile::Standard,
+                },
+                &format!("floor {}", floor),
+            )?;
+            println!(
+                "Console: Hall call at floor {} going {:?}",
+                floor, direction
+            );
+        }
+        ConsoleCommand::CarCall { elevator, floor } => {
+            // Wie ein Fahrgast im Fahrkorb: das Ziel geht als Position des unteren Decks an die Steuerung
+            let spec = &simulation.specs[elevator];
+            let position = (0..spec.decks)
+                .find_map(|deck| spec.position_for(deck, floor))
+                .ok_or_else(|| format!("elevator {} cannot stop at floor {}", elevator, floor))?;
+            simulation
+                .status_tx
+                .send(ElevatorStatus::PassengerTarget(
+                    ElevatorId(elevator),
+                    vec![FloorId(position)],
+                ))
+                .map_err(|_| unreachable("the control system"))?;
+            simulation.dashboard.update(elevator, |car| {
+                car.car_buttons.insert(floor);
+            });
+            println!("Console: Pressed floor {} in Elevator {}", floor, elevator);
+        }
+        ConsoleCommand::Fail { elevator, fault } => {
+            protocol::send_and_confirm(
+                &simulation.elevator_senders[elevator],
+                ElevatorCommand::InjectFault(fault),
+                &format!("elevator {}", elevator),
+            )?;
+            println!("Console: Injecting {:?} into Elevator {}", fault, elevator);
+        }
+        ConsoleCommand::Fire(alarm) => {
+            let command = match alarm {
+                Some(floor) => ControlCommand::FireAlarm(FloorId(floor)),
+                None => ControlCommand::FireAlarmReset,
+            };
+            protocol::send_and_confirm(&simulation.control_tx, command, "the control system")?;
+        }
+        ConsoleCommand::Spawn {
+            count,
+            from,
+            to,
+            profile,
+        } => {
+            let mut rng = rand::thread_rng();
+            let now = simulation.metrics.lock().unwrap().clock();
+            for _ in 0..count {
+                // Zufällige Ebenen so lange ziehen, bis die Fahrt mit passenden Fahrstühlen möglich ist
+                let trip = (0..100).find_map(|_| {
+                    let origin = from.unwrap_or_else(|| rng.gen_range(0..simulation.floors));
+                    let target = to.unwrap_or_else(|| rng.gen_range(0..simulation.floors));
+                    let arrival = Arrival {
+                        at: now,
+                        origin,
+                        target,
+                        profile,
+                        group: None,
+                        badges: Vec::new(),
+                    };
+                    let legs =
+                        plan_arrival(&simulation.specs, &arrival).filter(|_| origin != target)?;
+                    Some((arrival, legs))
+                });
+                let Some((arrival, legs)) = trip else {
+                    return Err(format!("no {} trip possible between these floors", profile));
+                };
+                let id = simulation.add_passenger(&arrival, legs, None, Duration::ZERO);
+                println!(
+                    "Console: Passenger {} ({}) from floor {} to floor {}",
+                    id, profile, arrival.origin, arrival.target
+                );
+            }
+        }
+        ConsoleCommand::Status => {
+            for (id, car) in simulation.dashboard.cars().iter().enumerate() {
+                println!("{}", car.describe(id));
+            }
+            let (waiting, summary) = {
+                let metrics = simulation.metrics.lock().unwrap();
+                (metrics.waiting(), metrics.summary())
+            };
+            let mut per_floor: BTreeMap<u8, (usize, usize)> = BTreeMap::new();
+            for leg in waiting {
+                let (up, down) = per_floor.entry(leg.from).or_default();
+                match leg.direction() {
+                    Direction::Up => *up += 1,
+                    Direction::Down => *down += 1,
+                }
+            }
+            if per_floor.is_empty() {
+                println!("Waiting: nobody");
+            }
+            for (floor, (up, down)) in per_floor {
+                println!("Waiting at floor {}: {} up, {} down", floor, up, down);
+            }
+            println!("{}", describe_summary(&summary));
+        }
+        ConsoleCommand::Help => println!("{}", HELP),
+        ConsoleCommand::Quit => {}
+    }
+    Ok(())
+}
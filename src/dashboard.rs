@@ -0,0 +1,433 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::os::unix::io::FromRawFd;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::{cursor, execute, queue, terminal};
+use serde::{Deserialize, Serialize};
+
+use crate::car::ElevatorSpec;
+use crate::clock;
+use crate::invariants::Observation;
+use crate::metrics::{Metrics, Summary};
+use crate::modes::OperatingMode;
+use crate::profile::{PassengerProfile, DECK_CAPACITY};
+use crate::{Direction, DoorState};
+
+const LOG_LINES: usize = 200; // Aufbewahrte Protokollzeilen
+const CELL_WIDTH: usize = 15;
+const REFRESH: Duration = Duration::from_millis(250);
+const STEP: Duration = Duration::from_secs(1); // Einzelschritt in der Pause, Zeit der Simulation
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 64.0;
+
+// Zustand eines Fahrkorbs, wie ihn die Anzeige zeigt; der Fahrstuhl aktualisiert ihn selbst.
+// Ein Snapshot speichert ihn unverändert, beim Fortsetzen muss der erneut abgespielte Lauf ihn genau so erreichen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarView {
+    pub floor: u8, // Position des unteren Decks
+    pub decks: u8,
+    pub moving: Option<Direction>, // Nur während einer Fahrt
+    pub door: DoorState,
+    pub loads: Vec<u32>,                             // Belegter Platz je Deck
+    pub car_buttons: BTreeSet<u8>,                   // Leuchtende Zieltasten im Fahrkorb
+    pub riders: Vec<Vec<(usize, PassengerProfile)>>, // Fahrgäste je Deck
+    pub mode: OperatingMode,
+    pub powered: bool,
+    pub out_of_service: bool,
+    pub slow_factor: f64, // Fehler `Slow`, 1.0 im Normalbetrieb
+    pub jammed: bool,     // Tür klemmt (Fehler `DoorJam`)
+}
+
+impl CarView {
+    // Fahrtrichtung; im Stand die Richtung der nächsten Zieltaste
+    fn direction(&self) -> Option<Direction> {
+        self.moving.or_else(|| {
+            if self.car_buttons.iter().any(|&floor| floor > self.floor) {
+                Some(Direction::Up)
+            } else if self.car_buttons.iter().any(|&floor| floor < self.floor) {
+                Some(Direction::Down)
+            } else {
+                None
+            }
+        })
+    }
+
+    fn status(&self) -> String {
+        if self.out_of_service {
+            "out of service".to_string()
+        } else if !self.powered {
+            "no power".to_string()
+        } else {
+            format!("{:?}", self.mode)
+        }
+    }
+
+    // Eine Zeile je Fahrstuhl, auch für `status` in der Konsole
+    pub fn describe(&self, id: usize) -> String {
+        let buttons: Vec<String> = self.car_buttons.iter().map(u8::to_string).collect();
+        format!(
+            "E{}  floor {:<3} door {:<8} load {:>2}/{:<2} buttons [{}] {}",
+            id,
+            self.floor,
+            format!("{:?}", self.door),
+            self.loads.iter().sum::<u32>(),
+            DECK_CAPACITY * self.decks as u32,
+            buttons.join(" "),
+            self.status()
+        )
+    }
+}
+
+// Laufende Kennzahlen in einer Zeile
+pub fn describe_summary(summary: &Summary) -> String {
+    let secs = |value: Option<f64>| value.map_or("-".to_string(), |secs| format!("{:.1}s", secs));
+    format!(
+        "Passengers {}  arrived {}  gave up {}  stairs {}  avg wait {}  avg journey {}  refused {}  failures {}  denials {}  violations {}  energy {:.3} kWh",
+        summary.passengers,
+        summary.completed,
+        summary.abandoned,
+        summary.legs_by_stairs,
+        secs(summary.average_wait_secs),
+        secs(summary.average_journey_secs),
+        summary.refused_boardings,
+        summary.failures,
+        summary.access_denials,
+        summary.invariant_violations,
+        summary.energy_kwh
+    )
+}
+
+// Live-Anzeige für Vorführungen: jeder Schacht eine Spalte, daneben Ruftasten und Wartende je Ebene,
+// darunter die laufenden Kennzahlen und das Protokoll
+pub struct Dashboard {
+    floors: u8,
+    served_floors: Vec<Vec<u8>>,
+    columns: Vec<Vec<usize>>, // Fahrstühle je Schacht; TWIN-Wagen teilen sich eine Spalte
+    cars: Mutex<Vec<CarView>>,
+    log: Mutex<VecDeque<String>>,
+    observers: Mutex<Vec<Sender<Observation>>>, // Bekommen jeden geänderten Fahrkorb, z. B. der `InvariantMonitor`
+}
+
+impl Dashboard {
+    // `shafts`: gemeinsame Schachtnummer je Fahrstuhl, `None` für einen eigenen Schacht
+    pub fn new(floors: u8, specs: &[ElevatorSpec], shafts: &[Option<usize>]) -> Arc<Self> {
+        let mut columns: Vec<(Option<usize>, Vec<usize>)> = Vec::new();
+        for (id, shaft) in shafts.iter().enumerate() {
+            match columns
+                .iter_mut()
+                .find(|(column, _)| shaft.is_some() && column == shaft)
+            {
+                Some((_, ids)) => ids.push(id),
+                None => columns.push((*shaft, vec![id])),
+            }
+        }
+        Arc::new(Self {
+            floors,
+            served_floors: specs
+                .iter()
+                .map(|spec| spec.served_floors.clone())
+                .collect(),
+            columns: columns.into_iter().map(|(_, ids)| ids).collect(),
+            cars: Mutex::new(
+                specs
+                    .iter()
+                    .map(|spec| CarView {
+                        floor: 0,
+                        decks: spec.decks,
+                        moving: None,
+                        door: DoorState::Closed,
+                        loads: vec![0; spec.decks as usize],
+                        car_buttons: BTreeSet::new(),
+                        riders: vec![Vec::new(); spec.decks as usize],
+                        mode: OperatingMode::Automatic,
+                        powered: true,
+                        out_of_service: false,
+                        slow_factor: 1.0,
+                        jammed: false,
+                    })
+                    .collect(),
+            ),
+            log: Mutex::new(VecDeque::new()),
+            observers: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn cars(&self) -> Vec<CarView> {
+        self.cars.lock().unwrap().clone()
+    }
+
+    pub fn subscribe(&self, observer: Sender<Observation>) {
+        self.observers.lock().unwrap().push(observer);
+    }
+
+    // Die Beobachter bekommen den neuen Zustand, solange die Fahrkörbe noch gesperrt sind,
+    // so kommen die Änderungen aller Fahrstühle in der Reihenfolge an, in der sie geschahen
+    pub fn update(&self, elevator_id: usize, update: impl FnOnce(&mut CarView)) {
+        let mut cars = self.cars.lock().unwrap();
+        if let Some(car) = cars.get_mut(elevator_id) {
+            update(car);
+            self.observers.lock().unwrap().retain(|observer| {
+                observer
+                    .send(Observation::Car(elevator_id, car.clone()))
+                    .is_ok()
+            });
+        }
+    }
+
+    fn log(&self, line: String) {
+        let mut log = self.log.lock().unwrap();
+        if log.len() == LOG_LINES {
+            log.pop_front();
+        }
+        log.push_back(line);
+    }
+
+    // Lässt die Simulation bis `until` auf ihrer Uhr laufen und zeichnet dabei die Anzeige; `true`, wenn vorher
+    // `q` gedrückt wurde, die Uhr steht dann. Pause, Einzelschritt und Tempo wirken auf die Uhr der Simulation.
+    pub fn run(&self, metrics: &Mutex<Metrics>, until: Duration) -> Result<bool, String> {
+        let _terminal = TerminalGuard::enter()?;
+        let elapsed = || metrics.lock().unwrap().clock();
+        let at = clock::now() + until.saturating_sub(elapsed());
+        clock::set_limit(at);
+        while !clock::reached(at) {
+            self.draw(metrics, elapsed(), until)
+                .map_err(|error| format!("cannot draw the dashboard: {}", error))?;
+            if !event::poll(REFRESH).map_err(|error| error.to_string())? {
+                continue;
+            }
+            let speed = clock::speed().unwrap_or(1.0);
+            match event::read().map_err(|error| error.to_string())? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(quit()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Ok(quit())
+                    }
+                    KeyCode::Char(' ') | KeyCode::Char('p') => clock::set_paused(!clock::paused()),
+                    KeyCode::Char('n') => {
+                        clock::set_paused(true);
+                        clock::step(STEP);
+                    }
+                    KeyCode::Char('+') => clock::set_speed(Some((speed * 2.0).min(MAX_SPEED))),
+                    KeyCode::Char('-') => clock::set_speed(Some((speed / 2.0).max(MIN_SPEED))),
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        Ok(false)
+    }
+
+    fn draw(&self, metrics: &Mutex<Metrics>, elapsed: Duration, until: Duration) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let cars = self.cars();
+        let (waiting, summary) = {
+            let metrics = metrics.lock().unwrap();
+            (metrics.waiting(), metrics.summary())
+        };
+
+        let mut lines = vec![
+            format!(
+                "Elevator dashboard   {:.1}s / {:.1}s   {}   speed {}x",
+                elapsed.as_secs_f64(),
+                until.as_secs_f64(),
+                if clock::paused() { "PAUSED" } else { "running" },
+                clock::speed().unwrap_or(1.0)
+            ),
+            format!(
+                "space pause   n step {}s   + faster   - slower   q quit",
+                STEP.as_secs()
+            ),
+            String::new(),
+        ];
+
+        let mut header = format!("{:>5}  {:<4} {:>4} ", "Floor", "Hall", "Wait");
+        for column in &self.columns {
+            let name = column
+                .iter()
+                .map(|id| format!("E{}", id))
+                .collect::<Vec<_>>()
+                .join("+");
+            header.push_str(&format!("|{:^width$}", name, width = CELL_WIDTH));
+        }
+        lines.push(header);
+        for floor in (0..self.floors).rev() {
+            let waiting_here: Vec<Direction> = waiting
+                .iter()
+                .filter(|leg| leg.from == floor)
+                .map(|leg| leg.direction())
+                .collect();
+            let hall = format!(
+                "{}{}",
+                if waiting_here.contains(&Direction::Up) {
+                    "▲"
+                } else {
+                    " "
+                },
+                if waiting_here.contains(&Direction::Down) {
+                    "▼"
+                } else {
+                    " "
+                }
+            );
+            let mut line = format!(
+                "{:>5}  {:<4} {:>4} ",
+                floor,
+                hall,
+                if waiting_here.is_empty() {
+                    String::new()
+                } else {
+                    waiting_here.len().to_string()
+                }
+            );
+            for column in &self.columns {
+                line.push_str(&format!(
+                    "|{:^width$}",
+                    self.cell(&cars, column, floor),
+                    width = CELL_WIDTH
+                ));
+            }
+            lines.push(line);
+        }
+        lines.push(String::new());
+
+        lines.extend(cars.iter().enumerate().map(|(id, car)| car.describe(id)));
+        lines.push(describe_summary(&summary));
+        lines.push(String::new());
+
+        // Der restliche Platz gehört den neuesten Protokollzeilen
+        let room = (height as usize).saturating_sub(lines.len());
+        {
+            let log = self.log.lock().unwrap();
+            lines.extend(log.iter().skip(log.len().saturating_sub(room)).cloned());
+        }
+
+        let mut out = io::stderr();
+        for (row, line) in lines.iter().take(height as usize).enumerate() {
+            let line: String = line.chars().take(width as usize).collect();
+            queue!(
+                out,
+                cursor::MoveTo(0, row as u16),
+                Print(line),
+                terminal::Clear(terminal::ClearType::UntilNewLine)
+            )?;
+        }
+        queue!(out, terminal::Clear(terminal::ClearType::FromCursorDown))?;
+        out.flush()
+    }
+
+    // Ein Feld der Schachtspalte: Fahrkorb mit Tür, Last und Richtung, sonst der Schacht mit leuchtenden Zieltasten
+    fn cell(&self, cars: &[CarView], column: &[usize], floor: u8) -> String {
+        for &id in column {
+            let car = &cars[id];
+            if floor >= car.floor && floor < car.floor + car.decks {
+                let door = match car.door {
+                    DoorState::Closed => "[|]",
+                    DoorState::Opening => "<|>",
+                    DoorState::Open => "[ ]",
+                    DoorState::Closing => ">|<",
+                };
+                let arrow = match car.direction() {
+                    Some(Direction::Up) => "▲",
+                    Some(Direction::Down) => "▼",
+                    None => " ",
+                };
+                let load = car.loads[(floor - car.floor) as usize];
+                return format!("{} {} {}/{} {}", id, door, load, DECK_CAPACITY, arrow);
+            }
+        }
+        let lit: Vec<String> = column
+            .iter()
+            .filter(|&&id| cars[id].car_buttons.contains(&floor))
+            .map(|id| format!("●{}", id))
+            .collect();
+        if !lit.is_empty() {
+            lit.join(" ")
+        } else if column
+            .iter()
+            .any(|&id| self.served_floors[id].contains(&floor))
+        {
+            "·".to_string()
+        } else {
+            String::new()
+        }
+    }
+}
+
+// Leitet die Protokollausgabe (stdout) in die Anzeige um; beim Drop schreibt stdout wieder ins Terminal
+pub struct LogCapture {
+    saved_stdout: i32,
+}
+
+impl LogCapture {
+    pub fn start(dashboard: &Arc<Dashboard>) -> Result<Self, String> {
+        if !io::stderr().is_terminal() {
+            return Err("the dashboard needs a terminal".to_string());
+        }
+        let _ = io::stdout().flush();
+        let mut fds = [0; 2];
+        // SAFETY: Nur Dateideskriptoren dieses Prozesses; das Leseende gehört danach allein `reader`
+        let (saved_stdout, reader) = unsafe {
+            if libc::pipe(fds.as_mut_ptr()) != 0 {
+                return Err(format!(
+                    "cannot capture the log: {}",
+                    io::Error::last_os_error()
+                ));
+            }
+            let saved_stdout = libc::dup(libc::STDOUT_FILENO);
+            libc::dup2(fds[1], libc::STDOUT_FILENO);
+            libc::close(fds[1]);
+            (saved_stdout, File::from_raw_fd(fds[0]))
+        };
+        let dashboard = Arc::clone(dashboard);
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines().map_while(Result::ok) {
+                dashboard.log(line);
+            }
+        });
+        Ok(Self { saved_stdout })
+    }
+}
+
+impl Drop for LogCapture {
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+        // SAFETY: `saved_stdout` stammt aus `dup` und wird nur hier geschlossen
+        unsafe {
+            libc::dup2(self.saved_stdout, libc::STDOUT_FILENO);
+            libc::close(self.saved_stdout);
+        }
+    }
+}
+
+// Hält die Uhr der Simulation an, damit der Bericht den Stand beim Beenden zeigt
+fn quit() -> bool {
+    clock::set_limit(clock::now());
+    true
+}
+
+// Rohmodus und eigener Bildschirm, solange die Anzeige läuft; auch bei einem Fehler wiederhergestellt
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> Result<Self, String> {
+        terminal::enable_raw_mode()
+            .map_err(|error| format!("cannot start the dashboard: {}", error))?;
+        execute!(io::stderr(), terminal::EnterAlternateScreen, cursor::Hide)
+            .map_err(|error| format!("cannot start the dashboard: {}", error))?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stderr(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+const GRAVITY: f64 = 9.81; // m/s²
+const JOULES_PER_KWH: f64 = 3_600_000.0;
+
+// Verbrauch und Rückspeisung einer einzelnen Fahrt
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TripEnergy {
+    pub consumed_kwh: f64,
+    pub regenerated_kwh: f64,
+}
+
+impl TripEnergy {
+    pub fn net_kwh(&self) -> f64 {
+        self.consumed_kwh - self.regenerated_kwh
+    }
+}
+
+// Physikalisches Modell eines Fahrstuhls für den Energieverbrauch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnergyModel {
+    pub car_mass_kg: f64,
+    pub counterweight_kg: f64, // Üblich: Leergewicht + 40-50 % der Nennlast
+    pub passenger_mass_kg: f64,
+    pub floor_height_m: f64,
+    pub speed_m_s: f64,
+    pub drive_efficiency: f64, // Wirkungsgrad von Motor, Getriebe und Umrichter
+    pub friction: f64,         // Fahrwiderstand als Anteil der bewegten Gewichtskraft
+    pub standby_power_w: f64,  // Licht, Lüftung und Steuerung im Stillstand
+    pub regeneration: Option<f64>, // Anteil der Bremsenergie, der ins Netz zurückgespeist wird
+}
+
+impl Default for EnergyModel {
+    fn default() -> Self {
+        Self {
+            car_mass_kg: 1200.0,
+            counterweight_kg: 1500.0,
+            passenger_mass_kg: 75.0,
+            floor_height_m: 3.5,
+            speed_m_s: 2.5,
+            drive_efficiency: 0.8,
+            friction: 0.02,
+            standby_power_w: 250.0,
+            regeneration: Some(0.35),
+        }
+    }
+}
+
+impl EnergyModel {
+    // Zwei Kabinen übereinander: doppeltes Leergewicht, doppelte Nennlast
+    pub fn double_deck() -> Self {
+        Self {
+            car_mass_kg: 2400.0,
+            counterweight_kg: 3000.0,
+            standby_power_w: 400.0,
+            ..Self::default()
+        }
+    }
+
+    // Energie einer Fahrt, abhängig von Beladung, Richtung und Strecke
+    pub fn trip(&self, from: u8, to: u8, passengers: usize) -> TripEnergy {
+        if from == to {
+            return TripEnergy::default();
+        }
+        let distance = (to as f64 - from as f64).abs() * self.floor_height_m;
+        let load = self.car_mass_kg + passengers as f64 * self.passenger_mass_kg;
+        let moving_mass = load + self.counterweight_kg;
+
+        // Schwere Kabine aufwärts oder leichte Kabine abwärts braucht Energie, sonst wird gebremst
+        let imbalance = load - self.counterweight_kg;
+        let lift = if to > from { imbalance } else { -imbalance };
+        let potential = lift * GRAVITY * distance;
+
+        let friction = self.friction * moving_mass * GRAVITY * distance;
+        let acceleration = 0.5 * moving_mass * self.speed_m_s * self.speed_m_s;
+
+        let mut consumed = (friction + acceleration) / self.drive_efficiency;
+        let mut regenerated = 0.0;
+        if potential > 0.0 {
+            consumed += potential / self.drive_efficiency;
+        } else if let Some(share) = self.regeneration {
+            regenerated = -potential * self.drive_efficiency * share;
+        }
+        TripEnergy {
+            consumed_kwh: consumed / JOULES_PER_KWH,
+            regenerated_kwh: regenerated / JOULES_PER_KWH,
+        }
+    }
+
+    // Reine Fahrzeit mit Nenngeschwindigkeit, ohne Anfahren und Bremsen
+    pub fn travel_time(&self, from: u8, to: u8) -> Duration {
+        let distance = (to as f64 - from as f64).abs() * self.floor_height_m;
+        Duration::from_secs_f64(distance / self.speed_m_s)
+    }
+
+    pub fn standby_kwh(&self, duration: Duration) -> f64 {
+        self.standby_power_w * duration.as_secs_f64() / JOULES_PER_KWH
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_trip_without_distance_costs_nothing() {
+        let trip = EnergyModel::default().trip(4, 4, 8);
+        assert_eq!(trip.consumed_kwh, 0.0);
+        assert_eq!(trip.regenerated_kwh, 0.0);
+        assert_eq!(EnergyModel::default().travel_time(4, 4), Duration::ZERO);
+    }
+
+    #[test]
+    fn heavy_cars_consume_upwards_and_regenerate_downwards() {
+        let model = EnergyModel::default();
+        let up = model.trip(0, 10, 12);
+        let down = model.trip(10, 0, 12);
+        assert!(up.consumed_kwh > down.consumed_kwh);
+        assert_eq!(up.regenerated_kwh, 0.0);
+        assert!(down.regenerated_kwh > 0.0);
+        assert!(down.net_kwh() < up.net_kwh());
+
+        // Ein leerer Fahrkorb ist leichter als das Gegengewicht, aufwärts wird gebremst
+        assert!(model.trip(0, 10, 0).regenerated_kwh > 0.0);
+
+        let without = EnergyModel {
+            regeneration: None,
+            ..model
+        };
+        assert_eq!(without.trip(10, 0, 12).regenerated_kwh, 0.0);
+    }
+
+    #[test]
+    fn travel_time_follows_distance_and_speed() {
+        let model = EnergyModel::default();
+        assert_eq!(model.travel_time(0, 5), Duration::from_secs(7));
+        assert_eq!(model.travel_time(5, 0), model.travel_time(0, 5));
+        assert!(model.travel_time(0, 10) > model.travel_time(0, 5));
+    }
+}
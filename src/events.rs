@@ -0,0 +1,178 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::access::AccessDenied;
+use crate::faults::Fault;
+use crate::invariants::Violation;
+use crate::journey::Leg;
+use crate::patience::Abandonment;
+use crate::profile::PassengerProfile;
+
+// Eintrag im Ereignisprotokoll eines Laufs; `Metrics` hält ihn mit der Zeit seit Start der Simulation fest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    JourneyStarted {
+        passenger: usize,
+        from: u8,
+        to: u8,
+        profile: PassengerProfile,
+        legs: usize,
+    },
+    LegRequested {
+        passenger: usize,
+        leg: Leg,
+    },
+    Boarded {
+        passenger: usize,
+        elevator: usize,
+        deck: String,
+    },
+    LegCompleted {
+        passenger: usize,
+        floor: u8,
+    },
+    LegAbandoned {
+        passenger: usize,
+        abandonment: Abandonment,
+    },
+    LegInterrupted {
+        passenger: usize,
+        floor: u8,
+    },
+    JourneyReplanned {
+        passenger: usize,
+        remaining_legs: usize,
+    },
+    Trip {
+        elevator: usize,
+        from: u8,
+        to: u8,
+    },
+    ElevatorFailed {
+        elevator: usize,
+        fault: Fault,
+        floor: u8,
+        passengers: Vec<usize>,
+    },
+    AccessDenied {
+        passenger: usize,
+        elevator: usize,
+        target: u8,
+        reason: AccessDenied,
+    },
+    GroupSplit {
+        group: usize,
+    },
+    Power {
+        message: String,
+    },
+    InvariantViolated(Violation),
+    ActorStalled {
+        actor: String,
+    },
+}
+
+// Wem ein Ereignis zugeordnet ist. Jeder Akteur meldet seine Ereignisse der Reihe nach, zwischen den
+// Akteuren hängt die Reihenfolge vom Scheduler ab; ein nach `Subject` sortiertes Protokoll ist daher
+// von Lauf zu Lauf vergleichbar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Subject {
+    Building, // Notstrom, von der Steuerung gemeldet
+    Elevator(usize),
+    Group(usize),
+    Passenger(usize),
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subject::Building => write!(f, "building"),
+            Subject::Elevator(id) => write!(f, "elevator {}", id),
+            Subject::Group(id) => write!(f, "group {}", id),
+            Subject::Passenger(id) => write!(f, "passenger {}", id),
+        }
+    }
+}
+
+impl Event {
+    pub fn subject(&self) -> Subject {
+        match self {
+            Event::JourneyStarted { passenger, .. }
+            | Event::LegRequested { passenger, .. }
+            | Event::Boarded { passenger, .. }
+            | Event::LegCompleted { passenger, .. }
+            | Event::LegAbandoned { passenger, .. }
+            | Event::LegInterrupted { passenger, .. }
+            | Event::JourneyReplanned { passenger, .. } => Subject::Passenger(*passenger),
+            // Ausfälle und abgewiesene Fahrbefehle meldet der Fahrstuhl
+            Event::Trip { elevator, .. }
+            | Event::ElevatorFailed { elevator, .. }
+            | Event::AccessDenied { elevator, .. } => Subject::Elevator(*elevator),
+            Event::GroupSplit { group } => Subject::Group(*group),
+            // Hänger meldet der Supervisor, nicht der Akteur selbst
+            Event::Power { .. } | Event::ActorStalled { .. } => Subject::Building,
+            Event::InvariantViolated(violation) => violation.subject(),
+        }
+    }
+}
+
+// Beschreibung ohne den Betroffenen, siehe `Subject`
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::JourneyStarted {
+                from,
+                to,
+                profile,
+                legs,
+                ..
+            } => write!(
+                f,
+                "journey {} -> {} ({}), {} leg(s)",
+                from, to, profile, legs
+            ),
+            Event::LegRequested { leg, .. } => write!(f, "requests {} -> {}", leg.from, leg.to),
+            Event::Boarded { elevator, deck, .. } => {
+                write!(f, "boards elevator {} ({})", elevator, deck)
+            }
+            Event::LegCompleted { floor, .. } => write!(f, "reaches floor {}", floor),
+            Event::LegAbandoned { abandonment, .. } => match abandonment {
+                Abandonment::Stairs => write!(f, "takes the stairs"),
+                Abandonment::GaveUp => write!(f, "gives up"),
+            },
+            Event::LegInterrupted { floor, .. } => write!(f, "leaves the car at floor {}", floor),
+            Event::JourneyReplanned { remaining_legs, .. } => {
+                write!(f, "replans, {} leg(s) left", remaining_legs)
+            }
+            Event::Trip { from, to, .. } => write!(f, "trip {} -> {}", from, to),
+            Event::ElevatorFailed {
+                fault,
+                floor,
+                passengers,
+                ..
+            } => write!(
+                f,
+                "fails with {:?} at floor {}, passengers {:?}",
+                fault, floor, passengers
+            ),
+            Event::AccessDenied {
+                passenger,
+                target,
+                reason,
+                ..
+            } => write!(
+                f,
+                "denies passenger {} floor {} ({})",
+                passenger, target, reason
+            ),
+            Event::GroupSplit { .. } => write!(f, "splits"),
+            Event::Power { message } => write!(f, "{}", message),
+            Event::InvariantViolated(violation) => {
+                write!(f, "violates an invariant: {}", violation)
+            }
+            Event::ActorStalled { actor } => write!(f, "{} stalled, no heartbeat", actor),
+        }
+    }
+}
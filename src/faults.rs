@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{self, Instant};
+use crate::protocol::{self, ElevatorCommand, Request};
+
+// Fehler, die sich gezielt in einen Fahrstuhl einspielen lassen
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Fault {
+    OutOfService,       // Fahrstuhl wird abgeschaltet, der Thread beendet sich
+    DoorJam,            // Tür lässt sich nicht mehr schließen
+    StuckBetweenFloors, // Bleibt bei der nächsten Fahrt zwischen zwei Ebenen stecken
+    Slow(f64),          // Fahrten dauern um diesen Faktor länger
+}
+
+// Ein Eintrag im Fehlerskript: nach `after` bekommt `elevator` den Fehler `fault`,
+// in der Konfiguration z. B. `{ after = "10s", elevator = 1, fault = "DoorJam" }`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaultInjection {
+    #[serde(with = "crate::config::duration_format")]
+    pub after: Duration,
+    pub elevator: usize,
+    pub fault: Fault,
+}
+
+// Spielt die Fehler zeitgesteuert über die normalen Befehlskanäle ein
+pub fn spawn_injector(
+    mut script: Vec<FaultInjection>,
+    elevators: Vec<Sender<Request<ElevatorCommand>>>,
+) {
+    script.sort_by_key(|injection| injection.after);
+    clock::spawn("Fault injector".to_string(), move || {
+        let start = Instant::now();
+        for injection in script {
+            clock::sleep(injection.after.saturating_sub(start.elapsed()));
+            println!(
+                "Fault injector: Injecting {:?} into Elevator {}",
+                injection.fault, injection.elevator
+            );
+            let recipient = format!("Elevator {}", injection.elevator);
+            let delivered = match elevators.get(injection.elevator) {
+                Some(elevator) => protocol::send_and_confirm(
+                    elevator,
+                    ElevatorCommand::InjectFault(injection.fault),
+                    &recipient,
+                ),
+                None => Err(format!("{} is not reachable", recipient)),
+            };
+            if let Err(problem) = delivered {
+                println!("Fault injector: {}", problem);
+            }
+        }
+    });
+}
@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use crate::car::ElevatorSpec;
+use crate::protocol::{ElevatorId, FloorId};
+
+// Rückrufebenen für den Brandfall (Phase I)
+#[derive(Debug, Clone)]
+pub struct FireServiceConfig {
+    pub designated_floor: u8, // Hauptrückrufebene, üblicherweise die Lobby
+    pub alternate_floor: u8, // Ausweichebene, wenn der Alarm in der Hauptrückrufebene ausgelöst wurde
+}
+
+impl FireServiceConfig {
+    // Rückrufebene eines Fahrstuhls. Liegt sie außerhalb seiner Zone, fährt er den nächstgelegenen
+    // Halt an; Halte, an denen ein Deck vor der Alarmebene stünde, scheiden aus.
+    pub fn recall_floor(&self, spec: &ElevatorSpec, alarm_floor: u8) -> Option<u8> {
+        let target = if alarm_floor == self.designated_floor {
+            self.alternate_floor
+        } else {
+            self.designated_floor
+        };
+        spec.stops()
+            .into_iter()
+            .filter(|&stop| !(stop..stop + spec.decks).contains(&alarm_floor))
+            .min_by_key(|&stop| (stop as i32 - target as i32).abs())
+    }
+}
+
+// Taster im Fahrkorb, die nur in Phase II wirken
+#[derive(Debug, Clone, Copy)]
+pub enum DoorButton {
+    Open,
+    Close,
+}
+
+// Bedienung durch die Feuerwehr (Phase II)
+#[derive(Debug, Clone, Copy)]
+pub enum FirefighterInput {
+    KeyOn(ElevatorId),          // Feuerwehrschalter im Fahrkorb einschalten
+    KeyOff(ElevatorId),         // Ausschalten, der Fahrstuhl kehrt zur Rückrufebene zurück
+    CarCall(FloorId),           // Zielebene, wird ohne Zwischenhalt angefahren
+    Door(DoorButton, Duration), // Taster wird so lange gedrückt gehalten
+}
+
+// Zustand des Brandfallbetriebs in der Steuerung
+#[derive(Clone)]
+pub struct FireRecall {
+    pub alarm_floor: u8,
+    pub recall_floors: Vec<Option<u8>>, // Rückrufebene je Fahrstuhl
+    pub recalled: Vec<bool>, // Phase I abgeschlossen: steht mit offener Tür in der Rückrufebene
+    pub firefighter: Option<usize>, // Fahrstuhl, den die Feuerwehr in Phase II bedient
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+use crate::car::ElevatorSpec;
+use crate::profile::PassengerProfile;
+
+// Ob Lasten (Roboter, Transportwagen, Betten) zusammen mit Fahrgästen fahren dürfen
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FreightPolicy {
+    Mixed,    // Lasten und Fahrgäste teilen sich den Fahrkorb, solange Platz ist
+    Separate, // Lasten fahren nie zusammen mit Fahrgästen
+}
+
+impl FreightPolicy {
+    // Darf `load` zu den Fahrgästen und Lasten `riders` in den Fahrkorb einsteigen?
+    pub fn may_board(&self, load: PassengerProfile, riders: &[PassengerProfile]) -> bool {
+        riders.iter().all(|rider| {
+            if load.exclusive() || rider.exclusive() {
+                return false;
+            }
+            *self == FreightPolicy::Mixed || load.is_freight() == rider.is_freight()
+        })
+    }
+
+    // Der Ruf darf nur einem leeren Fahrkorb zugeteilt werden, wenn es sich einrichten lässt
+    pub fn prefers_empty_car(&self, load: PassengerProfile) -> bool {
+        load.exclusive() || (*self == FreightPolicy::Separate && load.is_freight())
+    }
+}
+
+// Transportwagen und Betten passen nur in einen Lastenaufzug
+pub fn car_accepts(spec: &ElevatorSpec, load: PassengerProfile) -> bool {
+    spec.freight || !load.needs_freight_car()
+}
@@ -0,0 +1,295 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::config::SimulationConfig;
+
+const CONTEXT_LINES: usize = 2;
+
+// Regressionstest gegen gespeicherte Läufe. Ein Szenario ist eine Konfiguration `NAME.toml` mit festem
+// Startwert, optional mit einem Fahrtenprotokoll `NAME.csv` statt des zufälligen Verkehrs. `NAME.golden`
+// enthält Ereignisprotokoll und Kennzahlen eines akzeptierten Laufs, wie sie `--format events` ausgibt.
+pub struct Scenario {
+    name: String,
+    config: PathBuf,
+    trace: Option<PathBuf>,
+    golden: PathBuf,
+}
+
+// Alle Szenarien in `dir` oder nur die mit den angegebenen Namen
+pub fn discover(dir: &Path, names: &[String]) -> Result<Vec<Scenario>, String> {
+    let mut names = names.to_vec();
+    if names.is_empty() {
+        let entries = fs::read_dir(dir)
+            .map_err(|error| format!("cannot read {}: {}", dir.display(), error))?;
+        for entry in entries {
+            let path = entry.map_err(|error| error.to_string())?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "toml")
+            {
+                if let Some(stem) = path.file_stem() {
+                    names.push(stem.to_string_lossy().into_owned());
+                }
+            }
+        }
+        names.sort();
+    }
+    if names.is_empty() {
+        return Err(format!("no scenarios in {}", dir.display()));
+    }
+
+    let mut scenarios = Vec::new();
+    for name in names {
+        let config = dir.join(format!("{}.toml", name));
+        let trace = Some(dir.join(format!("{}.csv", name))).filter(|trace| trace.exists());
+        let loaded = SimulationConfig::load(&config.to_string_lossy())
+            .map_err(|error| format!("scenario {}: {}", name, error))?;
+        if trace.is_none() && loaded.seed.is_none() {
+            return Err(format!(
+                "scenario {}: needs a fixed seed or a trace {}.csv",
+                name, name
+            ));
+        }
+        scenarios.push(Scenario {
+            golden: dir.join(format!("{}.golden", name)),
+            name,
+            config,
+            trace,
+        });
+    }
+    Ok(scenarios)
+}
+
+// Führt die Szenarien aus und vergleicht sie mit ihren Golden-Dateien; `accept` schreibt stattdessen
+// die neuen Ergebnisse. Wie bei `batch` läuft jedes Szenario als eigener Prozess, immer mit `--strict`:
+// verletzt ein Lauf eine Sicherheitsregel oder kommt ein Passagier bis zum Ende nicht an, schlägt das
+// Szenario fehl, auch beim Akzeptieren.
+pub fn run(scenarios: &[Scenario], accept: bool, jobs: usize) -> Result<(), String> {
+    let executable =
+        std::env::current_exe().map_err(|error| format!("cannot find executable: {}", error))?;
+    eprintln!("Golden: running {} scenario(s)", scenarios.len());
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<String, String>>>> =
+        Mutex::new(vec![None; scenarios.len()]);
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(scenarios.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(scenario) = scenarios.get(index) else {
+                    break;
+                };
+                let result = run_scenario(&executable, scenario, index);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    let mut failed = 0;
+    for (scenario, result) in scenarios.iter().zip(results.into_inner().unwrap()) {
+        let actual = match result.expect("every scenario ran") {
+            Ok(actual) => actual,
+            Err(error) => {
+                println!("Golden: {} failed to run: {}", scenario.name, error);
+                failed += 1;
+                continue;
+            }
+        };
+        let unfinished = unfinished(&actual);
+        if unfinished > 0 {
+            println!(
+                "Golden: {} leaves {} passenger(s) unfinished, give them more time or fix the run",
+                scenario.name, unfinished
+            );
+            failed += 1;
+            continue;
+        }
+        if accept {
+            fs::write(&scenario.golden, &actual).map_err(|error| {
+                format!("cannot write {}: {}", scenario.golden.display(), error)
+            })?;
+            println!(
+                "Golden: {} accepted into {}",
+                scenario.name,
+                scenario.golden.display()
+            );
+            continue;
+        }
+        let Ok(expected) = fs::read_to_string(&scenario.golden) else {
+            println!(
+                "Golden: {} has no golden file {}, run with --accept to create it",
+                scenario.name,
+                scenario.golden.display()
+            );
+            failed += 1;
+            continue;
+        };
+        match diff(&expected, &actual) {
+            None => println!("Golden: {} ok", scenario.name),
+            Some(diff) => {
+                println!(
+                    "Golden: {} differs from {} (- expected, + actual)",
+                    scenario.name,
+                    scenario.golden.display()
+                );
+                print!("{}", diff);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!(
+            "{} of {} scenario(s) failed, rerun with --accept if the new behavior is intended",
+            failed,
+            scenarios.len()
+        ));
+    }
+    Ok(())
+}
+
+fn run_scenario(executable: &PathBuf, scenario: &Scenario, index: usize) -> Result<String, String> {
+    let output_path = std::env::temp_dir().join(format!(
+        "elevator-golden-{}-{}.txt",
+        std::process::id(),
+        index
+    ));
+    let mut command = Command::new(executable);
+    match &scenario.trace {
+        Some(trace) => command.arg("replay").arg(trace),
+        None => command.arg("run"),
+    };
+    command
+        .arg("--config")
+        .arg(&scenario.config)
+        .args(["--strict", "--format", "events", "--output"])
+        .arg(&output_path)
+        .stdout(Stdio::null());
+    let output = command
+        .output()
+        .map_err(|error| format!("cannot start: {}", error))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    let text = fs::read_to_string(&output_path)
+        .map_err(|error| format!("cannot read result: {}", error))?;
+    let _ = fs::remove_file(&output_path);
+    Ok(text)
+}
+
+// Passagiere, die weder angekommen sind noch aufgegeben haben, laut der Zeile `unfinished N` der Kennzahlen
+fn unfinished(report: &str) -> usize {
+    report
+        .lines()
+        .find_map(|line| line.strip_prefix("unfinished "))
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(0)
+}
+
+// Zeilenweiser Vergleich wie `diff -u`; `None`, wenn beide übereinstimmen. Die Uhr der Simulation macht
+// jeden Lauf eines Szenarios gleich, auch die Zeitangaben müssen daher genau stimmen.
+pub fn diff(expected: &str, actual: &str) -> Option<String> {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    // Längste gemeinsame Teilfolge, von hinten aufgebaut
+    let mut common = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let mut lines: Vec<(char, usize, &str)> = Vec::new(); // Markierung, Zeile in der Golden-Datei, Text
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            lines.push((' ', i, actual[j]));
+            i += 1;
+            j += 1;
+        } else if i < expected.len() && (j == actual.len() || common[i + 1][j] >= common[i][j + 1])
+        {
+            lines.push(('-', i, expected[i]));
+            i += 1;
+        } else {
+            lines.push(('+', i, actual[j]));
+            j += 1;
+        }
+    }
+    if lines.iter().all(|&(mark, _, _)| mark == ' ') {
+        return None;
+    }
+
+    // Nur die geänderten Zeilen mit etwas Kontext ausgeben, überlappende Abschnitte zusammengefasst
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for index in (0..lines.len()).filter(|&index| lines[index].0 != ' ') {
+        let from = index.saturating_sub(CONTEXT_LINES);
+        let to = (index + CONTEXT_LINES + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some((_, end)) if from <= *end => *end = to,
+            _ => hunks.push((from, to)),
+        }
+    }
+    let mut text = String::new();
+    for (from, to) in hunks {
+        text.push_str(&format!("@@ golden line {} @@\n", lines[from].1 + 1));
+        for &(mark, _, line) in &lines[from..to] {
+            text.push_str(&format!("{}{}\n", mark, line));
+        }
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = "===== Events =====\n    5.8s  elevator 1: trip 0 -> 3\n   13.2s  elevator 1: trip 3 -> 4\n===== Summary =====\npassengers 2\ncompleted 2\nabandoned 0\nunfinished 0\n";
+
+    #[test]
+    fn identical_runs_have_no_diff() {
+        assert_eq!(diff(REPORT, REPORT), None);
+        assert_eq!(diff("", ""), None);
+    }
+
+    #[test]
+    fn any_change_of_a_time_is_reported() {
+        let later = REPORT.replace("13.2s", "13.3s");
+        assert_eq!(
+            diff(REPORT, &later).unwrap(),
+            "@@ golden line 1 @@\n \
+             ===== Events =====\n \
+             \x20   5.8s  elevator 1: trip 0 -> 3\n\
+             -   13.2s  elevator 1: trip 3 -> 4\n\
+             +   13.3s  elevator 1: trip 3 -> 4\n \
+             ===== Summary =====\n \
+             passengers 2\n"
+        );
+    }
+
+    #[test]
+    fn distant_changes_get_their_own_hunks() {
+        let actual = REPORT
+            .replace("5.8s", "5.9s")
+            .replace("unfinished 0", "unfinished 1");
+        let text = diff(REPORT, &actual).unwrap();
+        assert_eq!(text.matches("@@ golden line").count(), 2);
+        assert!(text.contains("@@ golden line 6 @@\n"));
+        assert!(text.contains("-unfinished 0\n+unfinished 1\n"));
+    }
+
+    #[test]
+    fn counts_unfinished_passengers() {
+        assert_eq!(unfinished(REPORT), 0);
+        assert_eq!(
+            unfinished(&REPORT.replace("unfinished 0", "unfinished 3")),
+            3
+        );
+        assert_eq!(unfinished("===== Summary =====\npassengers 2\n"), 0);
+    }
+}
@@ -0,0 +1,36 @@
+use std::sync::{Arc, Mutex};
+
+use crate::profile::PassengerProfile;
+
+// Fahrgäste, die gemeinsam ankommen (z. B. zu einer Besprechung oder zur Mittagspause)
+// und denselben Fahrkorb nehmen wollen. Der erste wartende Fahrgast ruft den Fahrstuhl
+// und steigt für alle ein; wer keinen Platz mehr findet, fährt allein weiter.
+pub struct Group {
+    pub id: usize,
+    waiting: Mutex<Vec<(usize, PassengerProfile)>>, // Mitglieder, die noch in der Startebene warten
+}
+
+impl Group {
+    pub fn new(id: usize, members: Vec<(usize, PassengerProfile)>) -> Arc<Self> {
+        Arc::new(Self {
+            id,
+            waiting: Mutex::new(members),
+        })
+    }
+
+    pub fn leader(&self) -> Option<usize> {
+        self.waiting.lock().unwrap().first().map(|&(id, _)| id)
+    }
+
+    pub fn waiting(&self) -> Vec<(usize, PassengerProfile)> {
+        self.waiting.lock().unwrap().clone()
+    }
+
+    // Eingestiegen, abgespalten oder aufgegeben: der nächste wartende Fahrgast wird Anführer
+    pub fn leave(&self, passenger_id: usize) {
+        self.waiting
+            .lock()
+            .unwrap()
+            .retain(|&(id, _)| id != passenger_id);
+    }
+}
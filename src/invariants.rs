@@ -0,0 +1,333 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crossbeam_channel::{Receiver, RecvTimeoutError};
+use serde::{Deserialize, Serialize};
+
+use crate::car::ElevatorSpec;
+use crate::clock;
+use crate::dashboard::CarView;
+use crate::events::Subject;
+use crate::metrics::Metrics;
+use crate::profile::DECK_CAPACITY;
+use crate::shaft::too_close;
+use crate::DoorState;
+
+const HALL_CALL_CHECK: Duration = Duration::from_secs(1);
+
+// Was die Prüfung zu sehen bekommt. Die Fahrkörbe meldet die Anzeige nach jeder Änderung, alles andere
+// melden die Akteure selbst; alles läuft durch einen Kanal, damit die Reihenfolge erhalten bleibt.
+pub enum Observation {
+    Car(usize, CarView),
+    Exited {
+        passenger: usize,
+        elevator: usize,
+        floor: u8,
+    }, // Passagier steigt aus, Ebene aus seiner Sicht
+    MoveRefused {
+        elevator: usize,
+        from: u8,
+        to: u8,
+    }, // Fahrbefehl, während die Tür offen ist
+}
+
+// Verletzte Sicherheitsregel; jede wird einmal gemeldet, wenn sie eintritt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "invariant", rename_all = "kebab-case")]
+pub enum Violation {
+    MovedWithDoor {
+        elevator: usize,
+        from: u8,
+        to: u8,
+        door: DoorState,
+    },
+    DoorBetweenFloors {
+        elevator: usize,
+        floor: u8,
+        door: DoorState,
+    }, // Tür nicht geschlossen während der Fahrt
+    MoveWithOpenDoor {
+        elevator: usize,
+        from: u8,
+        to: u8,
+    },
+    Overloaded {
+        elevator: usize,
+        deck: u8,
+        load: u32,
+    },
+    OutsideZone {
+        elevator: usize,
+        floor: u8,
+    },
+    ShaftOverlap {
+        elevator: usize,
+        other: usize,
+        floor: u8,
+    },
+    ExitedAway {
+        passenger: usize,
+        elevator: usize,
+        floor: u8,
+        car_floor: u8,
+    },
+    HallCallUnserved {
+        passenger: usize,
+        floor: u8,
+        limit: Duration,
+    },
+}
+
+impl Violation {
+    pub fn subject(&self) -> Subject {
+        match self {
+            Violation::MovedWithDoor { elevator, .. }
+            | Violation::DoorBetweenFloors { elevator, .. }
+            | Violation::MoveWithOpenDoor { elevator, .. }
+            | Violation::Overloaded { elevator, .. }
+            | Violation::OutsideZone { elevator, .. }
+            | Violation::ShaftOverlap { elevator, .. } => Subject::Elevator(*elevator),
+            Violation::ExitedAway { passenger, .. }
+            | Violation::HallCallUnserved { passenger, .. } => Subject::Passenger(*passenger),
+        }
+    }
+}
+
+// Beschreibung ohne den Betroffenen, wie bei `Event`
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::MovedWithDoor { from, to, door, .. } => {
+                write!(
+                    f,
+                    "moved from floor {} to floor {} with door {:?}",
+                    from, to, door
+                )
+            }
+            Violation::DoorBetweenFloors { floor, door, .. } => {
+                write!(f, "door {:?} while moving away from floor {}", door, floor)
+            }
+            Violation::MoveWithOpenDoor { from, to, .. } => {
+                write!(
+                    f,
+                    "ordered from floor {} to floor {} with the door open",
+                    from, to
+                )
+            }
+            Violation::Overloaded { deck, load, .. } => {
+                write!(
+                    f,
+                    "deck {} carries load {} above capacity {}",
+                    deck, load, DECK_CAPACITY
+                )
+            }
+            Violation::OutsideZone { floor, .. } => {
+                write!(f, "stands at floor {} outside its zone", floor)
+            }
+            Violation::ShaftOverlap { other, floor, .. } => {
+                write!(
+                    f,
+                    "overlaps elevator {} in the shaft at floor {}",
+                    other, floor
+                )
+            }
+            Violation::ExitedAway {
+                elevator,
+                floor,
+                car_floor,
+                ..
+            } => write!(
+                f,
+                "exits elevator {} at floor {} while the car is at floor {}",
+                elevator, floor, car_floor
+            ),
+            Violation::HallCallUnserved { floor, limit, .. } => {
+                write!(
+                    f,
+                    "hall call at floor {} not served within {:.0}s",
+                    floor,
+                    limit.as_secs_f64()
+                )
+            }
+        }
+    }
+}
+
+// Prüft die Sicherheitsregeln neben der laufenden Simulation. Verletzungen werden protokolliert und in den
+// Kennzahlen gezählt; mit `--strict` endet der Lauf dann mit einem Fehler.
+pub struct InvariantMonitor {
+    specs: Vec<ElevatorSpec>,
+    shafts: Vec<Option<usize>>, // Gemeinsame Schachtnummer je Fahrstuhl
+    min_separation: u8,         // Wie `Shaft::min_separation`
+    hall_call_limit: Duration, // Länger wartet niemand, der nicht vorher die Treppe nimmt oder aufgibt
+    cars: Vec<Option<CarView>>, // Letzter gemeldeter Zustand, `None` bis zur ersten Meldung
+    reported_calls: HashSet<(usize, u8)>, // Passagier, Startebene
+    metrics: Arc<Mutex<Metrics>>,
+}
+
+impl InvariantMonitor {
+    pub fn spawn(
+        specs: Vec<ElevatorSpec>,
+        shafts: Vec<Option<usize>>,
+        min_separation: u8,
+        hall_call_limit: Duration,
+        metrics: Arc<Mutex<Metrics>>,
+        observations: Receiver<Observation>,
+    ) {
+        let mut monitor = Self {
+            cars: vec![None; specs.len()],
+            specs,
+            shafts,
+            min_separation,
+            hall_call_limit,
+            reported_calls: HashSet::new(),
+            metrics,
+        };
+        clock::spawn("Invariant monitor".to_string(), move || loop {
+            match clock::recv_timeout(&observations, HALL_CALL_CHECK) {
+                Ok(observation) => monitor.observe(observation),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            monitor.check_hall_calls();
+        });
+    }
+
+    fn observe(&mut self, observation: Observation) {
+        match observation {
+            Observation::Car(elevator, car) => self.check_car(elevator, car),
+            Observation::Exited {
+                passenger,
+                elevator,
+                floor,
+            } => {
+                let Some(car) = self.cars.get(elevator).cloned().flatten() else {
+                    return;
+                };
+                if car.moving.is_some() || !(car.floor..car.floor + car.decks).contains(&floor) {
+                    self.violated(Violation::ExitedAway {
+                        passenger,
+                        elevator,
+                        floor,
+                        car_floor: car.floor,
+                    });
+                }
+            }
+            Observation::MoveRefused { elevator, from, to } => {
+                self.violated(Violation::MoveWithOpenDoor { elevator, from, to })
+            }
+        }
+    }
+
+    // Vergleicht den neuen Zustand mit dem vorherigen; gemeldet wird nur, was neu verletzt ist
+    fn check_car(&mut self, elevator: usize, car: CarView) {
+        let Some(previous) = self.cars.get(elevator).cloned() else {
+            return;
+        };
+        let mut violations = Vec::new();
+        if let Some(previous) = &previous {
+            if car.floor != previous.floor {
+                let door = [previous.door, car.door]
+                    .into_iter()
+                    .find(|&door| door != DoorState::Closed);
+                if let Some(door) = door {
+                    violations.push(Violation::MovedWithDoor {
+                        elevator,
+                        from: previous.floor,
+                        to: car.floor,
+                        door,
+                    });
+                }
+            }
+        }
+        let was = |check: &dyn Fn(&CarView) -> bool| previous.as_ref().is_some_and(check);
+
+        let door_while_moving =
+            |car: &CarView| car.moving.is_some() && car.door != DoorState::Closed;
+        if door_while_moving(&car) && !was(&door_while_moving) {
+            violations.push(Violation::DoorBetweenFloors {
+                elevator,
+                floor: car.floor,
+                door: car.door,
+            });
+        }
+        for deck in 0..car.decks as usize {
+            let overloaded = |car: &CarView| {
+                car.loads
+                    .get(deck)
+                    .is_some_and(|&load| load > DECK_CAPACITY)
+                    && car.riders.get(deck).is_some_and(|riders| riders.len() > 1)
+                // Ein leeres Deck nimmt jeden auf
+            };
+            if overloaded(&car) && !was(&overloaded) {
+                violations.push(Violation::Overloaded {
+                    elevator,
+                    deck: deck as u8,
+                    load: car.loads[deck],
+                });
+            }
+        }
+        let spec = &self.specs[elevator];
+        let outside = |car: &CarView| car.moving.is_none() && !spec.can_stop(car.floor);
+        if outside(&car) && !was(&outside) {
+            violations.push(Violation::OutsideZone {
+                elevator,
+                floor: car.floor,
+            });
+        }
+
+        // Bereiche der Wagen im selben Schacht; während der Fahrt zählt die Ebene, von der der Wagen losfuhr
+        if let Some(shaft) = self.shafts[elevator] {
+            for (other, other_car) in self.cars.iter().enumerate() {
+                if other == elevator || self.shafts[other] != Some(shaft) {
+                    continue;
+                }
+                let Some(other_car) = other_car else {
+                    continue;
+                };
+                let overlaps = |car: &CarView| {
+                    too_close(
+                        car.floor,
+                        car.floor + car.decks.saturating_sub(1),
+                        other_car.floor,
+                        other_car.floor + other_car.decks.saturating_sub(1),
+                        self.min_separation,
+                    )
+                };
+                if overlaps(&car) && !was(&overlaps) {
+                    violations.push(Violation::ShaftOverlap {
+                        elevator,
+                        other,
+                        floor: car.floor,
+                    });
+                }
+            }
+        }
+
+        self.cars[elevator] = Some(car);
+        for violation in violations {
+            self.violated(violation);
+        }
+    }
+
+    // Hallenrufe, auf die ein Passagier länger als `hall_call_limit` wartet
+    fn check_hall_calls(&mut self) {
+        let waiting = self.metrics.lock().unwrap().waiting_since();
+        for (passenger, leg, waited) in waiting {
+            if waited > self.hall_call_limit && self.reported_calls.insert((passenger, leg.from)) {
+                self.violated(Violation::HallCallUnserved {
+                    passenger,
+                    floor: leg.from,
+                    limit: self.hall_call_limit,
+                });
+            }
+        }
+    }
+
+    fn violated(&self, violation: Violation) {
+        println!("Invariant monitor: {}: {}", violation.subject(), violation);
+        self.metrics.lock().unwrap().invariant_violated(violation);
+    }
+}
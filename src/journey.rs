@@ -0,0 +1,132 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::car::ElevatorSpec;
+use crate::Direction;
+
+// Ein Teilstück einer Fahrt, das mit genau einem Fahrstuhl zurückgelegt wird
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Leg {
+    pub from: u8,
+    pub to: u8,
+}
+
+impl Leg {
+    pub fn direction(&self) -> Direction {
+        if self.to > self.from {
+            Direction::Up
+        } else {
+            Direction::Down
+        }
+    }
+}
+
+// Prüft, ob ein Fahrstuhl von `floor` aus in Richtung `direction` noch eine Ebene bedient
+pub fn serves_direction(served_floors: &[u8], floor: u8, direction: Direction) -> bool {
+    served_floors.contains(&floor)
+        && served_floors.iter().any(|&f| match direction {
+            Direction::Up => f > floor,
+            Direction::Down => f < floor,
+        })
+}
+
+// Plant die Fahrt mit möglichst wenigen Umstiegen (Breitensuche über die Zonen der Fahrstühle).
+// Jeder Fahrstuhl verbindet die Ebenen, die dasselbe Deck erreicht, z. B. Shuttle Lobby <-> Sky-Lobby.
+pub fn plan_journey(from: u8, to: u8, specs: &[ElevatorSpec]) -> Option<Vec<Leg>> {
+    if from == to {
+        return Some(Vec::new());
+    }
+
+    let mut previous: HashMap<u8, u8> = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    previous.insert(from, from);
+
+    while let Some(floor) = queue.pop_front() {
+        if floor == to {
+            break;
+        }
+        for spec in specs
+            .iter()
+            .filter(|spec| spec.served_floors.contains(&floor))
+        {
+            for &next in &spec.served_floors {
+                if spec.deck_between(floor, next).is_none() {
+                    continue;
+                }
+                if let Entry::Vacant(entry) = previous.entry(next) {
+                    entry.insert(floor);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    if !previous.contains_key(&to) {
+        return None;
+    }
+
+    // Weg rückwärts vom Ziel zum Start zusammensetzen
+    let mut legs = Vec::new();
+    let mut floor = to;
+    while floor != from {
+        let before = previous[&floor];
+        legs.push(Leg {
+            from: before,
+            to: floor,
+        });
+        floor = before;
+    }
+    legs.reverse();
+    Some(legs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plans_direct_trips_and_transfers() {
+        let local = ElevatorSpec::single_deck((0..10).collect());
+        let alone = std::slice::from_ref(&local);
+        assert_eq!(plan_journey(3, 3, alone), Some(Vec::new()));
+        assert_eq!(
+            plan_journey(2, 7, alone),
+            Some(vec![Leg { from: 2, to: 7 }])
+        );
+
+        // Shuttle zur Sky-Lobby, dort Umstieg in die obere Zone
+        let shuttle = ElevatorSpec::single_deck(vec![0, 10]);
+        let upper = ElevatorSpec::single_deck((10..20).collect());
+        assert_eq!(
+            plan_journey(0, 15, &[local.clone(), shuttle.clone(), upper.clone()]),
+            Some(vec![Leg { from: 0, to: 10 }, Leg { from: 10, to: 15 }])
+        );
+        assert_eq!(
+            plan_journey(15, 4, &[local.clone(), shuttle, upper.clone()]),
+            Some(vec![
+                Leg { from: 15, to: 10 },
+                Leg { from: 10, to: 0 },
+                Leg { from: 0, to: 4 }
+            ])
+        );
+        assert_eq!(plan_journey(4, 15, &[local, upper]), None);
+    }
+
+    #[test]
+    fn directions_and_served_floors() {
+        assert!(matches!(Leg { from: 2, to: 5 }.direction(), Direction::Up));
+        assert!(matches!(
+            Leg { from: 5, to: 2 }.direction(),
+            Direction::Down
+        ));
+
+        let floors = [0, 10, 11, 12];
+        assert!(serves_direction(&floors, 10, Direction::Up));
+        assert!(serves_direction(&floors, 10, Direction::Down));
+        assert!(!serves_direction(&floors, 12, Direction::Up));
+        assert!(!serves_direction(&floors, 0, Direction::Down));
+        assert!(!serves_direction(&floors, 5, Direction::Up));
+    }
+}
@@ -1,66 +1,85 @@
-use std::os::unix::fs::DirEntryExt;
 //use std::sync::mpsc::{channel, Sender, Receiver};
-use crossbeam_channel::{bounded, select, unbounded, Receiver, Sender};
-use rand::Rng;
-use std::collections::HashMap;
-use std::sync::{Arc, Condvar, Mutex, RwLock};
-use std::thread;
-use std::thread::sleep;
+use crossbeam_channel::{select_biased, unbounded, Receiver, RecvTimeoutError, Sender};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 
-enum ElevatorCommand {
-    MoveTo(u8), // Bewege zu Ebene x
-    OpenDoor,
-    CloseDoor,
-}
-
-enum ControlCommand {
-    Request { floor: u8, direction: Direction },
-}
-
-enum FloorCommand {
-    Request { floor: u8, direction: Direction },
-}
-
-enum ElevatorArrived {
-    Elevator(u8),
-}
-
-enum PassengerToElevator {
-    Enter(u8),
-    PressedButton(u8),
-    Exit(u8),
-}
-
-enum ElevatorToPassenger {
-    YouEntered(),
-    YouCanExit(u8),
-    YouCanChooseFloor,
-}
-
-#[derive(Debug, Clone, Copy)]
+mod access;
+mod car;
+mod cli;
+mod clock;
+mod config;
+mod console;
+mod dashboard;
+mod energy;
+mod events;
+mod faults;
+mod fire;
+mod freight;
+mod golden;
+mod group;
+mod invariants;
+mod journey;
+mod metrics;
+mod modes;
+mod parking;
+mod patience;
+mod power;
+mod profile;
+mod protocol;
+mod scenario;
+mod shaft;
+mod snapshot;
+mod stress;
+mod supervisor;
+mod trace;
+
+use access::AccessControl;
+use car::{Deck, ElevatorSpec};
+use clock::Instant;
+use config::SimulationConfig;
+use console::ConsoleInput;
+use dashboard::{Dashboard, LogCapture};
+use faults::{Fault, FaultInjection};
+use fire::{DoorButton, FireRecall, FireServiceConfig, FirefighterInput};
+use freight::{car_accepts, FreightPolicy};
+use group::Group;
+use invariants::{InvariantMonitor, Observation};
+use journey::{plan_journey, serves_direction, Leg};
+use metrics::Metrics;
+use modes::{AttendantInput, OperatingMode};
+use parking::{ParkingPlanner, ParkingPolicy};
+use patience::{Abandonment, Patience, STAIRS_TIME_PER_FLOOR};
+use power::EmergencyPower;
+use profile::{PassengerProfile, ACCESSIBLE_DOOR_DWELL, DECK_CAPACITY, DOOR_DWELL};
+use protocol::{
+    ControlCommand, CorrelationId, ElevatorArrived, ElevatorCommand, ElevatorId, ElevatorStatus,
+    ElevatorToPassenger, FloorCommand, FloorId, Inbox, Outcome, Outstanding, PassengerId,
+    PassengerToElevator, Reply, Request, Responder, REPLY_TIMEOUT,
+};
+use shaft::Shaft;
+use snapshot::Snapshot;
+use supervisor::{
+    ActorId, FailurePolicy, Heartbeat, Supervisor, SupervisorConfig, HEARTBEAT_INTERVAL,
+};
+use trace::Arrival;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 enum Direction {
     Up,
     Down,
 }
 
 enum ElevatorState {
-    IdleAtFloor(u8), // Steht in einer Ebene, z. B. "IdleAtFloor(2)"
-    Moving(u8, u8),  // Fährt von Ebene x zu Ebene y, z. B. "Moving(1, 3)"
-    StoppedAtFloor(u8),
-}
-
-enum ElevatorStatus {
-    DoorOpened(usize, u8), // Fahrstuhl-ID, Ebene
-    DoorClosed(usize, u8),
-    ArrivedAtFloor(usize, u8),
-    TaskCompleted(usize), // Aufgabe erledigt, optional mit Zusatzinfo
-    PassengerCount(u8),
-    PassengerTarget(usize, Vec<u8>),
-    ElevatorReadyToCloseTheDoor(u8),
-    ElevatorIdle(usize, u8),
+    IdleAtFloor,    // Steht in der Ebene `current_floor`
+    Moving(u8, u8), // Fährt von Ebene x zu Ebene y, z. B. "Moving(1, 3)"
+    StoppedAtFloor,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 enum DoorState {
     Closed,  // Tür ist geschlossen
     Opening, // Tür öffnet sich
@@ -71,470 +90,2590 @@ enum DoorState {
 enum PassengerState {
     IdleAtFloor(u8),  // Wartet in einer Ebene
     EnteringElevator, // Betritt den Fahrstuhl
-    InElevator(u8),   // Ist im Fahrstuhl mit Ziel Ebene x
+    InElevator,       // Ist im Fahrstuhl `current_elevator` mit Ziel Ebene x
     ExitingElevator,  // Verlässt den Fahrstuhl
 }
 
 struct ControlSystem {
-    elevators: Vec<Sender<ElevatorCommand>>,
-    command_rx: Receiver<ControlCommand>,
+    elevators: Vec<Sender<Request<ElevatorCommand>>>,
+    command_rx: Receiver<Request<ControlCommand>>,
     status_rx: Receiver<ElevatorStatus>, // Receiver für Statusupdates
+    specs: Vec<ElevatorSpec>,            // Zone und Decks je Fahrstuhl
+    shafts: Vec<Option<Arc<Shaft>>>,     // Gemeinsamer Schacht je Fahrstuhl (TWIN)
+    parking_policy: ParkingPolicy,       // Wohin freie Fahrstühle fahren
+    start_hour: u8,                      // Uhrzeit beim Start, für Regeln nach Tageszeit
+    fire_config: FireServiceConfig,      // Rückrufebenen im Brandfall
+    metrics: Arc<Mutex<Metrics>>,        // Nachweis des Ablaufs im Notstrombetrieb
+    freight_policy: FreightPolicy,       // Ob Lasten mit Fahrgästen fahren dürfen
+}
+
+// Betriebszustand der Steuerung, der einen Neustart durch den Supervisor übersteht: Brandfall,
+// Notstrom, Betriebsarten und ausgefallene Fahrstühle. Fahrziele und zugewiesene Rufe beginnen leer.
+#[derive(Clone)]
+struct ControlState {
+    fire: Option<FireRecall>, // Brandfallbetrieb ersetzt die normale Zuteilung
+    deferred: Vec<HallCall>,  // Rufe während des Brandfalls, werden danach bedient
+    emergency: Option<EmergencyPower>, // Notstrombetrieb begrenzt die Zahl fahrender Wagen
+    powered: Vec<bool>,
+    modes: Vec<OperatingMode>,
+    in_service: Vec<bool>,
+    slow_factors: Vec<f64>, // Gemeldete Verlangsamung je Fahrstuhl
+}
+
+impl ControlState {
+    fn new(elevators: usize) -> Self {
+        Self {
+            fire: None,
+            deferred: Vec::new(),
+            emergency: None,
+            powered: vec![true; elevators],
+            modes: vec![OperatingMode::Automatic; elevators],
+            in_service: vec![true; elevators],
+            slow_factors: vec![1.0; elevators],
+        }
+    }
+}
+
+// Zugewiesener Ruf einer Ebene; wird bei Ausfall des Fahrstuhls neu vergeben
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct HallCall {
+    floor: u8,
+    direction: Direction,
+    destination: Option<u8>,
+    accessible: bool, // Barrierefreie Ruftaste: längere Türzeit und Vorrang bei der Zuteilung
+    load: PassengerProfile,
+}
+
+// Zustand der Fahrstühle, den die Zuteilung der Rufe braucht
+struct Fleet<'a> {
+    specs: &'a [ElevatorSpec],
+    shafts: &'a [Option<Arc<Shaft>>],
+    floors: &'a [u8],
+    passenger_counts: &'a [usize],
+    deck_loads: &'a [Vec<u32>],
+    stops: &'a [usize],    // Halte, die der Wagen noch vor sich hat
+    available: &'a [bool], // In Betrieb, mit Strom versorgt und im Automatikbetrieb
+    in_service: &'a [bool],
+    slow_factors: &'a [f64],
+    freight_policy: FreightPolicy,
+}
+
+impl Fleet<'_> {
+    // Nächster Fahrstuhl (und Deck), dessen Zone die Ebene und die gewünschte Richtung bedient.
+    // Bei Doppeldeckern muss das gewählte Deck auch das Ziel erreichen.
+    fn choose(&self, call: HallCall) -> Option<(usize, u8, u8)> {
+        (0..self.specs.len())
+            .filter(|&id| self.available[id])
+            .filter(|&id| car_accepts(&self.specs[id], call.load))
+            .filter(|&id| {
+                serves_direction(&self.specs[id].served_floors, call.floor, call.direction)
+            })
+            .flat_map(|id| (0..self.specs[id].decks).map(move |deck| (id, deck)))
+            .filter_map(|(id, deck)| {
+                let position = self.specs[id].position_for(deck, call.floor)?;
+                let destination = match call.destination {
+                    Some(to) => self.specs[id].position_for(deck, to)?,
+                    None => position,
+                };
+                // Hinter einem Wagen außer Betrieb liegt weder der Ruf noch sein Ziel
+                let stuck = |from: u8, to: u8| {
+                    blocked_for_good(
+                        &self.specs[id],
+                        &self.shafts[id],
+                        id,
+                        from,
+                        to,
+                        self.in_service,
+                    )
+                };
+                (!stuck(self.floors[id], position) && !stuck(position, destination))
+                    .then_some((id, deck, position))
+            })
+            // Wagen, denen ein anderer Wagen im Schacht im Weg steht, nur im Notfall wählen.
+            // Barrierefreie Rufe bekommen möglichst einen Wagen, in dessen Deck der Rufende noch passt.
+            // Lasten, die nicht mit Fahrgästen fahren dürfen, bekommen möglichst einen leeren Wagen.
+            // Sonst gewinnt die früheste Ankunft: Fahrzeit, bei langsamen Wagen entsprechend länger, und je
+            // ausstehendem Halt ein Türzyklus. Bei gleicher Ankunft die Anfahrt mit dem geringsten Energieverbrauch.
+            .min_by_key(|&(id, deck, position)| {
+                let spec = &self.specs[id];
+                let blocked = self.shafts[id].as_ref().is_some_and(|shaft| {
+                    let low = self.floors[id].min(position);
+                    let high = self.floors[id].max(position) + spec.decks - 1;
+                    !shaft.is_clear(id, low, high)
+                });
+                let load = self.deck_loads[id].get(deck as usize).copied().unwrap_or(0);
+                let no_room = call.accessible && !call.load.fits(load);
+                let occupied = self.passenger_counts[id] > 0
+                    && self.freight_policy.prefers_empty_car(call.load);
+                let arrival = spec
+                    .energy
+                    .travel_time(self.floors[id], position)
+                    .mul_f64(self.slow_factors[id])
+                    + STOP_TIME * self.stops[id] as u32;
+                let energy_mwh = spec
+                    .energy
+                    .trip(self.floors[id], position, self.passenger_counts[id])
+                    .net_kwh()
+                    * 1_000_000.0;
+                (
+                    blocked,
+                    no_room,
+                    occupied,
+                    arrival.as_millis(),
+                    energy_mwh.round() as i64,
+                )
+            })
+    }
+}
+
+// Steht zwischen `from` und `to` ein Wagen außer Betrieb im Schacht? Er gibt den Weg nicht mehr frei
+fn blocked_for_good(
+    spec: &ElevatorSpec,
+    shaft: &Option<Arc<Shaft>>,
+    id: usize,
+    from: u8,
+    to: u8,
+    in_service: &[bool],
+) -> bool {
+    shaft.as_ref().is_some_and(|shaft| {
+        let low = from.min(to);
+        let high = from.max(to) + spec.decks - 1;
+        shaft
+            .blockers(id, low, high)
+            .into_iter()
+            .any(|blocker| !in_service[blocker])
+    })
 }
 
 impl ControlSystem {
+    #[allow(clippy::too_many_arguments)]
     fn new(
-        elevators: Vec<Sender<ElevatorCommand>>,
-        command_rx: Receiver<ControlCommand>,
+        elevators: Vec<Sender<Request<ElevatorCommand>>>,
+        command_rx: Receiver<Request<ControlCommand>>,
         status_rx: Receiver<ElevatorStatus>,
+        specs: Vec<ElevatorSpec>,
+        shafts: Vec<Option<Arc<Shaft>>>,
+        parking_policy: ParkingPolicy,
+        start_hour: u8,
+        fire_config: FireServiceConfig,
+        metrics: Arc<Mutex<Metrics>>,
+        freight_policy: FreightPolicy,
+        supervisor: &Supervisor,
     ) -> Self {
         let control_system = Self {
             elevators,
             command_rx,
             status_rx,
+            specs,
+            shafts,
+            parking_policy,
+            start_hour,
+            fire_config,
+            metrics,
+            freight_policy,
         };
 
         let command_rx_clone = control_system.command_rx.clone();
         let status_rx_clone = control_system.status_rx.clone();
         let elevators_clone = control_system.elevators.clone();
-
-        // ControlSystem-Thread starten
-        thread::spawn(move || {
-            ControlSystem::run(elevators_clone, command_rx_clone, status_rx_clone);
+        let specs_clone = control_system.specs.clone();
+        let shafts_clone = control_system.shafts.clone();
+        let parking_policy_clone = control_system.parking_policy.clone();
+        let start_hour = control_system.start_hour;
+        let fire_config_clone = control_system.fire_config.clone();
+        let metrics_clone = Arc::clone(&control_system.metrics);
+        let freight_policy = control_system.freight_policy;
+        let heartbeat = supervisor.heartbeat(ActorId::ControlSystem);
+        let state = Arc::new(Mutex::new(ControlState::new(
+            control_system.elevators.len(),
+        )));
+
+        // ControlSystem-Thread starten; nach einem Neustart übernimmt die Steuerung den gesicherten
+        // Betriebszustand, aber keine Fahrziele
+        supervisor.spawn(ActorId::ControlSystem, move || {
+            ControlSystem::run(
+                elevators_clone.clone(),
+                command_rx_clone.clone(),
+                status_rx_clone.clone(),
+                specs_clone.clone(),
+                shafts_clone.clone(),
+                parking_policy_clone.clone(),
+                start_hour,
+                fire_config_clone.clone(),
+                Arc::clone(&metrics_clone),
+                freight_policy,
+                heartbeat.clone(),
+                Arc::clone(&state),
+            );
         });
 
         control_system
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn run(
-        elevators: Vec<Sender<ElevatorCommand>>,
-        command_rx: Receiver<ControlCommand>,
+        elevators: Vec<Sender<Request<ElevatorCommand>>>,
+        command_rx: Receiver<Request<ControlCommand>>,
         status_rx: Receiver<ElevatorStatus>,
+        specs: Vec<ElevatorSpec>,
+        shafts: Vec<Option<Arc<Shaft>>>,
+        parking_policy: ParkingPolicy,
+        start_hour: u8,
+        fire_config: FireServiceConfig,
+        metrics: Arc<Mutex<Metrics>>,
+        freight_policy: FreightPolicy,
+        mut heartbeat: Heartbeat,
+        state: Arc<Mutex<ControlState>>,
     ) {
-        let mut passenger_targets: Vec<Vec<u8>> = vec![Vec::new(); 3];
-        let mut elevator_floors: Vec<u8> = vec![0; 3];
+        let ControlState {
+            mut fire,
+            mut deferred,
+            mut emergency,
+            mut powered,
+            mut modes,
+            mut in_service,
+            mut slow_factors,
+        } = state.lock().unwrap().clone();
+        let mut passenger_targets: Vec<Vec<u8>> = vec![Vec::new(); elevators.len()];
+        let mut elevator_floors: Vec<u8> = vec![0; elevators.len()];
+        let mut passenger_counts: Vec<usize> = vec![0; elevators.len()];
+        let mut deck_loads: Vec<Vec<u32>> = vec![Vec::new(); elevators.len()]; // Belegter Platz je Deck
+        let mut pending_park: Vec<Option<u8>> = vec![None; elevators.len()]; // Laufende Parkfahrten
+        let mut idle_floors: Vec<Option<u8>> = vec![None; elevators.len()]; // Parkposition freier Fahrstühle
+        let floors = specs
+            .iter()
+            .flat_map(|spec| spec.served_floors.iter())
+            .max()
+            .map_or(0, |&top| top as usize + 1);
+        let mut parking = ParkingPlanner::new(parking_policy, floors, start_hour);
+        let mut assigned_calls: Vec<Vec<(u8, HallCall)>> = vec![Vec::new(); elevators.len()]; // Anzufahrende Position und Ruf
+        let mut unassigned: Vec<HallCall> = Vec::new();
+        let mut left_behind: Vec<Vec<HallCall>> = vec![Vec::new(); elevators.len()]; // Rufe am letzten Halt
+        let mut called_again: Vec<HallCall> = Vec::new(); // Erneute Rufe von dort, vorne einreihen
+        let mut lost: Vec<usize> = Vec::new(); // Ausgefallene oder nicht erreichbare Fahrstühle
+        let mut express: Vec<Option<u8>> = vec![None; elevators.len()]; // VIP-Fahrt, sobald die Tür geschlossen ist
+        let mut inbox = Inbox::default(); // Befehle von Ebenen, Konsole und Szenario
+        let (reply_tx, reply_rx) = unbounded();
+        let mut outbox = Outbox {
+            busy: vec![false; elevators.len()],
+            elevators,
+            replies: reply_tx,
+            outstanding: Outstanding::default(),
+        };
         loop {
-            select! {
-                recv(command_rx) -> command => {
-                    if let Ok(command) = command {
+            heartbeat.beat();
+            // Nach jeder Nachricht sichern, damit ein Neustart dort weitermacht
+            *state.lock().unwrap() = ControlState {
+                fire: fire.clone(),
+                deferred: deferred.clone(),
+                emergency: emergency.clone(),
+                powered: powered.clone(),
+                modes: modes.clone(),
+                in_service: in_service.clone(),
+                slow_factors: slow_factors.clone(),
+            };
+            // Im Brandfall und während der Evakuierung bei Notstrom gibt es keinen normalen Betrieb
+            let overridden = fire.is_some()
+                || emergency
+                    .as_ref()
+                    .is_some_and(|power| power.in_evacuation());
+            clock::wait(
+                vec![
+                    clock::ready(&command_rx),
+                    clock::ready(&reply_rx),
+                    clock::ready(&status_rx),
+                ],
+                Some(Instant::now() + HEARTBEAT_INTERVAL),
+            );
+            select_biased! {
+                recv(command_rx) -> request => {
+                    if let Some((command, responder)) = request.ok().and_then(|request| inbox.admit(request)) {
                         match command {
-                            ControlCommand::Request { floor, direction } => {
-                                println!("Control System: Received request from floor {} going {:?}", floor, direction);
-                                let best_elevator = 0; // Einfache Logik: immer der erste Fahrstuhl
-                                println!("Control System: Assigning Elevator {} to floor {}", best_elevator, floor);
-                                elevators[best_elevator]
-                                    .send(ElevatorCommand::MoveTo(floor))
-                                    .unwrap();
-
+                            ControlCommand::Request { floor: FloorId(floor), direction, destination, accessible, load } => {
+                                println!(
+                                    "Control System: Received {}request from floor {} going {:?}{}",
+                                    if accessible { "accessible " } else { "" },
+                                    floor,
+                                    direction,
+                                    if load.is_freight() { format!(" ({:?})", load) } else { String::new() }
+                                );
+                                parking.record_call(floor);
+                                let destination = destination.map(|FloorId(floor)| floor);
+                                let call = HallCall { floor, direction, destination, accessible, load };
+                                // Liftführer sehen Rufe aus ihrer Zone und entscheiden selbst, ob sie halten
+                                for id in (0..outbox.elevators.len()).filter(|&id| modes[id] == OperatingMode::Attendant) {
+                                    if serves_direction(&specs[id].served_floors, floor, direction) {
+                                        println!(
+                                            "Control System: Showing call from floor {} going {:?} to the attendant of Elevator {}",
+                                            floor, direction, id
+                                        );
+                                    }
+                                }
+                                if overridden {
+                                    println!("Control System: Emergency operation active, deferring request from floor {}", floor);
+                                    if !deferred.contains(&call) {
+                                        deferred.push(call);
+                                    }
+                                } else {
+                                    // Wer beim letzten Halt keinen Platz fand, ruft erneut und behält seinen Platz in der Reihe
+                                    if let Some(calls) = left_behind.iter_mut().find(|calls| calls.contains(&call)) {
+                                        calls.retain(|&other| other != call);
+                                        called_again.push(call);
+                                    }
+                                    unassigned.push(call);
+                                }
+                            }
+                            ControlCommand::FireAlarm(FloorId(alarm_floor)) => {
+                                if fire.is_some() {
+                                    Self::reject(responder, format!("Fire alarm at floor {}, fire service already active", alarm_floor));
+                                    continue;
+                                }
+                                println!("Control System: Fire alarm at floor {}, starting fire service Phase I", alarm_floor);
+                                // Alle Ruf- und Fahrziele verwerfen; wartende Rufe werden nach der Rückstellung bedient
+                                for id in 0..outbox.elevators.len() {
+                                    passenger_targets[id].clear();
+                                    deferred.extend(assigned_calls[id].drain(..).map(|(_, call)| call));
+                                    pending_park[id] = None;
+                                    idle_floors[id] = None;
+                                }
+                                deferred.append(&mut unassigned);
+                                let recall_floors: Vec<Option<u8>> = specs
+                                    .iter()
+                                    .map(|spec| fire_config.recall_floor(spec, alarm_floor))
+                                    .collect();
+                                for (id, recall_floor) in recall_floors.iter().enumerate() {
+                                    match recall_floor {
+                                        Some(recall_floor) if in_service[id] => {
+                                            println!("Control System: Recalling Elevator {} to floor {}", id, recall_floor);
+                                            outbox.send(&mut lost, id, ElevatorCommand::Recall(FloorId(*recall_floor)));
+                                        }
+                                        Some(_) => {}
+                                        None => println!("Control System: Elevator {} has no safe recall floor", id),
+                                    }
+                                }
+                                fire = Some(FireRecall {
+                                    alarm_floor,
+                                    recall_floors,
+                                    recalled: vec![false; outbox.elevators.len()],
+                                    firefighter: None,
+                                });
+                            }
+                            ControlCommand::FireAlarmReset => {
+                                let Some(recall) = &fire else {
+                                    Self::reject(responder, "No fire alarm to reset".to_string());
+                                    continue;
+                                };
+                                if let Some(id) = recall.firefighter {
+                                    Self::reject(responder, format!("Cannot reset fire service, Elevator {} is still in Phase II", id));
+                                    continue;
+                                }
+                                println!(
+                                    "Control System: Fire alarm at floor {} reset, returning to normal operation",
+                                    recall.alarm_floor
+                                );
+                                fire = None;
+                                // Türen schließen; die Fahrstühle melden sich danach wie gewohnt frei
+                                for id in (0..outbox.elevators.len()).filter(|&id| in_service[id] && powered[id]) {
+                                    outbox.send(&mut lost, id, ElevatorCommand::CloseDoor);
+                                }
+                                if emergency.as_ref().is_none_or(|power| !power.in_evacuation()) {
+                                    unassigned.append(&mut deferred);
+                                }
+                            }
+                            ControlCommand::Firefighter(input) => {
+                                let Some(recall) = &mut fire else {
+                                    Self::reject(responder, "Firefighter service requires an active fire alarm".to_string());
+                                    continue;
+                                };
+                                match input {
+                                    FirefighterInput::KeyOn(ElevatorId(id)) => {
+                                        if let Some(active) = recall.firefighter {
+                                            Self::reject(responder, format!("Elevator {} is already in Phase II", active));
+                                            continue;
+                                        } else if !recall.recalled.get(id).copied().unwrap_or(false) || !in_service[id] {
+                                            Self::reject(responder, format!("Elevator {} has not completed Phase I recall", id));
+                                            continue;
+                                        } else {
+                                            println!("Control System: Phase II, firefighter operates Elevator {}", id);
+                                            recall.firefighter = Some(id);
+                                        }
+                                    }
+                                    FirefighterInput::KeyOff(ElevatorId(id)) => {
+                                        if recall.firefighter != Some(id) {
+                                            Self::reject(responder, format!("Elevator {} is not in Phase II", id));
+                                            continue;
+                                        }
+                                        // Zurück in Phase I: der Fahrstuhl kehrt zur Rückrufebene zurück
+                                        println!("Control System: Phase II ended for Elevator {}", id);
+                                        recall.firefighter = None;
+                                        recall.recalled[id] = false;
+                                        if let Some(recall_floor) = recall.recall_floors[id] {
+                                            outbox.send(&mut lost, id, ElevatorCommand::Recall(FloorId(recall_floor)));
+                                        }
+                                    }
+                                    FirefighterInput::CarCall(FloorId(floor)) => {
+                                        let Some(id) = recall.firefighter else {
+                                            Self::reject(responder, "No elevator in Phase II".to_string());
+                                            continue;
+                                        };
+                                        if !specs[id].can_stop(floor) {
+                                            Self::reject(responder, format!("Elevator {} cannot stop at floor {}", id, floor));
+                                            continue;
+                                        }
+                                        println!("Control System: Firefighter sends Elevator {} to floor {}", id, floor);
+                                        outbox.send(&mut lost, id, ElevatorCommand::FirefighterMove(FloorId(floor)));
+                                    }
+                                    FirefighterInput::Door(button, held) => {
+                                        let Some(id) = recall.firefighter else {
+                                            Self::reject(responder, "No elevator in Phase II".to_string());
+                                            continue;
+                                        };
+                                        outbox.send(&mut lost, id, ElevatorCommand::FirefighterDoor(button, held));
+                                    }
+                                }
+                            }
+                            ControlCommand::EmergencyPower(budget) => {
+                                if emergency.is_some() {
+                                    Self::reject(responder, "Already on emergency power".to_string());
+                                    continue;
+                                }
+                                if budget == 0 {
+                                    Self::reject(responder, "Emergency power must run at least one elevator".to_string());
+                                    continue;
+                                }
+                                Self::power_event(
+                                    &metrics,
+                                    format!("Switched to emergency power, {} elevator(s) may run at once", budget),
+                                );
+                                // Alle Wagen halten an; Rufe werden erst nach der Evakuierung bedient
+                                for id in 0..outbox.elevators.len() {
+                                    passenger_targets[id].clear();
+                                    deferred.extend(assigned_calls[id].drain(..).map(|(_, call)| call));
+                                    pending_park[id] = None;
+                                    idle_floors[id] = None;
+                                    powered[id] = false;
+                                    if in_service[id] {
+                                        outbox.send(&mut lost, id, ElevatorCommand::Power(false));
+                                    }
+                                }
+                                deferred.append(&mut unassigned);
+                                emergency = Some(EmergencyPower::new(
+                                    budget,
+                                    (0..outbox.elevators.len()).filter(|&id| in_service[id]),
+                                ));
+                            }
+                            ControlCommand::SetMode(ElevatorId(id), mode) => {
+                                if id >= outbox.elevators.len() || !in_service[id] {
+                                    Self::reject(responder, format!("Elevator {} is not in service", id));
+                                    continue;
+                                }
+                                if modes[id] == mode {
+                                    responder.ack();
+                                    continue;
+                                }
+                                if let OperatingMode::Vip(floor) = mode {
+                                    if !specs[id].can_stop(floor) {
+                                        Self::reject(responder, format!("Elevator {} cannot stop at floor {}", id, floor));
+                                        continue;
+                                    }
+                                }
+                                println!("Control System: Elevator {} switched from {:?} to {:?}", id, modes[id], mode);
+                                modes[id] = mode;
+                                express[id] = None;
+                                outbox.send(&mut lost, id, ElevatorCommand::SetMode(mode));
+                                responder.ack();
+                                if mode == OperatingMode::Automatic {
+                                    continue;
+                                }
+                                // Zugeteilte Rufe übernehmen andere Fahrstühle, geparkt wird nicht mehr
+                                for (_, call) in assigned_calls[id].drain(..) {
+                                    println!(
+                                        "Control System: Reassigning request from floor {} going {:?}",
+                                        call.floor, call.direction
+                                    );
+                                    unassigned.push(call);
+                                }
+                                pending_park[id] = None;
+                                if let OperatingMode::Vip(floor) = mode {
+                                    // Ein freier Fahrstuhl fährt sofort, sonst nach dem Schließen der Tür
+                                    if idle_floors[id].is_some() && !overridden {
+                                        println!("Control System: Sending Elevator {} express to floor {}", id, floor);
+                                        outbox.send(&mut lost, id, ElevatorCommand::MoveTo(FloorId(floor)));
+                                    } else {
+                                        express[id] = Some(floor);
+                                    }
+                                }
+                                idle_floors[id] = None;
+                                continue; // Bereits bestätigt
+                            }
+                            ControlCommand::Attendant(ElevatorId(id), input) => {
+                                if modes.get(id) != Some(&OperatingMode::Attendant) {
+                                    Self::reject(responder, format!("Elevator {} is not in attendant mode", id));
+                                    continue;
+                                }
+                                match input {
+                                    AttendantInput::CarCall(FloorId(floor)) => {
+                                        if !specs[id].can_stop(floor) {
+                                            Self::reject(responder, format!("Elevator {} cannot stop at floor {}", id, floor));
+                                            continue;
+                                        } else if !passenger_targets[id].contains(&floor) {
+                                            println!("Control System: Attendant of Elevator {} selects floor {}", id, floor);
+                                            passenger_targets[id].push(floor);
+                                        }
+                                    }
+                                    AttendantInput::Start => {
+                                        println!("Control System: Attendant of Elevator {} closes the door", id);
+                                        outbox.send(&mut lost, id, ElevatorCommand::CloseDoor);
+                                    }
+                                }
+                            }
+                            ControlCommand::NormalPower => {
+                                if emergency.take().is_none() {
+                                    Self::reject(responder, "Not on emergency power".to_string());
+                                    continue;
+                                }
+                                Self::power_event(&metrics, "Normal power restored".to_string());
+                                // Abgeschaltete Wagen schließen die Tür und melden sich danach wie gewohnt frei
+                                for id in 0..outbox.elevators.len() {
+                                    if !in_service[id] || powered[id] {
+                                        continue;
+                                    }
+                                    powered[id] = true;
+                                    outbox.send(&mut lost, id, ElevatorCommand::Power(true));
+                                    if fire.is_none() {
+                                        outbox.send(&mut lost, id, ElevatorCommand::CloseDoor);
+                                    }
+                                }
+                                if fire.is_none() {
+                                    unassigned.append(&mut deferred);
+                                }
                             }
                         }
+                        responder.ack();
+                    }
+                }
+                recv(reply_rx) -> reply => {
+                    if let Ok(reply) = reply {
+                        match (outbox.outstanding.answered(&reply), reply.outcome) {
+                            (Ok(_), Outcome::Ack) => {}
+                            (Ok((to, command)), Outcome::Nack(reason)) => {
+                                println!("Control System: {} rejected {}: {}", to, command, reason);
+                            }
+                            (Err(problem), _) => println!("Control System: {}", problem),
+                        }
                     }
                 }
                 recv(status_rx) -> status => {
                     if let Ok(status) = status {
                         match status {
-                            ElevatorStatus::ArrivedAtFloor(id, floor) => {
-                                elevators[id]
-                                    .send(ElevatorCommand::OpenDoor)
-                                    .unwrap();
+                            ElevatorStatus::Recalled(ElevatorId(id), FloorId(floor)) => {
+                                elevator_floors[id] = floor;
+                                println!("Control System: Elevator {} recalled to floor {}, parked with doors open", id, floor);
+                                if let Some(recall) = &mut fire {
+                                    recall.recalled[id] = true;
+                                }
+                                if let Some(power) = &mut emergency {
+                                    if power.release(id) {
+                                        Self::power_event(&metrics, format!("Elevator {} unloaded at floor {}, shut down", id, floor));
+                                        powered[id] = false;
+                                        outbox.send(&mut lost, id, ElevatorCommand::Power(false));
+                                    }
+                                } else if fire.is_none() {
+                                    // Rückruf nach dem Ende des Sonderbetriebs abgeschlossen
+                                    outbox.send(&mut lost, id, ElevatorCommand::CloseDoor);
+                                }
                             }
-                            ElevatorStatus::DoorOpened(id, floor) => {
-                               
+                            ElevatorStatus::ArrivedAtFloor(ElevatorId(id), FloorId(floor)) if overridden => {
+                                // Im Brandfall öffnet nur die Feuerwehr die Tür
+                                elevator_floors[id] = floor;
+                                println!("Control System: Elevator {} at floor {}, doors stay closed", id, floor);
                             }
-                            ElevatorStatus::DoorClosed(id, floor) => {
-                                if let Some(&closest_floor) = passenger_targets[id]
-                                .iter()
-                                .min_by_key(|&&target| (target as i32 - floor as i32).abs())
+                            ElevatorStatus::ShaftBlocked(ElevatorId(id), ElevatorId(blocker), FloorId(target))
+                                if emergency.as_ref().is_some_and(|power| power.is_evacuating(id)) =>
                             {
-                                // Entferne das gefundene Ziel aus der Liste
-                                passenger_targets[id].retain(|&x| x != closest_floor);
-
-                                println!(
-                                    "Control System: Assigning Elevator {} to moveoto closest floor {}",
-                                    id, closest_floor
+                                // Der andere Wagen steht ohne Strom im Weg, nächstgelegenen freien Halt anfahren
+                                let stop = Self::nearest_clear_stop(&specs[id], &shafts[id], id, elevator_floors[id], target)
+                                    .unwrap_or(elevator_floors[id]);
+                                Self::power_event(
+                                    &metrics,
+                                    format!("Elevator {} blocked by Elevator {}, lowering to floor {} instead", id, blocker, stop),
                                 );
-                                elevators[id]
-                                    .send(ElevatorCommand::MoveTo(closest_floor))
-                                    .unwrap();
-                            } else {
-                                println!("Control System: No pending targets for Elevator {}", id);
+                                outbox.send(&mut lost, id, ElevatorCommand::Recall(FloorId(stop)));
                             }
+                            ElevatorStatus::ShaftBlocked(ElevatorId(id), ElevatorId(blocker), FloorId(target)) if fire.is_some() => {
+                                let recall = fire.as_ref().unwrap();
+                                if recall.firefighter != Some(id) && recall.recall_floors[id] != Some(target) {
+                                    // Normale Fahrt von vor dem Alarm, der Rückruf folgt ohnehin
+                                    continue;
+                                }
+                                if recall.firefighter == Some(id) || !in_service[blocker] {
+                                    println!(
+                                        "Control System: Elevator {} cannot reach floor {}, blocked by Elevator {}",
+                                        id, target, blocker
+                                    );
+                                    continue;
+                                }
+                                // Steht der andere Wagen schon geparkt im Weg, nächstgelegenen freien Halt anfahren
+                                let target = if recall.recalled[blocker] {
+                                    Self::nearest_clear_stop(&specs[id], &shafts[id], id, elevator_floors[id], target)
+                                        .unwrap_or(elevator_floors[id])
+                                } else {
+                                    target
+                                };
+                                println!("Control System: Recalling Elevator {} to floor {} instead", id, target);
+                                outbox.send(&mut lost, id, ElevatorCommand::Recall(FloorId(target)));
                             }
-                            ElevatorStatus::TaskCompleted(id) => {
-                                println!("Elevator {} completed a task", id);
+                            // Im Brandfall und bei der Evakuierung keine Fahrten für Passagiere und kein Parken
+                            ElevatorStatus::DoorClosed(..)
+                            | ElevatorStatus::ElevatorIdle(..)
+                            | ElevatorStatus::ElevatorReadyToCloseTheDoor(_)
+                            | ElevatorStatus::PassengerTarget(..)
+                                if overridden => {}
+                            ElevatorStatus::ArrivedAtFloor(ElevatorId(id), FloorId(floor)) => {
+                                elevator_floors[id] = floor;
+                                let accessible = assigned_calls[id]
+                                    .iter()
+                                    .any(|&(position, call)| position == floor && call.accessible);
+                                let served = passenger_targets[id].contains(&floor)
+                                    || assigned_calls[id].iter().any(|&(position, _)| position == floor);
+                                left_behind[id] = assigned_calls[id]
+                                    .iter()
+                                    .filter(|&&(position, _)| position == floor)
+                                    .map(|&(_, call)| call)
+                                    .collect();
+                                assigned_calls[id].retain(|&(position, _)| position != floor);
+                                passenger_targets[id].retain(|&target| target != floor);
+                                // Geparkte Fahrstühle ohne Halt in dieser Ebene warten mit geschlossener Tür
+                                // oder fahren gleich zum nächsten Halt
+                                let parked = pending_park[id].take() == Some(floor);
+                                if parked && !served && passenger_counts[id] == 0 {
+                                    println!("Control System: Elevator {} parked at floor {}", id, floor);
+                                    outbox.busy[id] = false;
+                                    if let Some(stop) = Self::next_stop(&passenger_targets[id], &assigned_calls[id], floor, &deck_loads[id]) {
+                                        idle_floors[id] = None;
+                                        outbox.send(&mut lost, id, ElevatorCommand::MoveTo(FloorId(stop)));
+                                    }
+                                    continue;
+                                }
+                                let dwell = if accessible { ACCESSIBLE_DOOR_DWELL } else { DOOR_DWELL };
+                                outbox.send(&mut lost, id, ElevatorCommand::OpenDoor(dwell));
                             }
-                            ElevatorStatus::PassengerCount(id) => {
-                                println!("{} passengers in elevator", id);
+                            ElevatorStatus::DoorOpened(_id, _floor) => {}
+                            ElevatorStatus::DoorClosed(ElevatorId(id), FloorId(floor)) => {
+                                outbox.busy[id] = false;
+                                if let Some(vip_floor) = express[id].take() {
+                                    // VIP-Fahrt vor allen anderen Zielen
+                                    println!("Control System: Sending Elevator {} express to floor {}", id, vip_floor);
+                                    idle_floors[id] = None;
+                                    outbox.send(&mut lost, id, ElevatorCommand::MoveTo(FloorId(vip_floor)));
+                                } else if let Some(closest_floor) =
+                                    Self::next_stop(&passenger_targets[id], &assigned_calls[id], floor, &deck_loads[id])
+                                {
+                                    idle_floors[id] = None;
+
+                                    println!(
+                                        "Control System: Assigning Elevator {} to moveoto closest floor {}",
+                                        id, closest_floor
+                                    );
+                                    outbox.send(&mut lost, id, ElevatorCommand::MoveTo(FloorId(closest_floor)));
+                                } else {
+                                    println!("Control System: No pending targets for Elevator {}", id);
+                                    if passenger_counts[id] == 0 && modes[id] == OperatingMode::Automatic {
+                                        // Leeren Fahrstuhl nach der Parkstrategie abstellen
+                                        let other_idle: Vec<u8> = idle_floors
+                                            .iter()
+                                            .enumerate()
+                                            .filter(|&(other, _)| other != id)
+                                            .filter_map(|(_, idle_floor)| *idle_floor)
+                                            .collect();
+                                        let elapsed = metrics.lock().unwrap().clock();
+                                        match parking.parking_floor(&specs[id], floor, &other_idle, elapsed) {
+                                            Some(park_floor) if pending_park[id].is_none() => {
+                                                println!("Control System: Parking Elevator {} at floor {}", id, park_floor);
+                                                idle_floors[id] = Some(park_floor);
+                                                pending_park[id] = Some(park_floor);
+                                                outbox.send(&mut lost, id, ElevatorCommand::MoveTo(FloorId(park_floor)));
+                                            }
+                                            _ => idle_floors[id] = Some(floor),
+                                        }
+                                    }
+                                }
+                            }
+                            ElevatorStatus::PassengerCount(ElevatorId(id), count, loads) => {
+                                println!("{} passengers in elevator {}", count, id);
+                                passenger_counts[id] = count;
+                                deck_loads[id] = loads;
                             }
-                            ElevatorStatus::ElevatorIdle(id, floor) => {
+                            ElevatorStatus::ElevatorIdle(ElevatorId(id), FloorId(floor)) => {
                                 println!("Elevator {} is idle at floor {}", id, floor);
-                                elevators[id]
-                                    .send(ElevatorCommand::CloseDoor)
-                                    .unwrap();
+                                outbox.send(&mut lost, id, ElevatorCommand::CloseDoor);
                             }
-                            ElevatorStatus::PassengerTarget(elevator_id, targets) => {
+                            ElevatorStatus::PassengerTarget(ElevatorId(elevator_id), targets) => {
                                 // Füge nur neue Ziele hinzu, die nicht bereits vorhanden sind
-                                for target in targets {
+                                for FloorId(target) in targets {
                                     println!("Control System: Received target {} for Elevator {}", target, elevator_id);
                                     if !passenger_targets[elevator_id].contains(&target) {
                                         passenger_targets[elevator_id].push(target);
                                     }
                                 }
-                                if let Some(&closest_floor) = passenger_targets[elevator_id]
-                                .iter()
-                                .min_by_key(|&&target| (target as i32 - elevator_floors[elevator_id] as i32).abs())
-                            {
-                                // Entferne das gefundene Ziel aus der Liste
-                                passenger_targets[elevator_id].retain(|&x| x != closest_floor);
+                                if outbox.busy[elevator_id] {
+                                    // Wird nach dem aktuellen Halt angefahren
+                                    continue;
+                                }
+                                if let Some(closest_floor) = Self::next_stop(
+                                    &passenger_targets[elevator_id],
+                                    &assigned_calls[elevator_id],
+                                    elevator_floors[elevator_id],
+                                    &deck_loads[elevator_id],
+                                ) {
+                                    idle_floors[elevator_id] = None;
 
+                                    println!(
+                                        "Control System: Assigning Elevator {} to moveoto closest floor {}",
+                                        elevator_id, closest_floor
+                                    );
+                                    outbox.send(&mut lost, elevator_id, ElevatorCommand::MoveTo(FloorId(closest_floor)));
+                                } else {
+                                    println!("Control System: No pending targets for Elevator {}", elevator_id);
+                                }
+                            }
+                            ElevatorStatus::ElevatorReadyToCloseTheDoor(ElevatorId(id)) => {
+                                println!("Elevator {} is ready to close the door", id);
+                                outbox.send(&mut lost, id, ElevatorCommand::CloseDoor);
+                            }
+                            ElevatorStatus::ShaftBlocked(ElevatorId(id), ElevatorId(blocker), FloorId(target)) => {
                                 println!(
-                                    "Control System: Assigning Elevator {} to moveoto closest floor {}",
-                                    elevator_id, closest_floor
+                                    "Control System: Elevator {} is blocked by Elevator {} on its way to floor {}",
+                                    id, blocker, target
                                 );
-                                elevators[elevator_id]
-                                    .send(ElevatorCommand::MoveTo(closest_floor))
-                                    .unwrap();
-                            } else {
-                                println!("Control System: No pending targets for Elevator {}", elevator_id);
-                            }
-
+                                // Der Wagen steht, wo er war, und nimmt wieder Fahrten an; war es seine Parkfahrt,
+                                // ist sie damit beendet und hält keine Ziele mehr zurück
+                                outbox.busy[id] = false;
+                                if pending_park[id] == Some(target) {
+                                    pending_park[id] = None;
+                                    idle_floors[id] = None;
+                                }
+                                let Some(shaft) = &shafts[id] else { continue };
+                                if !in_service[blocker] {
+                                    println!(
+                                        "Control System: Elevator {} cannot reach floor {}, Elevator {} is out of service",
+                                        id, target, blocker
+                                    );
+                                    // Rufe hinter dem Wagen außer Betrieb bekommt ein anderer Fahrstuhl
+                                    let from = elevator_floors[id];
+                                    let stuck = |to: u8| blocked_for_good(&specs[id], &shafts[id], id, from, to, &in_service);
+                                    let (unreachable, reachable): (Vec<_>, Vec<_>) = std::mem::take(&mut assigned_calls[id])
+                                        .into_iter()
+                                        .partition(|&(position, call)| stuck(position) || call.destination.is_some_and(stuck));
+                                    assigned_calls[id] = reachable;
+                                    for (_, call) in unreachable {
+                                        println!(
+                                            "Control System: Reassigning request from floor {} going {:?}",
+                                            call.floor, call.direction
+                                        );
+                                        unassigned.push(call);
+                                    }
+                                    // Fahrgäste mit einem Ziel dahinter steigen in der nächsten erreichbaren Ebene aus
+                                    // und planen von dort neu
+                                    if passenger_targets[id].iter().any(|&target| stuck(target)) {
+                                        let stop = Self::nearest_clear_stop(&specs[id], &shafts[id], id, from, target)
+                                            .unwrap_or(from);
+                                        println!("Control System: Letting the passengers of Elevator {} out at floor {}", id, stop);
+                                        passenger_targets[id].clear();
+                                        // Bis die Tür nach dem Aussteigen wieder zu ist
+                                        outbox.busy[id] = true;
+                                        outbox.send(&mut lost, id, ElevatorCommand::Recall(FloorId(stop)));
+                                    } else if let Some(stop) = Self::next_stop(&passenger_targets[id], &assigned_calls[id], from, &deck_loads[id]) {
+                                        outbox.send(&mut lost, id, ElevatorCommand::MoveTo(FloorId(stop)));
+                                    }
+                                    continue;
+                                }
+                                if !powered[blocker] {
+                                    // Abgeschalteter Wagen bei Notstrom: Ruf nach der Rückkehr des Netzstroms bedienen
+                                    println!(
+                                        "Control System: Elevator {} cannot reach floor {}, Elevator {} is shut down",
+                                        id, target, blocker
+                                    );
+                                    let (unreachable, reachable): (Vec<_>, Vec<_>) = std::mem::take(&mut assigned_calls[id])
+                                        .into_iter()
+                                        .partition(|&(position, _)| position == target);
+                                    assigned_calls[id] = reachable;
+                                    deferred.extend(unreachable.into_iter().map(|(_, call)| call));
+                                    continue;
+                                }
+                                // Verklemmung auflösen: der obere Wagen weicht nach oben aus, der untere fährt weiter
+                                let (lower, upper) = if shaft.position(blocker) > shaft.position(id) {
+                                    (id, blocker)
+                                } else {
+                                    (blocker, id)
+                                };
+                                // Ein freier unterer Wagen ohne Aufträge weicht nach unten aus
+                                let lower_floor = specs[lower].stops().first().copied().unwrap_or(elevator_floors[lower]);
+                                let lower_free = !outbox.busy[lower]
+                                    && passenger_counts[lower] == 0
+                                    && passenger_targets[lower].is_empty()
+                                    && assigned_calls[lower].is_empty();
+                                if id == upper && lower_free && elevator_floors[lower] != lower_floor {
+                                    println!(
+                                        "Control System: Parking Elevator {} at floor {} to clear shaft {}",
+                                        lower, lower_floor, shaft.id
+                                    );
+                                    pending_park[lower] = Some(lower_floor);
+                                    idle_floors[lower] = Some(lower_floor);
+                                    outbox.send(&mut lost, lower, ElevatorCommand::MoveTo(FloorId(lower_floor)));
+                                    outbox.send(&mut lost, id, ElevatorCommand::MoveTo(FloorId(target)));
+                                    continue;
+                                }
+                                let upper_floor = shaft.position(upper).map_or(elevator_floors[upper], |(low, _)| low);
+                                let park_floor = specs[upper].stops().last().copied().unwrap_or(upper_floor);
+                                if upper_floor == park_floor && id == lower {
+                                    println!(
+                                        "Control System: Elevator {} cannot reach floor {}, Elevator {} is already parked",
+                                        id, target, upper
+                                    );
+                                    continue;
+                                }
+                                if pending_park[upper].is_none() && upper_floor != park_floor {
+                                    println!(
+                                        "Control System: Parking Elevator {} at floor {} to clear shaft {}",
+                                        upper, park_floor, shaft.id
+                                    );
+                                    pending_park[upper] = Some(park_floor);
+                                    outbox.send(&mut lost, upper, ElevatorCommand::MoveTo(FloorId(park_floor)));
+                                }
+                                if id == lower || pending_park[upper].is_none() {
+                                    // Erneut versuchen; der Schacht wartet beim Reservieren, bis der andere Wagen weg ist
+                                    outbox.send(&mut lost, id, ElevatorCommand::MoveTo(FloorId(target)));
+                                } else if !passenger_targets[id].contains(&target) {
+                                    // Der obere Wagen fährt sein Ziel nach dem Parken an
+                                    passenger_targets[id].push(target);
+                                }
                             }
-                            ElevatorStatus::ElevatorReadyToCloseTheDoor(id) => {
-                                println!("Elevator {} is ready to close the door", id);
-                                elevators[id as usize]
-                                    .send(ElevatorCommand::CloseDoor)
-                                    .unwrap();
+                            ElevatorStatus::FaultReported(ElevatorId(id), fault, passengers) => {
+                                println!("Control System: Elevator {} reported {:?}", id, fault);
+                                if let Fault::Slow(factor) = fault {
+                                    // Langsamer Fahrstuhl bleibt in Betrieb, wird aber seltener gewählt
+                                    slow_factors[id] = factor;
+                                } else {
+                                    if !passengers.is_empty() {
+                                        println!(
+                                            "Control System: Passengers {:?} affected by the failure of Elevator {}",
+                                            passengers.iter().map(|passenger| passenger.0).collect::<Vec<_>>(),
+                                            id
+                                        );
+                                    }
+                                    lost.push(id);
+                                }
                             }
                         }
                     }
                 }
+                default => {} // Auch ohne Nachrichten regelmäßig Heartbeats senden
+            }
+
+            for (to, command) in outbox.outstanding.overdue() {
+                println!(
+                    "Control System: No reply from {} to {} within {}s, command lost",
+                    to,
+                    command,
+                    REPLY_TIMEOUT.as_secs()
+                );
+            }
+
+            // Notstrom: Fahrstühle im Rahmen des Budgets nacheinander zur untersten Ebene ihrer Zone
+            // bringen und ausladen; danach bleiben die ausgewählten Wagen in Betrieb
+            if let Some(power) = &mut emergency {
+                while let Some(id) = power.next_to_evacuate() {
+                    let lobby = specs[id]
+                        .stops()
+                        .first()
+                        .copied()
+                        .unwrap_or(elevator_floors[id]);
+                    Self::power_event(
+                        &metrics,
+                        format!("Elevator {} lowering to floor {}", id, lobby),
+                    );
+                    powered[id] = true;
+                    outbox.send(&mut lost, id, ElevatorCommand::Power(true));
+                    outbox.send(&mut lost, id, ElevatorCommand::Recall(FloorId(lobby)));
+                }
+                if power.evacuation_done() {
+                    let first_selection = power.in_evacuation();
+                    let selected = power.selected.get_or_insert_with(Vec::new);
+                    let before = selected.len();
+                    let candidates: Vec<usize> = (0..outbox.elevators.len())
+                        .filter(|&id| in_service[id])
+                        .collect();
+                    power::select_cars(&specs, selected, &candidates, power.budget);
+                    for &id in &selected[before..] {
+                        Self::power_event(
+                            &metrics,
+                            format!("Elevator {} stays in service on emergency power", id),
+                        );
+                        powered[id] = true;
+                        outbox.send(&mut lost, id, ElevatorCommand::Power(true));
+                        if fire.is_none() {
+                            outbox.send(&mut lost, id, ElevatorCommand::CloseDoor);
+                        }
+                    }
+                    if first_selection {
+                        for id in candidates.iter().copied().filter(|&id| !powered[id]) {
+                            Self::power_event(
+                                &metrics,
+                                format!("Elevator {} remains shut down", id),
+                            );
+                        }
+                        if fire.is_none() {
+                            unassigned.append(&mut deferred);
+                        }
+                    }
+                }
+            }
+
+            // Ausgefallene Fahrstühle aus dem Betrieb nehmen und ihre Rufe neu vergeben
+            while !lost.is_empty() || !unassigned.is_empty() {
+                for id in std::mem::take(&mut lost) {
+                    if !in_service[id] {
+                        continue;
+                    }
+                    in_service[id] = false;
+                    println!("Control System: Taking Elevator {} out of service", id);
+                    outbox.outstanding.forget(ActorId::Elevator(id));
+                    let targets = std::mem::take(&mut passenger_targets[id]);
+                    if !targets.is_empty() {
+                        // Die Passagiere steigen aus und rufen von ihrer Ebene aus neu
+                        println!(
+                            "Control System: Dropping targets {:?} of Elevator {}",
+                            targets, id
+                        );
+                    }
+                    pending_park[id] = None;
+                    idle_floors[id] = None;
+                    if let Some(recall) = fire
+                        .as_mut()
+                        .filter(|recall| recall.firefighter == Some(id))
+                    {
+                        println!("Control System: Phase II ended, Elevator {} failed", id);
+                        recall.firefighter = None;
+                    }
+                    if let Some(power) = &mut emergency {
+                        // Ein ausgefallener Wagen gibt seinen Anteil am Notstrom frei
+                        power.release(id);
+                        if let Some(selected) = &mut power.selected {
+                            selected.retain(|&selected| selected != id);
+                        }
+                        if powered[id] {
+                            Self::power_event(
+                                &metrics,
+                                format!("Elevator {} failed on emergency power", id),
+                            );
+                        }
+                    }
+                    for (_, call) in assigned_calls[id].drain(..) {
+                        println!(
+                            "Control System: Reassigning request from floor {} going {:?}",
+                            call.floor, call.direction
+                        );
+                        unassigned.push(call);
+                    }
+                }
+
+                if fire.is_some()
+                    || emergency
+                        .as_ref()
+                        .is_some_and(|power| power.in_evacuation())
+                {
+                    deferred.append(&mut unassigned);
+                }
+                // Rufe mit der barrierefreien Taste zuerst zuteilen
+                unassigned.sort_by_key(|call| !call.accessible);
+                let available: Vec<bool> = (0..outbox.elevators.len())
+                    .map(|id| {
+                        in_service[id] && powered[id] && modes[id] == OperatingMode::Automatic
+                    })
+                    .collect();
+                for call in std::mem::take(&mut unassigned) {
+                    let stops: Vec<usize> = (0..outbox.elevators.len())
+                        .map(|id| {
+                            let mut stops: Vec<u8> = assigned_calls[id]
+                                .iter()
+                                .map(|&(position, _)| position)
+                                .collect();
+                            stops.extend(&passenger_targets[id]);
+                            stops.sort_unstable();
+                            stops.dedup();
+                            stops.len()
+                        })
+                        .collect();
+                    let fleet = Fleet {
+                        specs: &specs,
+                        shafts: &shafts,
+                        floors: &elevator_floors,
+                        passenger_counts: &passenger_counts,
+                        deck_loads: &deck_loads,
+                        stops: &stops,
+                        available: &available,
+                        in_service: &in_service,
+                        slow_factors: &slow_factors,
+                        freight_policy,
+                    };
+                    if let Some((best_elevator, deck, position)) = fleet.choose(call) {
+                        println!(
+                            "Control System: Assigning Elevator {} ({}) to floor {}",
+                            best_elevator,
+                            specs[best_elevator].deck_name(deck),
+                            call.floor
+                        );
+                        if let Some(index) = called_again.iter().position(|&other| other == call) {
+                            called_again.remove(index);
+                            assigned_calls[best_elevator].insert(0, (position, call));
+                        } else {
+                            assigned_calls[best_elevator].push((position, call));
+                        }
+                        if !outbox.busy[best_elevator] {
+                            idle_floors[best_elevator] = None;
+                            outbox.send(
+                                &mut lost,
+                                best_elevator,
+                                ElevatorCommand::MoveTo(FloorId(position)),
+                            );
+                        }
+                    } else {
+                        println!(
+                            "Control System: No elevator serves floor {} going {:?}",
+                            call.floor, call.direction
+                        );
+                    }
+                }
             }
         }
     }
+
+    // Nächster Halt eines freien Fahrstuhls. Er fährt das älteste Fahrziel an, ohne Fahrgäste den ältesten
+    // barrierefreien Ruf, für den Platz ist, sonst den ältesten Ruf, und hält auf dem Weg dorthin an jedem weiteren Ziel und an
+    // jedem Ruf in seiner Fahrtrichtung, für den im Deck noch Platz ist; so wartet niemand länger als eine
+    // Fahrt in jede Richtung. Die Fahrt zu einem barrierefreien Ruf bleibt dem Rufenden vorbehalten und hält
+    // für keinen anderen Ruf, sonst wäre der Wagen bei seiner Ankunft wieder zu voll. Die Ebene, die der
+    // Wagen gerade verlässt, zählt nicht als Halt auf dem Weg, sonst hielte ein voller Wagen immer wieder für
+    // einen Ruf, den er nicht bedienen kann. Ein Fahrstuhl, der fährt oder mit offener Tür hält, bekommt keine
+    // weitere Fahrt; sie stünde in seiner Warteschlange vor dem Öffnen der Tür am aktuellen Halt
+    fn next_stop(targets: &[u8], calls: &[(u8, HallCall)], floor: u8, loads: &[u32]) -> Option<u8> {
+        // Das Deck vor der Rufebene liegt `call.floor - position` über der Position des Wagens
+        let room = |stop: u8, call: HallCall| {
+            let load = loads
+                .get((call.floor - stop) as usize)
+                .copied()
+                .unwrap_or(0);
+            call.load.fits(load)
+        };
+        let priority = calls
+            .iter()
+            .find(|&&(stop, call)| call.accessible && room(stop, call));
+        let reserved = targets.is_empty() && priority.is_some();
+        let goal = targets
+            .first()
+            .or(priority.or(calls.first()).map(|(position, _)| position))
+            .copied()?;
+        let direction = if goal > floor {
+            Direction::Up
+        } else {
+            Direction::Down
+        };
+        let on_the_way =
+            |stop: u8| stop != floor && (floor.min(goal)..=floor.max(goal)).contains(&stop);
+        targets
+            .iter()
+            .copied()
+            .filter(|&stop| on_the_way(stop))
+            .chain(
+                calls
+                    .iter()
+                    .filter(|&&(stop, call)| {
+                        !reserved
+                            && on_the_way(stop)
+                            && call.direction == direction
+                            && room(stop, call)
+                    })
+                    .map(|&(stop, _)| stop),
+            )
+            .chain([goal])
+            .min_by_key(|&stop| (stop as i32 - floor as i32).abs())
+    }
+
+    fn nearest_clear_stop(
+        spec: &ElevatorSpec,
+        shaft: &Option<Arc<Shaft>>,
+        id: usize,
+        from: u8,
+        target: u8,
+    ) -> Option<u8> {
+        spec.stops()
+            .into_iter()
+            .filter(|&stop| {
+                shaft.as_ref().is_none_or(|shaft| {
+                    let low = from.min(stop);
+                    let high = from.max(stop) + spec.decks - 1;
+                    shaft.is_clear(id, low, high)
+                })
+            })
+            .min_by_key(|&stop| (stop as i32 - target as i32).abs())
+    }
+
+    // Protokolliert einen Schritt im Notstrombetrieb für den Bericht
+    fn power_event(metrics: &Arc<Mutex<Metrics>>, event: String) {
+        println!("Control System: {}", event);
+        metrics.lock().unwrap().power_event(event);
+    }
+
+    // Abgelehnter Befehl an die Steuerung, z. B. von der Konsole; der Grund steht auch im Protokoll
+    fn reject(responder: Responder, reason: String) {
+        println!("Control System: {}", reason);
+        responder.nack(reason);
+    }
+}
+
+// Befehle der Steuerung an die Fahrstühle; merkt sich, welche noch nicht bestätigt sind
+struct Outbox {
+    elevators: Vec<Sender<Request<ElevatorCommand>>>,
+    replies: Sender<Reply>,
+    outstanding: Outstanding,
+    busy: Vec<bool>, // Fährt oder hält mit offener Tür, siehe `ControlSystem::next_stop`
+}
+
+impl Outbox {
+    // Ein Fahrstuhl, der keine Befehle mehr annimmt, gilt als ausgefallen
+    fn send(&mut self, lost: &mut Vec<usize>, id: usize, command: ElevatorCommand) {
+        if let ElevatorCommand::MoveTo(_) = command {
+            self.busy[id] = true;
+        }
+        let description = format!("{:?}", command);
+        let request = Request::new(command, &self.replies);
+        self.outstanding
+            .sent(&request, ActorId::Elevator(id), description);
+        if self.elevators[id].send(request).is_err() {
+            println!("Control System: Elevator {} is not responding", id);
+            self.outstanding.forget(ActorId::Elevator(id));
+            lost.push(id);
+        }
+    }
+}
+
+// Fahrt von `from` nach `to`; die Ebenen erreicht der Wagen nach der Fahrzeit ab `started`
+struct Trip {
+    from: u8,
+    to: u8,
+    started: Instant,
+    slow_factor: f64, // Beim Losfahren, ein späterer Fehler `Slow` wirkt erst auf die nächste Fahrt
+    purpose: TripPurpose,
+}
+
+// Was der Wagen am Ziel tut
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TripPurpose {
+    Move,        // Ankunft melden und anzeigen
+    Recall,      // Tür öffnen und alle aussteigen lassen, siehe `recall`
+    Firefighter, // Nur melden, die Tür bedient der Feuerwehrmann
 }
 
 struct Elevator {
     id: usize,
     current_floor: u8,
     state: ElevatorState,
-    door: Door,
+    decks: Vec<Deck>,                  // Tür und Passagiere je Deck
     status_tx: Sender<ElevatorStatus>, // Sender für Statusupdates
     passenger_count: usize,
-    elevator_floor_transmitter: Arc<RwLock<Vec<Sender<ElevatorArrived>>>>,
-    elevator_to_passenger_transmitter: Arc<RwLock<Vec<Sender<ElevatorToPassenger>>>>,
-    passenger_to_elevator_receiver: Arc<RwLock<Vec<Receiver<PassengerToElevator>>>>,
-    passengers: Vec<usize>, // Vektor für Passagier-IDs,
+    lanterns: Arc<RwLock<Vec<Lantern>>>, // Ankunftsanzeige je Ebene
+    elevator_to_passenger_transmitter:
+        Arc<RwLock<HashMap<PassengerId, Sender<ElevatorToPassenger>>>>,
+    passenger_to_elevator_receiver: Arc<RwLock<Vec<Receiver<Request<PassengerToElevator>>>>>,
+    spec: ElevatorSpec,        // Zone und Anzahl der Decks
+    shaft: Option<Arc<Shaft>>, // Schacht, den sich der Fahrstuhl mit anderen teilt
+    metrics: Arc<Mutex<Metrics>>,
+    slow_factor: f64, // Fahrten dauern um diesen Faktor länger (Fehler `Slow`)
+    stuck_on_next_trip: bool, // Bleibt bei der nächsten Fahrt zwischen zwei Ebenen stecken
+    out_of_service: bool,
+    commands: Receiver<Request<ElevatorCommand>>,
+    inbox: Inbox, // Befehle der Steuerung und Nachrichten der Passagiere
+    pending_commands: VecDeque<ElevatorCommand>, // Während eines Befehls eingetroffen, Notfallbefehle vorne
+    trip: Option<Trip>,                          // Laufende Fahrt, siehe `Elevator::run`
+    heartbeat: Heartbeat,
+    powered: bool, // Bei Notstrom nur für die Fahrstühle, die fahren dürfen
+    mode: OperatingMode,
+    access: Arc<AccessControl>,                       // Gesicherte Ebenen
+    rider_profiles: HashMap<usize, PassengerProfile>, // Profile der Fahrgäste, die zugestiegen sind
+    freight_policy: FreightPolicy,
+    dashboard: Arc<Dashboard>,         // Live-Anzeige, falls sie läuft
+    observations: Sender<Observation>, // Zum `InvariantMonitor`
 }
 
 impl Elevator {
-    fn new(
+    // Den Zustand besitzt allein der Fahrstuhl-Thread, er liegt nur für einen Neustart hinter einem `Mutex`.
+    // Wer den Fahrkorb beobachtet, liest ihn aus der Anzeige (`Dashboard::cars`); Befehle gehen über `rx`
+    #[allow(clippy::too_many_arguments)]
+    fn spawn(
         id: usize,
-        rx: Receiver<ElevatorCommand>,
+        rx: Receiver<Request<ElevatorCommand>>,
         status_tx: Sender<ElevatorStatus>,
-        elevator_floor_transmitter: Arc<RwLock<Vec<Sender<ElevatorArrived>>>>,
-        elevator_to_passenger_transmitter: Arc<RwLock<Vec<Sender<ElevatorToPassenger>>>>,
-        passenger_to_elevator_receiver: Arc<RwLock<Vec<Receiver<PassengerToElevator>>>>,
-    ) -> Arc<Mutex<Self>> {
+        lanterns: Arc<RwLock<Vec<Lantern>>>,
+        elevator_to_passenger_transmitter: Arc<
+            RwLock<HashMap<PassengerId, Sender<ElevatorToPassenger>>>,
+        >,
+        passenger_to_elevator_receiver: Arc<RwLock<Vec<Receiver<Request<PassengerToElevator>>>>>,
+        spec: ElevatorSpec,
+        shaft: Option<Arc<Shaft>>,
+        metrics: Arc<Mutex<Metrics>>,
+        access: Arc<AccessControl>,
+        freight_policy: FreightPolicy,
+        dashboard: Arc<Dashboard>,
+        observations: Sender<Observation>,
+        supervisor: &Supervisor,
+    ) {
+        metrics
+            .lock()
+            .unwrap()
+            .register_elevator(id, spec.energy.clone());
+        // Startet am untersten gültigen Halt seiner Zone, der im Schacht noch frei ist
+        let start_floor = spec
+            .stops()
+            .into_iter()
+            .find(|&floor| {
+                shaft
+                    .as_ref()
+                    .is_none_or(|shaft| shaft.is_clear(id, floor, floor + spec.decks - 1))
+            })
+            .unwrap_or(0);
+        if let Some(shaft) = &shaft {
+            shaft.place(id, start_floor, start_floor + spec.decks - 1);
+        }
         let elevator = Arc::new(Mutex::new(Self {
             id,
-            current_floor: 0,
-            state: ElevatorState::IdleAtFloor(0),
-            door: Door::new(),
+            current_floor: start_floor,
+            state: ElevatorState::IdleAtFloor,
+            decks: (0..spec.decks).map(|_| Deck::new()).collect(),
             status_tx,
             passenger_count: 0,
-            elevator_floor_transmitter,
+            lanterns,
             elevator_to_passenger_transmitter,
             passenger_to_elevator_receiver,
-            passengers: Vec::new(),
+            spec,
+            shaft,
+            metrics,
+            slow_factor: 1.0,
+            stuck_on_next_trip: false,
+            out_of_service: false,
+            commands: rx,
+            inbox: Inbox::default(),
+            pending_commands: VecDeque::new(),
+            trip: None,
+            heartbeat: supervisor.heartbeat(ActorId::Elevator(id)),
+            powered: true,
+            mode: OperatingMode::Automatic,
+            access,
+            rider_profiles: HashMap::new(),
+            freight_policy,
+            dashboard,
+            observations,
         }));
 
-        let elevator_clone = Arc::clone(&elevator);
-
-        // Elevator-Thread starten
-        thread::spawn(move || {
-            Self::run(elevator_clone, rx);
+        // Elevator-Thread starten; ein Neustart übernimmt den Zustand des abgestürzten Threads
+        supervisor.spawn(ActorId::Elevator(id), move || {
+            if elevator.is_poisoned() {
+                println!("Elevator {}: Recovering state after a crash", id);
+                elevator.clear_poison();
+            }
+            Self::run(Arc::clone(&elevator));
         });
-
-        elevator
     }
 
-    fn run(elevator: Arc<Mutex<Self>>, rx: Receiver<ElevatorCommand>) {
+    // Gesperrt wird nur, solange ein Befehl ausgeführt wird, nicht beim Warten auf den nächsten Befehl
+    // oder auf die nächste Ebene einer Fahrt
+    fn run(elevator: Arc<Mutex<Self>>) {
+        let (commands, mut heartbeat) = {
+            let elevator = elevator.lock().unwrap();
+            elevator.show();
+            (elevator.commands.clone(), elevator.heartbeat.clone())
+        };
         loop {
+            let (command, next_floor) = {
+                let mut elevator = elevator.lock().unwrap();
+                elevator.heartbeat.beat();
+                match elevator.next_floor() {
+                    Some(next_floor) => (None, Some(next_floor)),
+                    None => (elevator.next_pending(), None),
+                }
+            };
+            if let Some((floor, at)) = next_floor {
+                // Unterwegs nimmt der Wagen Befehle an; ein Notfallbefehl beendet die Fahrt in der nächsten Ebene
+                let request = heartbeat.recv(&commands, Some(at));
+                let mut elevator = elevator.lock().unwrap();
+                match request {
+                    Ok(request) => {
+                        if let Some(command) = elevator.admit(request) {
+                            elevator.queue_command(command);
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => elevator.advance(floor),
+                    Err(RecvTimeoutError::Disconnected) => {
+                        clock::sleep_until(at);
+                        elevator.advance(floor);
+                    }
+                }
+                continue;
+            }
+            let request = match command {
+                Some(_) => None,
+                None => clock::recv_timeout(&commands, HEARTBEAT_INTERVAL).ok(),
+            };
             let mut elevator = elevator.lock().unwrap();
-            select! {
-                recv(rx) -> command => {
-                    if let Ok(command) = command {
-                        // Nur während der Verarbeitung sperren
-                        {
-                            match command {
-                                ElevatorCommand::MoveTo(floor) => {
-                                    println!("Elevator {}: Received move request to floor {}", elevator.id, floor);
-                                    elevator.move_to(floor);
-                                    elevator.status_tx
-                                            .send(ElevatorStatus::ArrivedAtFloor(elevator.id, elevator.current_floor))
-                                            .unwrap();
-
-                                }
-                                ElevatorCommand::OpenDoor => {
-                                    println!("Elevator {}: Received open door command", elevator.id);
-                                    elevator.open_door();
-                                    elevator.status_tx
-                                        .send(ElevatorStatus::DoorOpened(elevator.id, elevator.current_floor))
-                                        .unwrap();
-                                    // Kopiere die Liste der Passagiere und ihre Transmitter
-                                    // let passengers = elevator.passengers.clone();
-                                    let passenger_transmitters = elevator
-                                        .elevator_to_passenger_transmitter
-                                        .read()
-                                        .unwrap()
-                                        .clone();
-
-                                    // Iteriere über die Passagiere
-                                    for passenger_id in &elevator.passengers {
-                                        if let Some(transmitter) = passenger_transmitters.get(*passenger_id) {
-                                            transmitter
-                                                .send(ElevatorToPassenger::YouCanExit(elevator.current_floor))
-                                                .expect("Failed to send YouCanExit message");
-                                        }
-                                    }
-                                    
-                                    // elevator.status_tx
-                                    //     .send(ElevatorStatus::ArrivedAtFloor(elevator.id, elevator.current_floor))
-                                    //     .unwrap();
-                                    // Schleife mit Timeout
-                                    let start_time = std::time::Instant::now();
-                                    let receiver = elevator
-                                                                                .passenger_to_elevator_receiver
-                                                                                .read()
-                                                                                .unwrap()[elevator.id]
-                                                                                .clone(); // Klonen des Receivers, um den Lesezugriff sofort freizugeben
-                                    loop {
-                                        if start_time.elapsed() >= std::time::Duration::from_secs(10) {
-                                            println!(
-                                                "Elevator {}: Time limit of 10 seconds reached, closing door",
-                                                elevator.id
-                                            );
-                                            elevator.status_tx
-                                                .send(ElevatorStatus::ElevatorIdle(elevator.id, elevator.current_floor))
-                                                .unwrap();
-                                            break;
-                                        }
+            let command = command.or_else(|| request.and_then(|request| elevator.admit(request)));
+            if let Some(command) = command {
+                elevator.handle_command(command);
+                elevator.show();
+            }
+
+            if elevator.out_of_service {
+                println!("Elevator {}: Out of service, shutting down", elevator.id);
+                return;
+            }
+        }
+    }
 
+    // Bestätigt einen Befehl, sobald er angenommen ist; ausgeführt wird er sofort oder aus der Warteschlange.
+    // Ein abgeschalteter Fahrstuhl lehnt alles ab
+    fn admit(&mut self, request: Request<ElevatorCommand>) -> Option<ElevatorCommand> {
+        let (command, responder) = self.inbox.admit(request)?;
+        if self.out_of_service {
+            responder.nack(format!("Elevator {} is out of service", self.id));
+            return None;
+        }
+        responder.ack();
+        Some(command)
+    }
 
-                                        // Prüfe auf Passagier-Nachrichten
-                                        if let Ok(message) = receiver.try_recv()
-                                        {
-                                            match message {
-                                                PassengerToElevator::Enter(passenger_id) => {
-                                                    if elevator.passengers.len() >= 2 {
-                                                        println!(
-                                                            "Elevator {}: Capacity reached, ignoring Passenger {}",
-                                                            elevator.id, passenger_id
-                                                        );
-                                                        break;
-                                                    }
-
-                                                    println!("Elevator {}: Passenger {} entered", elevator.id, passenger_id);
-                                                    elevator.passengers.push(passenger_id as usize);
-                                                    elevator.passenger_count += 1;
-
-                                                    // Sende Bestätigung
-                                                    elevator
-                                                        .elevator_to_passenger_transmitter
-                                                        .read()
-                                                        .unwrap()
-                                                        .get(passenger_id as usize)
-                                                        .unwrap()
-                                                        .send(ElevatorToPassenger::YouEntered())
-                                                        .unwrap();
-
-                                                    if elevator.passengers.len() == 2 {
-                                                        println!("Elevator {}: Reached maximum capacity", elevator.id);
-                                                        elevator.status_tx
-                                                            .send(ElevatorStatus::ElevatorReadyToCloseTheDoor(elevator.id as u8))
-                                                            .unwrap();
-                                                        break;
-                                                    }
-                                                }
-                                                PassengerToElevator::Exit(passenger_id) => {
-                                                    println!("Elevator {}: Passenger {} exited", elevator.id, passenger_id);
-                                                    elevator.passengers.retain(|&x| x != passenger_id as usize);
-                                                    elevator.passenger_count -= 1;
-                                                    if elevator.passengers.is_empty() {
-                                                        println!("Elevator {}: No more passengers, closing door", elevator.id);
-                                                        break;
-                                                    }
-                                                }
-                                                PassengerToElevator::PressedButton(target_floor) => {
-                                                    println!("Elevator {}: Passenger pressed button for floor {}", elevator.id, target_floor);
-                                                    elevator.status_tx
-                                                        .send(ElevatorStatus::PassengerTarget(elevator.id, vec![target_floor]))
-                                                        .unwrap();
-                                                }
-                                                _ => {
-                                                    println!("Elevator {}: Received unexpected message", elevator.id);
-                                                }
-                                            }
-                                        } else {
-                                        }
-                                    }
+    // Zuerst Befehle, die während eines anderen Befehls eingetroffen sind; eine Fahrt wartet dort,
+    // bis die Tür zu ist
+    fn next_pending(&mut self) -> Option<ElevatorCommand> {
+        match self.pending_commands.front() {
+            Some(ElevatorCommand::MoveTo(FloorId(floor))) if self.door_holds(*floor) => None,
+            Some(_) => self.pending_commands.pop_front(),
+            None => None,
+        }
+    }
+
+    // Führt einen Befehl aus; Türzeit und Zielwahl brechen bei einem Notfallbefehl ab. Eine Fahrt beginnt
+    // hier nur, die Ebenen fährt `run` ohne Sperre ab
+    fn handle_command(&mut self, command: ElevatorCommand) {
+        match command {
+            // Die Steuerung vergibt Rufe auch an Fahrstühle, die gerade mit offener Tür halten
+            ElevatorCommand::MoveTo(FloorId(floor)) if self.door_holds(floor) => {
+                println!(
+                    "Elevator {}: Move request to floor {} waits until the door is closed",
+                    self.id, floor
+                );
+                self.pending_commands.push_front(command);
+            }
+            ElevatorCommand::MoveTo(FloorId(floor)) => {
+                println!(
+                    "Elevator {}: Received move request to floor {}",
+                    self.id, floor
+                );
+                self.move_to(floor);
+            }
+            ElevatorCommand::OpenDoor(dwell) => {
+                println!("Elevator {}: Received open door command", self.id);
+                let alighting = self.open_door();
+                // Steigt hier niemand aus und kann niemand zusteigen, schließt die Tür gleich wieder
+                let dwell = if alighting || (self.anyone_waiting() && !self.full()) {
+                    dwell
+                } else {
+                    Duration::ZERO
+                };
+                for deck in self.decks.iter_mut() {
+                    deck.door.set_dwell(dwell);
+                }
+                self.report(ElevatorStatus::DoorOpened(
+                    ElevatorId(self.id),
+                    FloorId(self.current_floor),
+                ));
+                let passenger_transmitters = self.elevator_to_passenger_transmitter.read().unwrap();
+
+                // Iteriere über die Passagiere, jedes Deck hält vor seiner eigenen Ebene
+                for (deck_index, deck) in self.decks.iter().enumerate() {
+                    let deck_floor = self.current_floor + deck_index as u8;
+                    for passenger_id in &deck.passengers {
+                        if let Some(transmitter) =
+                            passenger_transmitters.get(&PassengerId(*passenger_id))
+                        {
+                            if transmitter
+                                .send(ElevatorToPassenger::YouCanExit(FloorId(deck_floor)))
+                                .is_err()
+                            {
+                                println!(
+                                    "Elevator {}: Passenger {} no longer listens",
+                                    self.id, passenger_id
+                                );
+                            }
+                        }
+                    }
+                }
+                drop(passenger_transmitters);
+
+                // Schleife mit Timeout
+                let start_time = Instant::now();
+                let mut refused = false; // Abgewiesene warten in der Ebene, bis Platz frei wird
+                let receiver = self.passenger_to_elevator_receiver.read().unwrap()[self.id].clone(); // Klonen des Receivers, um den Lesezugriff sofort freizugeben
+                loop {
+                    self.heartbeat.beat();
+                    if self.queue_commands() {
+                        println!("Elevator {}: Door time interrupted", self.id);
+                        break;
+                    }
+                    // Bis zum Ende der längsten Offenhaltezeit warten; im Liftführerbetrieb ohne Frist
+                    let until = if self.mode == OperatingMode::Attendant {
+                        Instant::now() + HEARTBEAT_INTERVAL
+                    } else {
+                        self.decks
+                            .iter()
+                            .filter_map(|deck| deck.door.dwell_until)
+                            .max()
+                            .unwrap_or_else(Instant::now)
+                    };
+                    // Im Liftführerbetrieb schließt nur der Liftführer die Tür
+                    if self.decks.iter().all(|deck| deck.door.dwell_elapsed())
+                        && self.mode != OperatingMode::Attendant
+                    {
+                        println!(
+                            "Elevator {}: Door dwell time of {:.1}s elapsed, closing door",
+                            self.id,
+                            start_time.elapsed().as_secs_f64()
+                        );
+                        self.report(ElevatorStatus::ElevatorIdle(
+                            ElevatorId(self.id),
+                            FloorId(self.current_floor),
+                        ));
+                        break;
+                    }
+
+                    // Auf Passagier-Nachrichten warten, höchstens bis die Tür schließen soll
+                    if let Some((message, responder)) = self
+                        .passenger_message(&receiver, until)
+                        .and_then(|request| self.inbox.admit(request))
+                    {
+                        match message {
+                            PassengerToElevator::Enter {
+                                boarders,
+                                deck,
+                                floor: FloorId(floor),
+                            } => {
+                                if self.current_floor + deck != floor {
+                                    responder.nack(format!(
+                                        "Elevator {} is not at floor {}",
+                                        self.id, floor
+                                    ));
+                                    continue;
                                 }
-                                ElevatorCommand::CloseDoor => {
-                                    println!("Elevator {}: Received close door command", elevator.id);
-                                    elevator.close_door();
-                                    elevator.status_tx
-                                        .send(ElevatorStatus::DoorClosed(elevator.id, elevator.current_floor))
-                                        .unwrap();
-                                    // // Kopiere die Liste der Passagiere und ihre Transmitter
-                                    // let passengers = elevator.passengers.clone();
-                                    let passenger_transmitters = elevator
+                                responder.ack();
+                                // Eine Gruppe steigt gemeinsam ein; wer keinen Platz mehr findet, wartet auf den nächsten Fahrkorb
+                                let mut entered = false;
+                                for (PassengerId(passenger_id), profile) in boarders {
+                                    let reply = if self.board(passenger_id, deck, profile) {
+                                        entered = true;
+                                        ElevatorToPassenger::YouEntered {
+                                            elevator: ElevatorId(self.id),
+                                            deck,
+                                        }
+                                    } else {
+                                        refused = true;
+                                        self.metrics.lock().unwrap().boarding_refused(passenger_id);
+                                        ElevatorToPassenger::NoRoom
+                                    };
+                                    if let Some(transmitter) = self
                                         .elevator_to_passenger_transmitter
                                         .read()
                                         .unwrap()
-                                        .clone();
-
-                                    // Iteriere über die Passagiere
-                                    for passenger_id in &elevator.passengers {
-                                        if let Some(transmitter) = passenger_transmitters.get(*passenger_id) {
-                                            println!(
-                                                "Elevator {}: Informing Passenger {} to choose their floor",
-                                                elevator.id, passenger_id
-                                            );
-
-                                            // Sende `YouCanChooseFloor` an den entsprechenden Passagier
-                                            transmitter
-                                                .send(ElevatorToPassenger::YouCanChooseFloor)
-                                                .expect("Failed to send YouCanChooseFloor message");
-                                        } else {
-                                            println!(
-                                                "Elevator {}: No transmitter found for Passenger {}",
-                                                elevator.id, passenger_id
-                                            );
-                                        }
+                                        .get(&PassengerId(passenger_id))
+                                    {
+                                        let _ = transmitter.send(reply);
                                     }
-                                    // 2 Sekunden lang gedrückte Knöpfe sammeln
-                                    let receiver = elevator.passenger_to_elevator_receiver.read().unwrap()[elevator.id].clone();
-                                    let mut pressed_buttons = Vec::new();
-                                    let start_time = std::time::Instant::now();
-
-                                    while start_time.elapsed() < std::time::Duration::from_secs(2) {
-                                        if let Ok(message) = receiver.try_recv() {
-                                            match message {
-                                                PassengerToElevator::PressedButton(target_floor) => {
-                                                    println!("Elevator {}: Passenger pressed button for floor {}", elevator.id, target_floor);
-                                                    pressed_buttons.push(target_floor);
-                                                    elevator.status_tx
-                                                        .send(ElevatorStatus::PassengerTarget(elevator.id, pressed_buttons.clone()))
-                                                        .unwrap();
-                                                }
-                                                _ => {}
-                                            }
-                                        }
+                                }
+                                self.show();
+
+                                // Wer hier aussteigt, braucht die Tür noch; nach einer Abweisung bleibt sie ebenfalls offen
+                                if entered && !alighting && self.full() {
+                                    println!("Elevator {}: Reached maximum capacity", self.id);
+                                    self.report(ElevatorStatus::ElevatorReadyToCloseTheDoor(
+                                        ElevatorId(self.id),
+                                    ));
+                                    break;
+                                }
+                            }
+                            PassengerToElevator::Exit(PassengerId(passenger_id)) => {
+                                // Nur wer mitfährt, kann aussteigen; sonst liefe der Zähler unter null
+                                let riding = self
+                                    .decks
+                                    .iter()
+                                    .any(|deck| deck.passengers.contains(&passenger_id));
+                                let Some(count) =
+                                    self.passenger_count.checked_sub(1).filter(|_| riding)
+                                else {
+                                    responder.nack(format!(
+                                        "Passenger {} is not in Elevator {}",
+                                        passenger_id, self.id
+                                    ));
+                                    continue;
+                                };
+                                responder.ack();
+                                println!("Elevator {}: Passenger {} exited", self.id, passenger_id);
+                                let profile = self.rider_profile(passenger_id);
+                                for deck in self.decks.iter_mut() {
+                                    if deck.alight(passenger_id, profile.space()) {
+                                        deck.door.extend_dwell(profile.dwell());
                                     }
+                                }
+                                self.passenger_count = count;
+                                self.show();
+                                // Der frei gewordene Platz geht an die, die eben abgewiesen wurden
+                                if refused {
+                                    refused = false;
+                                    self.announce_arrival();
+                                } else if self.passenger_count == 0
+                                    && self.mode != OperatingMode::Attendant
+                                    && !self.anyone_waiting()
+                                {
+                                    println!(
+                                        "Elevator {}: No more passengers, closing door",
+                                        self.id
+                                    );
+                                    self.report(ElevatorStatus::ElevatorIdle(
+                                        ElevatorId(self.id),
+                                        FloorId(self.current_floor),
+                                    ));
+                                    break;
+                                }
+                            }
+                            PassengerToElevator::PressedButton {
+                                passenger: PassengerId(passenger_id),
+                                deck,
+                                floor: FloorId(target_floor),
+                                badges,
+                            } => {
+                                responder.ack();
+                                println!(
+                                    "Elevator {}: Passenger pressed button for floor {}",
+                                    self.id, target_floor
+                                );
+                                if let Some(position) =
+                                    self.car_call(passenger_id, deck, target_floor, &badges)
+                                {
+                                    self.report(ElevatorStatus::PassengerTarget(
+                                        ElevatorId(self.id),
+                                        vec![FloorId(position)],
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            ElevatorCommand::CloseDoor => {
+                println!("Elevator {}: Received close door command", self.id);
+                self.close_door();
+                if self.decks.iter().any(|deck| deck.door.jammed) {
+                    // Mit klemmender Tür darf der Fahrstuhl nicht mehr fahren
+                    self.fail(Fault::DoorJam);
+                } else {
+                    self.report(ElevatorStatus::PassengerCount(
+                        ElevatorId(self.id),
+                        self.passenger_count,
+                        self.deck_loads(),
+                    ));
+                    let passenger_transmitters =
+                        self.elevator_to_passenger_transmitter.read().unwrap();
+
+                    // Iteriere über die Passagiere aller Decks
+                    for passenger_id in self.decks.iter().flat_map(|deck| &deck.passengers) {
+                        if let Some(transmitter) =
+                            passenger_transmitters.get(&PassengerId(*passenger_id))
+                        {
+                            println!(
+                                "Elevator {}: Informing Passenger {} to choose their floor",
+                                self.id, passenger_id
+                            );
+
+                            // Sende `YouCanChooseFloor` an den entsprechenden Passagier
+                            if transmitter
+                                .send(ElevatorToPassenger::YouCanChooseFloor)
+                                .is_err()
+                            {
+                                println!(
+                                    "Elevator {}: Passenger {} no longer listens",
+                                    self.id, passenger_id
+                                );
+                            }
+                        } else {
+                            println!(
+                                "Elevator {}: No transmitter found for Passenger {}",
+                                self.id, passenger_id
+                            );
+                        }
+                    }
+                    drop(passenger_transmitters);
+                    // 2 Sekunden lang gedrückte Knöpfe sammeln, sofern jemand mitfährt; erst danach meldet der
+                    // Fahrstuhl die geschlossene Tür, damit die Steuerung den nächsten Halt mit den Fahrzielen wählt
+                    let receiver =
+                        self.passenger_to_elevator_receiver.read().unwrap()[self.id].clone();
+                    let mut pressed_buttons = Vec::new();
+                    let until = Instant::now()
+                        + if self.passenger_count > 0 {
+                            Duration::from_secs(2)
+                        } else {
+                            Duration::ZERO
+                        };
 
+                    // Auch ohne Fahrgäste noch die Anfragen beantworten, die vor dem Schließen eintrafen
+                    while Instant::now() < until || !receiver.is_empty() {
+                        self.heartbeat.beat();
+                        if self.queue_commands() {
+                            break;
+                        }
+                        if let Some((message, responder)) = self
+                            .passenger_message(&receiver, until)
+                            .and_then(|request| self.inbox.admit(request))
+                        {
+                            match message {
+                                PassengerToElevator::PressedButton {
+                                    passenger: PassengerId(passenger_id),
+                                    deck,
+                                    floor: FloorId(target_floor),
+                                    badges,
+                                } => {
+                                    responder.ack();
+                                    println!(
+                                        "Elevator {}: Passenger pressed button for floor {}",
+                                        self.id, target_floor
+                                    );
+                                    // Das Ziel wird als Position des unteren Decks gemeldet
+                                    let Some(position) =
+                                        self.car_call(passenger_id, deck, target_floor, &badges)
+                                    else {
+                                        continue;
+                                    };
+                                    pressed_buttons.push(FloorId(position));
+                                    self.report(ElevatorStatus::PassengerTarget(
+                                        ElevatorId(self.id),
+                                        pressed_buttons.clone(),
+                                    ));
                                 }
-                            
-                        } // `Mutex` wird hier automatisch freigegeben
+                                // Ein- und Aussteigen nur bei offener Tür
+                                PassengerToElevator::Enter { .. }
+                                | PassengerToElevator::Exit(_) => {
+                                    responder.nack(format!(
+                                        "the door of Elevator {} is closed",
+                                        self.id
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                    self.report(ElevatorStatus::DoorClosed(
+                        ElevatorId(self.id),
+                        FloorId(self.current_floor),
+                    ));
+                }
+            }
+            ElevatorCommand::InjectFault(fault) => {
+                println!("Elevator {}: Fault injected: {:?}", self.id, fault);
+                match fault {
+                    Fault::OutOfService => self.fail(fault),
+                    Fault::DoorJam => {
+                        for deck in self.decks.iter_mut() {
+                            deck.door.jammed = true;
+                        }
+                    }
+                    Fault::StuckBetweenFloors => self.stuck_on_next_trip = true,
+                    Fault::Slow(factor) => {
+                        self.slow_factor = factor;
+                        self.report(ElevatorStatus::FaultReported(
+                            ElevatorId(self.id),
+                            fault,
+                            Vec::new(),
+                        ));
+                    }
+                }
+            }
+            ElevatorCommand::Recall(FloorId(floor)) => {
+                println!("Elevator {}: Recall to floor {}", self.id, floor);
+                self.pending_commands
+                    .retain(|pending| !matches!(pending, ElevatorCommand::MoveTo(_)));
+                self.recall(floor);
+            }
+            ElevatorCommand::FirefighterMove(FloorId(floor)) => {
+                println!("Elevator {}: Firefighter move to floor {}", self.id, floor);
+                if self
+                    .decks
+                    .iter()
+                    .any(|deck| !matches!(deck.door.state, DoorState::Closed))
+                {
+                    println!("Elevator {}: Cannot move while door is open!", self.id);
+                } else {
+                    self.depart(floor, TripPurpose::Firefighter);
+                }
+            }
+            ElevatorCommand::FirefighterDoor(button, held) => {
+                println!(
+                    "Elevator {}: Firefighter holds door {:?} for {:.1}s",
+                    self.id,
+                    button,
+                    held.as_secs_f64()
+                );
+                self.hold_door(button, held);
+            }
+            ElevatorCommand::Power(powered) => self.set_power(powered),
+            ElevatorCommand::SetMode(mode) => self.set_mode(mode),
+        }
+    }
+
+    // Befehle, die während eines anderen Befehls eintreffen, für später aufheben. Gibt `true` zurück,
+    // wenn die laufende Arbeit abgebrochen werden soll, siehe `interrupted`
+    fn queue_commands(&mut self) -> bool {
+        while let Ok(request) = self.commands.try_recv() {
+            if let Some(command) = self.admit(request) {
+                self.queue_command(command);
+            }
+        }
+        self.interrupted()
+    }
+
+    // Notfallbefehle überholen alle vorgemerkten Befehle, ein Rückruf verwirft dabei alle normalen Fahrten.
+    // Das Schließen der Tür durch den Liftführer kommt ebenfalls nach vorne; Stromversorgung und
+    // Betriebsart wirken sofort.
+    fn queue_command(&mut self, command: ElevatorCommand) {
+        match command {
+            ElevatorCommand::Recall(_) => {
+                self.pending_commands.retain(|pending| {
+                    !matches!(
+                        pending,
+                        ElevatorCommand::MoveTo(_)
+                            | ElevatorCommand::OpenDoor(_)
+                            | ElevatorCommand::CloseDoor
+                    )
+                });
+                self.pending_commands.push_front(command);
+            }
+            ElevatorCommand::InjectFault(Fault::OutOfService) => {
+                self.pending_commands.push_front(command)
+            }
+            ElevatorCommand::CloseDoor
+                if self.mode == OperatingMode::Attendant
+                    && self
+                        .decks
+                        .iter()
+                        .any(|deck| matches!(deck.door.state, DoorState::Open)) =>
+            {
+                self.pending_commands.push_front(command);
+            }
+            ElevatorCommand::Power(powered) => self.set_power(powered),
+            ElevatorCommand::SetMode(mode) => self.set_mode(mode),
+            _ => self.pending_commands.push_back(command),
+        }
+    }
+
+    // Steht vorne ein Befehl, der Türzeit und Zielwahl abbricht: Rückruf, Abschalten oder das Schließen
+    // der Tür durch den Liftführer
+    fn interrupted(&self) -> bool {
+        match self.pending_commands.front() {
+            Some(
+                ElevatorCommand::Recall(_) | ElevatorCommand::InjectFault(Fault::OutOfService),
+            ) => true,
+            Some(ElevatorCommand::CloseDoor) => {
+                self.mode == OperatingMode::Attendant
+                    && self
+                        .decks
+                        .iter()
+                        .any(|deck| matches!(deck.door.state, DoorState::Open))
+            }
+            _ => false,
+        }
+    }
+
+    // Wartet `duration`, nimmt dabei aber weiter Befehle an und sendet Heartbeats. Ein Notfallbefehl,
+    // der währenddessen eintrifft, steht danach vorne und wird als Nächstes ausgeführt
+    fn pause(&mut self, duration: Duration) {
+        let until = Instant::now() + duration;
+        loop {
+            self.heartbeat.beat();
+            let left = until.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return;
+            }
+            match clock::recv_timeout(&self.commands, left.min(HEARTBEAT_INTERVAL)) {
+                Ok(request) => {
+                    if let Some(command) = self.admit(request) {
+                        self.queue_command(command);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    clock::sleep(left);
+                    return;
+                }
+            }
+        }
+    }
+
+    // Wartet bis `until` auf eine Nachricht eines Passagiers, nimmt dabei Befehle an; höchstens
+    // `HEARTBEAT_INTERVAL` lang, damit der Aufrufer Heartbeats sendet und Notfallbefehle prüft
+    fn passenger_message(
+        &mut self,
+        receiver: &Receiver<Request<PassengerToElevator>>,
+        until: Instant,
+    ) -> Option<Request<PassengerToElevator>> {
+        let until = until.min(Instant::now() + HEARTBEAT_INTERVAL);
+        clock::wait(
+            vec![clock::ready(&self.commands), clock::ready(receiver)],
+            Some(until),
+        );
+        select_biased! {
+            recv(self.commands) -> request => match request {
+                Ok(request) => {
+                    if let Some(command) = self.admit(request) {
+                        self.queue_command(command);
                     }
+                    None
                 }
+                Err(_) => clock::recv_deadline(receiver, until).ok(),
+            },
+            recv(receiver) -> request => match request {
+                Ok(request) => Some(request),
+                Err(_) => {
+                    clock::sleep_until(until);
+                    None
+                }
+            },
+            default => None,
+        }
+    }
+
+    // Lässt einen Passagier in `deck` einsteigen; `false`, wenn kein Platz ist oder die Last nicht passt
+    fn board(&mut self, passenger_id: usize, deck: u8, profile: PassengerProfile) -> bool {
+        // Wiederholte Anfrage eines Passagiers, der schon eingestiegen ist: nur erneut bestätigen
+        if self
+            .decks
+            .iter()
+            .any(|d| d.passengers.contains(&(passenger_id)))
+        {
+            return true;
+        }
+        let deck_name = self.spec.deck_name(deck);
+        // Betten und Transportwagen nur im Lastenaufzug, Lasten je nach Regel nicht mit Fahrgästen
+        let riders: Vec<PassengerProfile> = self
+            .decks
+            .iter()
+            .flat_map(|d| d.passengers.iter())
+            .map(|&id| self.rider_profile(id))
+            .collect();
+        if !car_accepts(&self.spec, profile) || !self.freight_policy.may_board(profile, &riders) {
+            println!(
+                "Elevator {}: Passenger {} ({:?}) may not ride with the current load, ignoring",
+                self.id, passenger_id, profile
+            );
+            return false;
+        }
+        let priority = self.priority_boarders(deck, passenger_id, profile);
+        let Some(deck) = self.decks.get_mut(deck as usize) else {
+            println!(
+                "Elevator {}: Passenger {} chose an unknown deck",
+                self.id, passenger_id
+            );
+            return false;
+        };
+        // Ein volles Deck nimmt niemanden mehr auf, das andere Deck kann weiter einsteigen lassen.
+        // Ein leeres Deck nimmt jeden auf, auch wenn er mehr Platz braucht.
+        if !profile.fits(deck.load) {
+            println!(
+                "Elevator {}: Capacity reached on {}, ignoring Passenger {} ({:?})",
+                self.id, deck_name, passenger_id, profile
+            );
+            return false;
+        }
+        // Wer mit der barrierefreien Taste gerufen hat, steigt zuerst ein: passt er jetzt noch, nach diesem
+        // Fahrgast aber nicht mehr, bleibt der Platz für ihn frei
+        if let Some((waiting_id, waiting)) = priority.into_iter().find(|(_, waiting)| {
+            waiting.fits(deck.load) && !waiting.fits(deck.load + profile.space())
+        }) {
+            println!(
+                "Elevator {}: Keeping room on {} for Passenger {} ({:?}), ignoring Passenger {} ({:?})",
+                self.id, deck_name, waiting_id, waiting, passenger_id, profile
+            );
+            return false;
+        }
+
+        deck.board(passenger_id, profile.space());
+        deck.door.extend_dwell(profile.dwell());
+        self.rider_profiles.insert(passenger_id, profile);
+        println!(
+            "Elevator {}: Passenger {} entered ({}, {:?})",
+            self.id, passenger_id, deck_name, profile
+        );
+        self.passenger_count += 1;
+        true
+    }
+
+    // Wartende mit Vorrang vor `deck`, die dieses Deck mitnehmen kann; nicht für Fahrgäste, die selbst Vorrang haben
+    fn priority_boarders(
+        &self,
+        deck: u8,
+        passenger_id: usize,
+        profile: PassengerProfile,
+    ) -> Vec<(usize, PassengerProfile)> {
+        if profile.uses_accessible_button() {
+            return Vec::new();
+        }
+        let lanterns = self.lanterns.read().unwrap();
+        let Some(lantern) = lanterns.get((self.current_floor + deck) as usize) else {
+            return Vec::new();
+        };
+        lantern
+            .priority()
+            .into_iter()
+            .filter(|&(waiting_id, waiting, to)| {
+                waiting_id != passenger_id
+                    && car_accepts(&self.spec, waiting)
+                    && self.spec.position_for(deck, to).is_some()
+            })
+            .map(|(waiting_id, waiting, _)| (waiting_id, waiting))
+            .collect()
+    }
+
+    fn deck_loads(&self) -> Vec<u32> {
+        self.decks.iter().map(|deck| deck.load).collect()
+    }
+
+    // Profil eines Fahrgasts im Fahrkorb; unbekannte Fahrgäste zählen als `Standard`
+    fn rider_profile(&self, passenger_id: usize) -> PassengerProfile {
+        self.rider_profiles
+            .get(&passenger_id)
+            .copied()
+            .unwrap_or(PassengerProfile::Standard)
+    }
+
+    // Aktueller Zustand für die Live-Anzeige; alle Decks öffnen und schließen gemeinsam
+    fn show(&self) {
+        self.dashboard.update(self.id, |car| {
+            car.floor = self.current_floor;
+            car.moving = match self.state {
+                ElevatorState::Moving(from, to) if to > from => Some(Direction::Up),
+                ElevatorState::Moving(_, _) => Some(Direction::Down),
+                _ => None,
+            };
+            car.door = self.decks[0].door.state;
+            car.loads = self.decks.iter().map(|deck| deck.load).collect();
+            car.riders = self
+                .decks
+                .iter()
+                .map(|deck| {
+                    deck.passengers
+                        .iter()
+                        .map(|&id| (id, self.rider_profile(id)))
+                        .collect()
+                })
+                .collect();
+            car.mode = self.mode;
+            car.powered = self.powered;
+            car.out_of_service = self.out_of_service;
+            car.slow_factor = self.slow_factor;
+            car.jammed = self.decks.iter().any(|deck| deck.door.jammed);
+        });
+    }
+
+    fn set_mode(&mut self, mode: OperatingMode) {
+        println!("Elevator {}: Operating mode {:?}", self.id, mode);
+        self.mode = mode;
+    }
+
+    fn set_power(&mut self, powered: bool) {
+        if self.powered != powered {
+            println!(
+                "Elevator {}: Power {}",
+                self.id,
+                if powered { "on" } else { "off" }
+            );
+        }
+        self.powered = powered;
+    }
+
+    // Statusmeldung an die Steuerung; ist sie nicht erreichbar, wird das nur protokolliert
+    fn report(&self, status: ElevatorStatus) {
+        if self.status_tx.send(status).is_err() {
+            println!("Elevator {}: Control system unreachable", self.id);
+        }
+    }
+
+    // Fahrstuhl außer Betrieb nehmen. Steht er in einer Ebene, steigen die Passagiere aus;
+    // steckt er zwischen zwei Ebenen fest, bleiben sie eingeschlossen.
+    fn fail(&mut self, fault: Fault) {
+        let affected: Vec<usize> = self
+            .decks
+            .iter()
+            .flat_map(|deck| deck.passengers.iter().copied())
+            .collect();
+        println!(
+            "Elevator {}: Out of service ({:?}), {} passenger(s) affected",
+            self.id,
+            fault,
+            affected.len()
+        );
+        if fault != Fault::StuckBetweenFloors {
+            self.open_door();
+            self.unload(ElevatorToPassenger::OutOfService);
+        }
+        self.metrics
+            .lock()
+            .unwrap()
+            .elevator_failed(self.id, fault, self.current_floor, &affected);
+        let affected = affected.into_iter().map(PassengerId).collect();
+        self.report(ElevatorStatus::FaultReported(
+            ElevatorId(self.id),
+            fault,
+            affected,
+        ));
+        self.out_of_service = true;
+    }
+
+    // Alle Passagiere müssen aussteigen; `message` sagt ihnen, in welcher Ebene und warum
+    fn unload(&mut self, message: fn(FloorId) -> ElevatorToPassenger) {
+        let passenger_transmitters = self.elevator_to_passenger_transmitter.read().unwrap();
+        for (deck_index, deck) in self.decks.iter_mut().enumerate() {
+            let deck_floor = self.current_floor + deck_index as u8;
+            for passenger_id in deck.passengers.drain(..) {
+                if let Some(transmitter) = passenger_transmitters.get(&PassengerId(passenger_id)) {
+                    let _ = transmitter.send(message(FloorId(deck_floor)));
                 }
             }
-            
+            deck.load = 0;
         }
+        self.passenger_count = 0;
+        self.dashboard
+            .update(self.id, |car| car.car_buttons.clear());
+        self.show();
     }
 
+    // Brandfall Phase I und Notstrom: Tür schließen, ohne Halt zur Rückrufebene, dort öffnen und alle aussteigen lassen
+    fn recall(&mut self, recall_floor: u8) {
+        self.close_door();
+        if self.decks.iter().any(|deck| deck.door.jammed) {
+            self.fail(Fault::DoorJam);
+            return;
+        }
+        self.depart(recall_floor, TripPurpose::Recall);
+    }
+
+    // Offene Tür in einer anderen Ebene als `target_floor`: der Fahrbefehl muss warten
+    fn door_holds(&self, target_floor: u8) -> bool {
+        target_floor != self.current_floor
+            && self
+                .decks
+                .iter()
+                .any(|deck| matches!(deck.door.state, DoorState::Open))
+    }
+
+    // Ohne Fahrt meldet der Fahrstuhl gleich, wo er steht; versperrt ein anderer Wagen im Schacht den Weg,
+    // bleibt der Wagen stecken oder hat er keinen Strom, meldet er keine Ankunft
     fn move_to(&mut self, target_floor: u8) {
-        if (self.current_floor == 0 && target_floor < 0)
-            || (self.current_floor == 3 && target_floor > 3)
+        if !self.spec.can_stop(target_floor) {
+            println!(
+                "Elevator {}: Invalid move requested! Floor {} is outside its zone.",
+                self.id, target_floor
+            );
+        } else if self
+            .decks
+            .iter()
+            .any(|deck| matches!(deck.door.state, DoorState::Open))
         {
+            if target_floor == self.current_floor {
+                // Steht schon mit offener Tür in der Ebene (z. B. Umsteiger an der Sky-Lobby)
+                self.announce_arrival();
+            } else {
+                println!("Elevator {}: Cannot move while door is open!", self.id);
+                let _ = self.observations.send(Observation::MoveRefused {
+                    elevator: self.id,
+                    from: self.current_floor,
+                    to: target_floor,
+                });
+            }
+        } else {
+            self.depart(target_floor, TripPurpose::Move);
+            return;
+        }
+        self.report(ElevatorStatus::ArrivedAtFloor(
+            ElevatorId(self.id),
+            FloorId(self.current_floor),
+        ));
+    }
+
+    // Fährt mit geschlossener Tür los; die Ebenen fährt `Elevator::run` ab, am Ziel folgt `arrive`.
+    // Gibt `false` zurück, wenn der Wagen nicht losfahren kann
+    fn depart(&mut self, target_floor: u8, purpose: TripPurpose) -> bool {
+        if target_floor == self.current_floor {
+            // Schon da: keine Fahrt, die in Protokoll, Fahrten und Energie auftaucht
+            self.arrive(purpose);
+            return true;
+        }
+        if !self.powered {
             println!(
-                "Elevator {}: Invalid move requested! Cannot move beyond floor limits.",
-                self.id
+                "Elevator {}: No power, cannot move to floor {}",
+                self.id, target_floor
             );
+            return false;
+        }
+        let top_deck = self.spec.decks - 1;
+        if let Some(shaft) = &self.shaft {
+            // Gesamten Fahrweg im Schacht reservieren, bevor der Wagen losfährt
+            let low = self.current_floor.min(target_floor);
+            let high = self.current_floor.max(target_floor) + top_deck;
+            if let Err(blocker) = shaft.reserve(self.id, low, high, Duration::from_secs(3)) {
+                println!(
+                    "Elevator {}: Shaft {} blocked by Elevator {}, cannot move to floor {}",
+                    self.id, shaft.id, blocker, target_floor
+                );
+                self.report(ElevatorStatus::ShaftBlocked(
+                    ElevatorId(self.id),
+                    ElevatorId(blocker),
+                    FloorId(target_floor),
+                ));
+                return false;
+            }
+        }
+        println!(
+            "Elevator {} moving from floor {} to floor {}",
+            self.id, self.current_floor, target_floor
+        );
+        self.state = ElevatorState::Moving(self.current_floor, target_floor);
+        self.show();
+        if self.stuck_on_next_trip {
+            println!(
+                "Elevator {}: Stuck between floors on the way from floor {} to floor {}",
+                self.id, self.current_floor, target_floor
+            );
+            self.fail(Fault::StuckBetweenFloors);
+            return false;
+        }
+        self.trip = Some(Trip {
+            from: self.current_floor,
+            to: target_floor,
+            started: Instant::now(),
+            slow_factor: self.slow_factor,
+            purpose,
+        });
+        true
+    }
+
+    // Nächste Ebene der laufenden Fahrt und wann der Wagen sie erreicht
+    fn next_floor(&self) -> Option<(u8, Instant)> {
+        let trip = self.trip.as_ref()?;
+        let floor = if trip.to > self.current_floor {
+            self.current_floor + 1
+        } else {
+            self.current_floor - 1
+        };
+        let travel_time = self.spec.energy.travel_time(trip.from, floor);
+        Some((floor, trip.started + travel_time.mul_f64(trip.slow_factor)))
+    }
+
+    // Der Wagen erreicht die nächste Ebene. Die Fahrt endet am Ziel oder, wenn ein Notfallbefehl wartet,
+    // in der ersten Ebene, in der der Wagen halten kann; ein Rückruf in eine Ebene, die die Fahrt noch
+    // erreicht, endet erst dort
+    fn advance(&mut self, floor: u8) {
+        self.current_floor = floor;
+        let target = self.trip.as_ref().map_or(floor, |trip| trip.to);
+        let interrupted = self.spec.can_stop(floor) && self.queue_commands();
+        let recall_ahead = matches!(
+            self.pending_commands.front(),
+            Some(ElevatorCommand::Recall(FloorId(recall_floor)))
+                if *recall_floor != floor
+                    && (floor.min(target)..=floor.max(target)).contains(recall_floor)
+        );
+        if floor != target && (!interrupted || recall_ahead) {
+            self.show();
             return;
         }
-        if let DoorState::Open = self.door.state {
-            println!("Elevator {}: Cannot move while door is open!", self.id);
+        let Some(trip) = self.trip.take() else {
+            return;
+        };
+        if floor != trip.to {
+            println!(
+                "Elevator {}: Stopping at floor {} instead of floor {} for an emergency command",
+                self.id, floor, trip.to
+            );
+        }
+        let energy = self
+            .spec
+            .energy
+            .trip(trip.from, floor, self.passenger_count);
+        self.metrics
+            .lock()
+            .unwrap()
+            .trip_completed(self.id, trip.from, floor, energy);
+        self.state = ElevatorState::IdleAtFloor;
+        // Anzeige vor dem Freigeben des Schachts, sonst sieht sie den nächsten Wagen im alten Bereich dieses Wagens
+        self.show();
+        if let Some(shaft) = &self.shaft {
+            shaft.settle(self.id, floor, floor + self.spec.decks - 1);
+        }
+        if floor == trip.to {
+            self.arrive(trip.purpose);
+        }
+    }
+
+    // Am Ziel einer Fahrt oder ohne Fahrt schon dort
+    fn arrive(&mut self, purpose: TripPurpose) {
+        let arrived =
+            ElevatorStatus::ArrivedAtFloor(ElevatorId(self.id), FloorId(self.current_floor));
+        match purpose {
+            TripPurpose::Move => {
+                self.announce_arrival();
+                self.report(arrived);
+            }
+            TripPurpose::Recall => {
+                self.open_door();
+                self.unload(ElevatorToPassenger::Evacuate);
+                self.report(ElevatorStatus::Recalled(
+                    ElevatorId(self.id),
+                    FloorId(self.current_floor),
+                ));
+            }
+            TripPurpose::Firefighter => self.report(arrived),
+        }
+    }
+
+    // Meldet die Ankunft an jeder Ebene, vor der ein Deck hält
+    fn announce_arrival(&self) {
+        // Im Sonderbetrieb steigen nur Fahrgäste aus den Ebenen zu, die der Fahrstuhl bedient
+        if !self.mode.answers_hall(self.current_floor) {
+            return;
+        }
+        let lanterns = self.lanterns.read().unwrap();
+        for deck in 0..self.spec.decks {
+            if let Some(lantern) = lanterns.get((self.current_floor + deck) as usize) {
+                lantern.announce(ElevatorArrived::Elevator {
+                    elevator: ElevatorId(self.id),
+                    deck,
+                });
+            }
+        }
+    }
+
+    fn full(&self) -> bool {
+        self.decks.iter().all(|deck| deck.load >= DECK_CAPACITY)
+    }
+
+    // Ob vor einem der Decks noch jemand wartet, der gleich einsteigen könnte
+    fn anyone_waiting(&self) -> bool {
+        let lanterns = self.lanterns.read().unwrap();
+        (0..self.spec.decks)
+            .filter_map(|deck| lanterns.get((self.current_floor + deck) as usize))
+            .any(Lantern::anyone_waiting)
+    }
+
+    // Rechnet einen Knopfdruck in einem Deck in die Position des unteren Decks um
+    // Gesicherte Ebenen nur mit passendem Ausweis; sonst verlässt der Passagier den Fahrkorb
+    fn car_call(
+        &mut self,
+        passenger_id: usize,
+        deck: u8,
+        target_floor: u8,
+        badges: &[u32],
+    ) -> Option<u8> {
+        let elapsed = self.metrics.lock().unwrap().clock();
+        if let Err(reason) = self.access.check(target_floor, badges, elapsed) {
+            let floor = self.current_floor + deck;
+            println!(
+                "Elevator {}: Access to floor {} denied for Passenger {}: {}",
+                self.id, target_floor, passenger_id, reason
+            );
+            let profile = self.rider_profile(passenger_id);
+            for deck in self.decks.iter_mut() {
+                deck.alight(passenger_id, profile.space());
+            }
+            self.passenger_count = self.passenger_count.saturating_sub(1);
+            self.metrics.lock().unwrap().access_denied(
+                passenger_id,
+                self.id,
+                floor,
+                target_floor,
+                reason,
+            );
+            if let Some(transmitter) = self
+                .elevator_to_passenger_transmitter
+                .read()
+                .unwrap()
+                .get(&PassengerId(passenger_id))
+            {
+                let _ = transmitter.send(ElevatorToPassenger::CallRejected(
+                    FloorId(target_floor),
+                    reason,
+                ));
+            }
+            self.report(ElevatorStatus::PassengerCount(
+                ElevatorId(self.id),
+                self.passenger_count,
+                self.deck_loads(),
+            ));
+            self.show();
+            return None;
+        }
+        let position = self.spec.position_for(deck, target_floor);
+        if position.is_some() {
+            self.dashboard.update(self.id, |car| {
+                car.car_buttons.insert(target_floor);
+            });
         } else {
             println!(
-                "Elevator {} moving from floor {} to floor {}",
-                self.id, self.current_floor, target_floor
+                "Elevator {}: Floor {} cannot be reached from the {}",
+                self.id,
+                target_floor,
+                self.spec.deck_name(deck)
             );
-            self.state = ElevatorState::Moving(self.current_floor, target_floor);
-            self.current_floor = target_floor;
-            self.state = ElevatorState::IdleAtFloor(target_floor);
-            self.elevator_floor_transmitter
-                .read()
-                .unwrap()
-                .get(self.current_floor as usize)
-                .unwrap()
-                .send(ElevatorArrived::Elevator(self.id as u8))
-                .unwrap();
         }
+        position
     }
 
-    fn open_door(&mut self) {
-        self.door.open();
-        self.state = ElevatorState::StoppedAtFloor(self.current_floor);
+    // Die Türen aller Decks öffnen und schließen gleichzeitig
+    // Die Zieltasten der Ebenen, vor denen die Decks halten, erlöschen; wahr, wenn eine davon leuchtete
+    fn open_door(&mut self) -> bool {
+        if matches!(self.decks[0].door.state, DoorState::Closed) {
+            self.dashboard
+                .update(self.id, |car| car.door = DoorState::Opening);
+        }
+        self.operate_doors(Door::begin_open, DOOR_OPERATING_TIME);
+        self.state = ElevatorState::StoppedAtFloor;
+        let deck_floors = self.current_floor..self.current_floor + self.spec.decks;
+        let mut alighting = false;
+        self.dashboard.update(self.id, |car| {
+            let lit = car.car_buttons.len();
+            car.car_buttons.retain(|floor| !deck_floors.contains(floor));
+            alighting = car.car_buttons.len() < lit;
+        });
+        self.show();
+        alighting
     }
 
     fn close_door(&mut self) {
-        self.door.close();
+        if matches!(self.decks[0].door.state, DoorState::Open) && !self.decks[0].door.jammed {
+            self.dashboard
+                .update(self.id, |car| car.door = DoorState::Closing);
+        }
+        self.operate_doors(Door::begin_close, DOOR_OPERATING_TIME);
+        self.show();
+    }
+
+    // Phase II: Türtaster mit Dauerkontakt, gilt für alle Decks gleichzeitig
+    fn hold_door(&mut self, button: DoorButton, held: Duration) {
+        match button {
+            DoorButton::Open => self.operate_doors(Door::begin_open, held),
+            DoorButton::Close => self.operate_doors(Door::begin_close, held),
+        }
+    }
+
+    // Bewegt die Türen aller Decks, die `begin` in Bewegung setzt, gemeinsam. Wird der Taster vor Ablauf
+    // der Öffnungszeit losgelassen, fährt die Tür zurück. Eine Tür bleibt nie halb offen stehen:
+    // ein Notfallbefehl wirkt erst, wenn sie sich fertig bewegt hat
+    fn operate_doors(&mut self, begin: fn(&mut Door) -> bool, held: Duration) {
+        let moving: Vec<usize> = (0..self.decks.len())
+            .filter(|&deck| begin(&mut self.decks[deck].door))
+            .collect();
+        if moving.is_empty() {
+            return;
+        }
+        self.pause(held.min(DOOR_OPERATING_TIME));
+        if held < DOOR_OPERATING_TIME {
+            for &deck in &moving {
+                self.decks[deck].door.reverse();
+            }
+            self.pause(held);
+        }
+        for &deck in &moving {
+            self.decks[deck].door.finish();
+        }
     }
 }
 
+const DOOR_OPERATING_TIME: Duration = Duration::from_secs(1);
+// Ungefähre Dauer eines Halts: Tür öffnen, offen halten, schließen und Zielwahl
+const STOP_TIME: Duration = Duration::from_secs(14);
+
 struct Door {
     state: DoorState,
+    jammed: bool,                 // Klemmende Tür bleibt offen
+    dwell_until: Option<Instant>, // Offenhaltezeit, danach schließt der Fahrstuhl die Tür
 }
 
 impl Door {
     fn new() -> Self {
         Self {
             state: DoorState::Closed,
+            jammed: false,
+            dwell_until: None,
+        }
+    }
+
+    fn set_dwell(&mut self, dwell: Duration) {
+        self.dwell_until = Some(Instant::now() + dwell);
+    }
+
+    // Verlängert die Offenhaltezeit, verkürzt sie aber nie
+    fn extend_dwell(&mut self, dwell: Duration) {
+        let until = Instant::now() + dwell;
+        self.dwell_until = Some(self.dwell_until.map_or(until, |current| current.max(until)));
+    }
+
+    fn dwell_elapsed(&self) -> bool {
+        self.dwell_until.is_none_or(|until| Instant::now() >= until)
+    }
+
+    // Die Tür schläft nicht selbst, die Bewegung dauert `DOOR_OPERATING_TIME` und der Fahrstuhl
+    // wartet sie in `operate_doors` ab; `false`, wenn sich die Tür nicht bewegt
+    fn begin_open(&mut self) -> bool {
+        if !matches!(self.state, DoorState::Closed) {
+            return false;
+        }
+        self.state = DoorState::Opening;
+        println!("Opening the door...");
+        true
+    }
+
+    fn begin_close(&mut self) -> bool {
+        if self.jammed {
+            println!("Door is jammed and cannot close.");
+            return false;
+        }
+        if !matches!(self.state, DoorState::Open) {
+            return false;
         }
+        self.state = DoorState::Closing;
+        println!("Closing the door...");
+        true
     }
 
-    fn open(&mut self) {
-        if let DoorState::Closed = self.state {
-            self.state = DoorState::Opening;
-            println!("Opening the door...");
-            thread::sleep(Duration::from_secs(1)); // Warte 1 Sekunde
-            self.state = DoorState::Open;
-            println!("Door is now open.");
+    // Türtaster vor Ablauf der Öffnungszeit losgelassen: die Tür fährt zurück
+    fn reverse(&mut self) {
+        match self.state {
+            DoorState::Opening => {
+                println!("Door button released, door closes again.");
+                self.state = DoorState::Closing;
+            }
+            DoorState::Closing => {
+                println!("Door button released, door opens again.");
+                self.state = DoorState::Opening;
+            }
+            DoorState::Open | DoorState::Closed => {}
         }
     }
 
-    fn close(&mut self) {
-        if let DoorState::Open = self.state {
-            self.state = DoorState::Closing;
-            println!("Closing the door...");
-            thread::sleep(Duration::from_secs(1)); // Warte 1 Sekunde
-            self.state = DoorState::Closed;
-            println!("Door is now closed.");
+    fn finish(&mut self) {
+        match self.state {
+            DoorState::Opening => {
+                self.state = DoorState::Open;
+                println!("Door is now open.");
+            }
+            DoorState::Closing => {
+                self.state = DoorState::Closed;
+                println!("Door is now closed.");
+            }
+            DoorState::Open | DoorState::Closed => {}
         }
     }
 }
 
-struct Floor {
-    id: u8,
-    up_request: bool,
-    down_request: bool,
-    control_tx: Sender<ControlCommand>,
+// Ankunftsanzeige einer Ebene: Jede Ankunft erreicht alle Passagiere, die dort gerade warten. Über einen
+// gemeinsamen Kanal bekäme sie nur einer von ihnen, die anderen warteten auf den nächsten Fahrkorb.
+// Profil und Ziel der Wartenden braucht der Fahrstuhl für den Vorrang beim Einsteigen.
+type Waiting = (PassengerProfile, u8, Sender<ElevatorArrived>);
+
+#[derive(Default)]
+struct Lantern {
+    waiting: Mutex<BTreeMap<usize, Waiting>>, // Nach Passagier-ID, damit die Reihenfolge feststeht
+}
+
+impl Lantern {
+    fn announce(&self, arrived: ElevatorArrived) {
+        self.waiting
+            .lock()
+            .unwrap()
+            .retain(|_, (_, _, transmitter)| transmitter.send(arrived).is_ok());
+    }
+
+    fn watch(
+        &self,
+        passenger: usize,
+        profile: PassengerProfile,
+        to: u8,
+        transmitter: Sender<ElevatorArrived>,
+    ) {
+        self.waiting
+            .lock()
+            .unwrap()
+            .insert(passenger, (profile, to, transmitter));
+    }
+
+    // Wartende, die mit der barrierefreien Taste gerufen haben, mit Profil und Ziel
+    fn priority(&self) -> Vec<(usize, PassengerProfile, u8)> {
+        self.waiting
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, (profile, _, _))| profile.uses_accessible_button())
+            .map(|(&passenger, &(profile, to, _))| (passenger, profile, to))
+            .collect()
+    }
+
+    fn unwatch(&self, passenger: usize) {
+        self.waiting.lock().unwrap().remove(&passenger);
+    }
+
+    fn anyone_waiting(&self) -> bool {
+        !self.waiting.lock().unwrap().is_empty()
+    }
 }
 
+struct Floor;
+
 impl Floor {
-    fn new(id: u8, control_tx: Sender<ControlCommand>, floor_rx: Receiver<FloorCommand>) {
-        thread::spawn(move || {
-            while let Ok(FloorCommand::Request {
-                floor: _,
-                direction,
-            }) = floor_rx.recv()
-            {
-                control_tx
-                    .send(ControlCommand::Request {
-                        floor: id,
-                        direction,
-                    })
-                    .unwrap();
+    fn spawn(
+        id: u8,
+        control_tx: Sender<Request<ControlCommand>>,
+        floor_rx: Receiver<Request<FloorCommand>>,
+        supervisor: &Supervisor,
+    ) {
+        let heartbeat = supervisor.heartbeat(ActorId::Floor(id));
+        supervisor.spawn(ActorId::Floor(id), move || {
+            let mut heartbeat = heartbeat.clone();
+            loop {
+                heartbeat.beat();
+                match clock::recv_timeout(&floor_rx, HEARTBEAT_INTERVAL) {
+                    Ok(request) => {
+                        // Die Steuerung bestätigt den Ruf direkt dem Passagier
+                        let FloorCommand::Request {
+                            direction,
+                            destination,
+                            accessible,
+                            load,
+                        } = request.body;
+                        let forwarded = request.forward(ControlCommand::Request {
+                            floor: FloorId(id),
+                            direction,
+                            destination,
+                            accessible,
+                            load,
+                        });
+                        // Ohne Steuerung bleibt der Ruf offen; der Passagier bemerkt die fehlende Antwort
+                        if control_tx.send(forwarded).is_err() {
+                            println!("Floor {}: Control system no longer listens", id);
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
             }
         });
     }
@@ -544,170 +2683,385 @@ struct Passenger {
     id: usize,
     current_floor: u8,
     state: PassengerState,
-    floor_transmitters: Arc<RwLock<HashMap<u8, Sender<FloorCommand>>>>,
-    elevator_floor_receiver: Arc<RwLock<Vec<Receiver<ElevatorArrived>>>>,
+    floor_transmitters: Arc<RwLock<HashMap<u8, Sender<Request<FloorCommand>>>>>,
+    lanterns: Arc<RwLock<Vec<Lantern>>>,
+    arrival_tx: Sender<ElevatorArrived>, // Eingetragen in der Anzeige der Ebene, in der der Passagier wartet
+    arrival_rx: Receiver<ElevatorArrived>,
+    watching: Option<u8>, // Ebene, deren Anzeige der Passagier gerade beobachtet, siehe `watch`
     elevator_passenger_receiver: Receiver<ElevatorToPassenger>,
-    passenger_elevator_transmitter: Arc<RwLock<Vec<Sender<PassengerToElevator>>>>,
+    elevator_passenger_transmitter: Arc<RwLock<HashMap<PassengerId, Sender<ElevatorToPassenger>>>>, // Eigener Eintrag, siehe `Drop`
+    passenger_elevator_transmitter: Arc<RwLock<Vec<Sender<Request<PassengerToElevator>>>>>,
+    replies: Sender<Reply>, // Antworten von Steuerung und Fahrstühlen auf die eigenen Befehle
+    reply_rx: Receiver<Reply>,
+    outstanding: Outstanding,
     target_floor: u8,
-    current_elevator: u8,
+    current_elevator: ElevatorId,
+    current_deck: u8, // Deck, in dem der Passagier fährt (0 = unteres Deck)
+    legs: Vec<Leg>,   // Teilstücke der Fahrt, z. B. Lobby -> Sky-Lobby -> Zielebene
+    current_leg: usize,
+    specs: Arc<Vec<ElevatorSpec>>,
+    failed_elevators: Vec<usize>, // Ausgefallene Fahrstühle, die bei der Routenplanung fehlen
+    badges: Vec<u32>,             // Zutrittsausweise, leer ohne Ausweis
+    profile: PassengerProfile,
+    patience: Patience,
+    jitter: Duration, // Zufällige Verzögerung vor Ein- und Ausstieg und Zielwahl, siehe `hesitate`
+    rng: StdRng,      // Aus Startwert und ID, damit ein Lauf mit demselben Startwert gleich zögert
+    heartbeat: Heartbeat, // Erst ab der Abfahrt, vorher wird der Passagier nicht auf Hänger geprüft
+    waiting_since: Option<Instant>, // Beginn der Wartezeit auf das aktuelle Teilstück
+    group: Option<Arc<Group>>, // Gemeinsame Ankunft mit anderen Fahrgästen
+    metrics: Arc<Mutex<Metrics>>,
+    observations: Sender<Observation>, // Zum `InvariantMonitor`
 }
 
 impl Passenger {
-    fn new(
+    #[allow(clippy::too_many_arguments)]
+    fn spawn(
         id: usize,
         current_floor: u8,
-        floor_transmitters: Arc<RwLock<HashMap<u8, Sender<FloorCommand>>>>, // Nachricht an die Ebene zum Drücken des Knopfes
-        elevator_floor_receiver: Arc<RwLock<Vec<Receiver<ElevatorArrived>>>>, // Nachricht vom Elevator an den gesamten Floor
+        floor_transmitters: Arc<RwLock<HashMap<u8, Sender<Request<FloorCommand>>>>>, // Nachricht an die Ebene zum Drücken des Knopfes
+        lanterns: Arc<RwLock<Vec<Lantern>>>, // Nachricht vom Elevator an alle, die in einer Ebene warten
         elevator_passenger_receiver: Receiver<ElevatorToPassenger>, // Direkte Nachricht vom Elevator an den Passenger
-        passenger_elevator_transmitter: Arc<RwLock<Vec<Sender<PassengerToElevator>>>>, // Direkte Nachricht vom Passenger an den Elevator
-        target_floor: u8,
+        elevator_passenger_transmitter: Arc<
+            RwLock<HashMap<PassengerId, Sender<ElevatorToPassenger>>>,
+        >,
+        passenger_elevator_transmitter: Arc<RwLock<Vec<Sender<Request<PassengerToElevator>>>>>, // Direkte Nachricht vom Passenger an den Elevator
+        legs: Vec<Leg>,
+        specs: Arc<Vec<ElevatorSpec>>,
+        badges: Vec<u32>,
+        profile: PassengerProfile,
+        patience: Patience,
+        jitter: Duration,
+        seed: u64,
+        group: Option<Arc<Group>>,
+        departure: Duration, // Ankunft in der Startebene, gezählt ab jetzt
+        metrics: Arc<Mutex<Metrics>>,
+        observations: Sender<Observation>,
+        supervisor: &Supervisor,
     ) {
+        let (replies, reply_rx) = unbounded();
+        let (arrival_tx, arrival_rx) = unbounded();
         let passenger = Passenger {
             id,
             current_floor,
             state: PassengerState::IdleAtFloor(current_floor),
             floor_transmitters,
-            elevator_floor_receiver,
+            lanterns,
+            arrival_tx,
+            arrival_rx,
+            watching: None,
             elevator_passenger_receiver,
+            elevator_passenger_transmitter,
             passenger_elevator_transmitter,
-            target_floor,
-            current_elevator: 0,
+            replies,
+            reply_rx,
+            outstanding: Outstanding::default(),
+            target_floor: legs.last().map_or(current_floor, |leg| leg.to),
+            current_elevator: ElevatorId(0),
+            current_deck: 0,
+            legs,
+            current_leg: 0,
+            specs,
+            failed_elevators: Vec::new(),
+            badges,
+            profile,
+            patience,
+            jitter,
+            rng: StdRng::seed_from_u64(seed ^ id as u64),
+            heartbeat: supervisor.heartbeat(ActorId::Passenger(id)),
+            waiting_since: None,
+            group,
+            metrics,
+            observations,
         };
-        // Ownership von passenger in den Thread verschieben
-        thread::spawn(move || {
-            let mut rng = rand::thread_rng();
+        // Ownership von passenger in den Thread verschieben; ein Passagier lässt sich daher nicht neu starten
+        supervisor.spawn_once(ActorId::Passenger(id), move || {
             let mut passenger = passenger; // passenger ist jetzt exklusiv im Thread
-                                           // Zielstockwerk auswählen
-            let mut target_floor;
-            loop {
-                target_floor = rng.gen_range(0..4);
-                if target_floor != passenger.current_floor {
-                    break;
-                }
-            }
-            // Determine new direction
-            let direction = if target_floor > passenger.current_floor {
-                Direction::Up
-            } else {
-                Direction::Down
-            };
+            clock::sleep(departure);
+            println!(
+                "Passenger {}: Journey from floor {} to floor {} in {} leg(s) ({:?})",
+                passenger.id,
+                passenger.current_floor,
+                passenger.target_floor,
+                passenger.legs.len(),
+                passenger.profile
+            );
+            passenger
+                .metrics
+                .lock()
+                .unwrap()
+                .journey_started(passenger.id, &passenger.legs, passenger.profile);
 
-            loop {
+            while passenger.current_leg < passenger.legs.len() {
+                passenger.heartbeat.beat();
+                passenger.check_replies();
+                let leg = passenger.legs[passenger.current_leg];
                 if !matches!(passenger.state, PassengerState::IdleAtFloor(floor) if floor == passenger.current_floor) {
-                    // Nachricht an den Fahrstuhl senden
-                    let elevator_transmitter = passenger
-                    .passenger_elevator_transmitter
-                    .read()
-                    .unwrap()
-                    .get(passenger.current_elevator as usize)
-                    .cloned() // Klone den Sender, damit er außerhalb nutzbar bleibt
-                    .expect("Failed to get PassengerToElevator transmitter");
-                    
-                    let elevator_receiver = passenger
-                            .elevator_passenger_receiver
-                            .clone();
-                        if let Ok(message) = elevator_receiver.recv() {
-                            match message {
-                                ElevatorToPassenger::YouCanExit(floor) => {
-                                    println!(
-                                        "Passenger {}: arrived at floor {}",
-                                        passenger.id, floor
+                    if let Ok(message) = passenger.heartbeat.recv(&passenger.elevator_passenger_receiver, None) {
+                        match message {
+                            ElevatorToPassenger::YouCanExit(FloorId(floor)) => {
+                                println!("Passenger {}: arrived at floor {}", passenger.id, floor);
+                                if floor == leg.to {
+                                    passenger.heartbeat.sleep(passenger.profile.transfer_time());
+                                    passenger.hesitate();
+                                    let exit = passenger.send_to_elevator(
+                                        passenger.current_elevator,
+                                        PassengerToElevator::Exit(PassengerId(passenger.id)),
                                     );
-                                    if floor == passenger.target_floor {
-                                        elevator_transmitter
-                                            .send(PassengerToElevator::Exit(passenger.id as u8))
-                                            .expect("Failed to send PassengerToElevator::Exit message");
-                                        passenger.state = PassengerState::ExitingElevator;
-                                        passenger.current_floor = floor;
-                                        passenger.state = PassengerState::IdleAtFloor(passenger.current_floor);
-                                        break;
+                                    // Abgelehnt, z. B. weil sich die Tür schon schloss: weiterfahren, das Ziel erneut
+                                    // wählen und beim nächsten Halt dort aussteigen
+                                    if let Err(reason) = passenger.confirm(exit) {
+                                        println!(
+                                            "Passenger {}: Could not leave Elevator {} at floor {} ({}), staying on board",
+                                            passenger.id, passenger.current_elevator, floor, reason
+                                        );
+                                        continue;
+                                    }
+                                    let _ = passenger.observations.send(Observation::Exited {
+                                        passenger: passenger.id,
+                                        elevator: passenger.current_elevator.0,
+                                        floor,
+                                    });
+                                    passenger.state = PassengerState::ExitingElevator;
+                                    passenger.current_floor = floor;
+                                    passenger.state = PassengerState::IdleAtFloor(passenger.current_floor);
+                                    passenger.metrics.lock().unwrap().leg_completed(passenger.id);
+                                    passenger.current_leg += 1;
+
+                                    // Umsteigen: zurück in die Warteschlange der aktuellen Ebene
+                                    if let Some(next_leg) = passenger.legs.get(passenger.current_leg) {
+                                        println!(
+                                            "Passenger {}: Transferring at floor {} for leg {}/{} to floor {}",
+                                            passenger.id,
+                                            floor,
+                                            passenger.current_leg + 1,
+                                            passenger.legs.len(),
+                                            next_leg.to
+                                        );
                                     }
                                 }
-                                ElevatorToPassenger::YouCanChooseFloor => {
-                                    println!("Passenger chooses floor {}", target_floor);
-                                    elevator_transmitter
-                                        .send(PassengerToElevator::PressedButton(target_floor))
-                                        .expect("Failed to send button press message");
+                            }
+                            ElevatorToPassenger::YouCanChooseFloor => {
+                                passenger.hesitate();
+                                println!("Passenger {}: chooses floor {}", passenger.id, leg.to);
+                                passenger.send_to_elevator(
+                                    passenger.current_elevator,
+                                    PassengerToElevator::PressedButton {
+                                        passenger: PassengerId(passenger.id),
+                                        deck: passenger.current_deck,
+                                        floor: FloorId(leg.to),
+                                        badges: passenger.badges.clone(),
+                                    },
+                                );
+                            }
+                            ElevatorToPassenger::OutOfService(FloorId(floor)) => {
+                                println!(
+                                    "Passenger {}: Elevator {} is out of service, leaving at floor {}",
+                                    passenger.id, passenger.current_elevator, floor
+                                );
+                                passenger.failed_elevators.push(passenger.current_elevator.0);
+                                // Ein ausgefallener Fahrstuhl antwortet nicht mehr
+                                passenger.outstanding.forget(ActorId::Elevator(passenger.current_elevator.0));
+                                if !passenger.replan_from(floor) {
+                                    return;
+                                }
+                            }
+                            ElevatorToPassenger::Evacuate(FloorId(floor)) => {
+                                println!(
+                                    "Passenger {}: Evacuated from Elevator {} at floor {}",
+                                    passenger.id, passenger.current_elevator, floor
+                                );
+                                if !passenger.replan_from(floor) {
+                                    return;
+                                }
+                            }
+                            ElevatorToPassenger::CallRejected(target_floor, reason) => {
+                                // Ohne Berechtigung endet die Fahrt in der Ebene, in der der Passagier eingestiegen ist
+                                println!(
+                                    "Passenger {}: Access to floor {} denied ({}), leaving Elevator {} at floor {}",
+                                    passenger.id, target_floor, reason, passenger.current_elevator, passenger.current_floor
+                                );
+                                passenger.state = PassengerState::IdleAtFloor(passenger.current_floor);
+                                passenger
+                                    .metrics
+                                    .lock()
+                                    .unwrap()
+                                    .leg_interrupted(passenger.id, passenger.current_floor);
+                                return;
+                            }
+                            _ => {}
+                        }
+                    }
+                    continue;
+                }
+
+                let direction = leg.direction();
+                passenger.metrics.lock().unwrap().leg_requested(passenger.id, leg);
+
+                // Gruppenmitglieder warten, bis der Anführer den Fahrkorb für alle betritt
+                if passenger
+                    .group
+                    .as_ref()
+                    .is_some_and(|group| group.leader() != Some(passenger.id))
+                {
+                    let waiting_since = *passenger.waiting_since.get_or_insert_with(Instant::now);
+                    let response =
+                        clock::recv_timeout(&passenger.elevator_passenger_receiver, Duration::from_secs(1)).ok();
+                    match response {
+                        Some(ElevatorToPassenger::YouEntered { elevator, deck }) => passenger.entered(elevator, deck),
+                        Some(ElevatorToPassenger::NoRoom) => {
+                            println!(
+                                "Passenger {}: No room left for the whole group, continuing alone",
+                                passenger.id
+                            );
+                            passenger.leave_group(true);
+                        }
+                        _ => {
+                            if let Some((after, abandonment)) = passenger.patience.limit(leg, passenger.profile) {
+                                if waiting_since.elapsed() >= after
+                                    && !passenger.abandon_leg(leg, abandonment, waiting_since.elapsed())
+                                {
+                                    return;
                                 }
-                                _ => {}
                             }
                         }
-                    if let Ok(ElevatorToPassenger::YouCanExit(floor)) = elevator_receiver.recv() {
-                        println!(
-                            "Passenger {}: arrived at floor {}",
-                            passenger.id, floor
-                        );
-                        break;
                     }
                     continue;
                 }
-                else {
+
                 // Anfrage an die aktuelle Etage senden
-                if let Some(sender) = passenger
+                let sender = passenger
                     .floor_transmitters
                     .read()
                     .unwrap()
                     .get(&passenger.current_floor)
-                {
+                    .cloned();
+                if let Some(sender) = sender {
                     println!(
                         "Passenger {}: Requesting {:?} from floor {}",
                         passenger.id, direction, passenger.current_floor
                     );
-                    sender
-                        .send(FloorCommand::Request {
-                            floor: passenger.current_floor,
+                    let request = Request::new(
+                        FloorCommand::Request {
                             direction,
-                        })
-                        .unwrap();
+                            destination: Some(FloorId(leg.to)),
+                            accessible: passenger.profile.uses_accessible_button(),
+                            load: passenger.profile,
+                        },
+                        &passenger.replies,
+                    );
+                    // Die Ebene leitet den Ruf weiter, die Steuerung antwortet
+                    let description = format!("{:?} call at floor {}", direction, passenger.current_floor);
+                    passenger.outstanding.sent(&request, ActorId::ControlSystem, description);
+                    if sender.send(request).is_err() {
+                        println!("Passenger {}: Floor {} no longer listens", passenger.id, passenger.current_floor);
+                    }
                 }
 
-                // Warten auf Nachricht vom Fahrstuhl
-                if let Some(receiver) = passenger
-                    .elevator_floor_receiver
-                    .read()
-                    .unwrap()
-                    .get(passenger.current_floor as usize)
-                {
-                    if let Ok(ElevatorArrived::Elevator(elevator_id)) = receiver.recv() {
+                // Warten auf Nachricht vom Fahrstuhl, höchstens bis die Geduld erschöpft ist
+                let waiting_since = *passenger.waiting_since.get_or_insert_with(Instant::now);
+                let limit = passenger.patience.limit(leg, passenger.profile);
+                if passenger.watch() {
+                    let until = limit.map(|(after, _)| waiting_since + after);
+                    let arrival = passenger.heartbeat.recv(&passenger.arrival_rx, until);
+                    if let (Err(RecvTimeoutError::Timeout), Some((_, abandonment))) = (&arrival, limit) {
+                        if !passenger.abandon_leg(leg, abandonment, waiting_since.elapsed()) {
+                            return;
+                        }
+                        continue;
+                    }
+                    if let Ok(ElevatorArrived::Elevator { elevator, deck }) = arrival {
+                        let elevator_id = elevator.0;
+                        let spec = &passenger.specs[elevator_id];
                         println!(
-                            "Passenger {}: Elevator {} arrived at floor {}",
-                            passenger.id, elevator_id, passenger.current_floor
+                            "Passenger {}: Elevator {} arrived at floor {} ({})",
+                            passenger.id, elevator_id, passenger.current_floor, spec.deck_name(deck)
                         );
 
-                        // Nachricht an den Fahrstuhl senden
-                        let elevator_transmitter = passenger
-                            .passenger_elevator_transmitter
-                            .read()
-                            .unwrap()
-                            .get(elevator_id as usize)
-                            .cloned() // Klone den Sender, damit er außerhalb nutzbar bleibt
-                            .expect("Failed to get PassengerToElevator transmitter");
-
-                        elevator_transmitter
-                            .send(PassengerToElevator::Enter(passenger.id as u8))
-                            .expect("Failed to send PassengerToElevator::Enter message");
-
-                        // Warten auf Antwort vom Fahrstuhl
-                        let response = select! {
-                            recv(passenger.elevator_passenger_receiver) -> msg => msg.ok(),
-                            default(Duration::from_secs(2)) => None, // Timeout nach 2 Sekunde
+                        // Nur einsteigen, wenn das Deck vor dieser Ebene auch das Ziel des Teilstücks anfährt
+                        if spec.position_for(deck, leg.to).is_none() {
+                            println!(
+                                "Passenger {}: Elevator {} ({}) does not serve floor {}, waiting for another",
+                                passenger.id, elevator_id, spec.deck_name(deck), leg.to
+                            );
+                            continue;
+                        }
+                        if !car_accepts(spec, passenger.profile) {
+                            println!(
+                                "Passenger {}: Elevator {} is no freight car, waiting for another",
+                                passenger.id, elevator_id
+                            );
+                            continue;
+                        }
+                        let deck_name = spec.deck_name(deck);
+
+                        // Der Anführer einer Gruppe steigt für alle wartenden Mitglieder ein
+                        let boarders: Vec<(PassengerId, PassengerProfile)> = match &passenger.group {
+                            Some(group) => group
+                                .waiting()
+                                .into_iter()
+                                .map(|(id, profile)| (PassengerId(id), profile))
+                                .collect(),
+                            None => vec![(PassengerId(passenger.id), passenger.profile)],
+                        };
+                        passenger.state = PassengerState::EnteringElevator;
+                        passenger.heartbeat.sleep(passenger.profile.transfer_time());
+                        passenger.hesitate();
+                        let enter = passenger.send_to_elevator(elevator, PassengerToElevator::Enter {
+                            boarders,
+                            deck,
+                            floor: FloorId(passenger.current_floor),
+                        });
+
+                        // Warten auf Antwort vom Fahrstuhl, höchstens 2 Sekunden oder bis er ablehnt
+                        let until = Instant::now() + Duration::from_secs(2);
+                        let mut rejected = false;
+                        let response = loop {
+                            clock::wait(
+                                vec![
+                                    clock::ready(&passenger.elevator_passenger_receiver),
+                                    clock::ready(&passenger.reply_rx),
+                                ],
+                                Some(until),
+                            );
+                            select_biased! {
+                                recv(passenger.elevator_passenger_receiver) -> msg => break msg.ok(),
+                                recv(passenger.reply_rx) -> reply => {
+                                    let Ok(reply) = reply else { break None };
+                                    let correlation = reply.correlation;
+                                    if passenger.handle_reply(reply).is_err() && Some(correlation) == enter {
+                                        rejected = true;
+                                        break None;
+                                    }
+                                }
+                                default => if Instant::now() >= until {
+                                    break None;
+                                },
+                            }
                         };
 
-                        if let Some(ElevatorToPassenger::YouEntered()) = response {
+                        if rejected {
+                            // Die Tür schloss sich, bevor der Fahrstuhl den Einstieg annahm: erneut rufen
                             println!(
-                                "Passenger {}: Successfully entered Elevator {}",
+                                "Passenger {}: Elevator {} did not let them in, calling again",
                                 passenger.id, elevator_id
                             );
-                            passenger.state = PassengerState::InElevator(elevator_id);
-                            // thread::sleep(Duration::from_secs(1)); // Warte 1 Sekunde
-                            continue; // Beende die Schleife, wenn der Passagier eingestiegen ist
+                            passenger.state = PassengerState::IdleAtFloor(passenger.current_floor);
+                        } else if let Some(ElevatorToPassenger::YouEntered { elevator, deck }) = response {
+                            passenger.entered(elevator, deck);
+                        } else if let Some(ElevatorToPassenger::NoRoom) = response {
+                            println!(
+                                "Passenger {}: No room in Elevator {} ({}), waiting for the next car",
+                                passenger.id, elevator_id, deck_name
+                            );
+                            passenger.state = PassengerState::IdleAtFloor(passenger.current_floor);
                         } else {
                             println!(
-                                "Passenger {}: No response from Elevator {} within 1 second",
+                                "Passenger {}: No response from Elevator {} within 2 seconds",
                                 passenger.id, elevator_id
                             );
-                            continue; // Zurück zur Anfrage an die aktuelle Etage
+                            passenger.state = PassengerState::IdleAtFloor(passenger.current_floor);
                         }
+                        continue; // Zurück zur Anfrage an die aktuelle Etage
                     }
                 }
 
@@ -715,117 +3069,718 @@ impl Passenger {
                     "Passenger {}: No elevator available, retrying...",
                     passenger.id
                 );
-                thread::sleep(Duration::from_secs(1));
-                }
+                clock::sleep(Duration::from_secs(1));
             }
+            println!(
+                "Passenger {}: Reached destination floor {}",
+                passenger.id, passenger.target_floor
+            );
         });
     }
-}
 
-fn main() {
-    let floors = 4;
-    let elevators = 3;
-    let passengers = 1;
-
-    let mut elevator_floor_receiver: Arc<RwLock<Vec<Receiver<ElevatorArrived>>>> =
-        Arc::new(RwLock::new(Vec::new()));
-    let mut elevator_floor_transmitter: Arc<RwLock<Vec<Sender<ElevatorArrived>>>> =
-        Arc::new(RwLock::new(Vec::new()));
-    let mut elevator_passenger_transmitter: Arc<RwLock<Vec<Sender<ElevatorToPassenger>>>> =
-        Arc::new(RwLock::new(Vec::new()));
-    let mut passenger_elevator_transmitter: Arc<RwLock<Vec<Sender<PassengerToElevator>>>> =
-        Arc::new(RwLock::new(Vec::new()));
-    let mut passenger_elevator_receiver: Arc<RwLock<Vec<Receiver<PassengerToElevator>>>> =
-        Arc::new(RwLock::new(Vec::new()));
-    let floor_transmitter: Arc<RwLock<HashMap<u8, Sender<FloorCommand>>>> =
-        Arc::new(RwLock::new(HashMap::new()));
-    let mut elevator_senders = Vec::new();
-    let (control_tx, control_rx) = unbounded();
-    let (status_tx, status_rx) = unbounded();
-
-    // Etagen initialisieren
-    for i in 0..floors {
-        let (floor_tx, floor_rx) = unbounded();
-        let (elevator_floor_tx, elevator_floor_rx) = unbounded();
-        elevator_floor_transmitter
-            .write()
+    // Korrelations-ID des gesendeten Befehls, `None`, wenn es den Fahrstuhl nicht gibt
+    fn send_to_elevator(
+        &mut self,
+        elevator: ElevatorId,
+        message: PassengerToElevator,
+    ) -> Option<CorrelationId> {
+        let transmitter = self
+            .passenger_elevator_transmitter
+            .read()
             .unwrap()
-            .push(elevator_floor_tx);
-        elevator_floor_receiver
-            .write()
+            .get(elevator.0)
+            .cloned(); // Klone den Sender, damit er außerhalb nutzbar bleibt
+        let Some(transmitter) = transmitter else {
+            println!(
+                "Passenger {}: Elevator {} does not exist",
+                self.id, elevator.0
+            );
+            return None;
+        };
+        let description = format!("{:?}", message);
+        let request = Request::new(message, &self.replies);
+        let correlation = request.correlation;
+        self.outstanding
+            .sent(&request, ActorId::Elevator(elevator.0), description);
+        // Ein verlorener Befehl fällt in `check_replies` als unbeantwortet auf
+        if transmitter.send(request).is_err() {
+            println!(
+                "Passenger {}: Elevator {} no longer listens",
+                self.id, elevator.0
+            );
+        }
+        Some(correlation)
+    }
+
+    // Meldet Ablehnungen und unerwartete Antworten; `Err` mit dem Grund, wenn der Befehl abgelehnt wurde
+    fn handle_reply(&mut self, reply: Reply) -> Result<(), String> {
+        match (self.outstanding.answered(&reply), reply.outcome) {
+            (Ok(_), Outcome::Ack) => Ok(()),
+            (Ok((to, command)), Outcome::Nack(reason)) => {
+                println!(
+                    "Passenger {}: {} rejected {}: {}",
+                    self.id, to, command, reason
+                );
+                Err(reason)
+            }
+            (Err(problem), _) => {
+                println!("Passenger {}: {}", self.id, problem);
+                Ok(())
+            }
+        }
+    }
+
+    // Wartet auf die Antwort auf `correlation`, andere Antworten werden nebenbei ausgewertet.
+    // `Err` bei Ablehnung oder wenn innerhalb von `REPLY_TIMEOUT` keine Antwort kommt.
+    fn confirm(&mut self, correlation: Option<CorrelationId>) -> Result<(), String> {
+        let correlation = correlation.ok_or("the elevator does not exist")?;
+        let until = Instant::now() + REPLY_TIMEOUT;
+        loop {
+            let reply = clock::recv_deadline(&self.reply_rx, until)
+                .map_err(|_| format!("no reply within {}s", REPLY_TIMEOUT.as_secs()))?;
+            let answers = reply.correlation == correlation;
+            let outcome = self.handle_reply(reply);
+            if answers {
+                return outcome;
+            }
+        }
+    }
+
+    // Abgelehnte und verlorene Befehle werden nur gemeldet, der Passagier reagiert auf die Nachrichten des Fahrstuhls
+    fn check_replies(&mut self) {
+        while let Ok(reply) = self.reply_rx.try_recv() {
+            let _ = self.handle_reply(reply);
+        }
+        for (to, command) in self.outstanding.overdue() {
+            println!(
+                "Passenger {}: No reply from {} to {} within {}s, command lost",
+                self.id,
+                to,
+                command,
+                REPLY_TIMEOUT.as_secs()
+            );
+        }
+    }
+
+    // Zögert zufällig bis zu `jitter`, damit Stresstests andere Reihenfolgen der Nachrichten erzeugen
+    fn hesitate(&mut self) {
+        if !self.jitter.is_zero() {
+            let share: f64 = self.rng.gen();
+            clock::sleep(self.jitter.mul_f64(share));
+        }
+    }
+
+    // Bestätigter Einstieg in `deck` von `elevator`; die Wartezeit endet, eine Gruppe fährt ab hier einzeln
+    fn entered(&mut self, elevator: ElevatorId, deck: u8) {
+        println!(
+            "Passenger {}: Successfully entered Elevator {}",
+            self.id, elevator
+        );
+        self.state = PassengerState::InElevator;
+        self.waiting_since = None;
+        self.unwatch();
+        self.current_elevator = elevator;
+        self.current_deck = deck;
+        let deck_name = self.specs[elevator.0].deck_name(deck);
+        self.metrics
+            .lock()
             .unwrap()
-            .push(elevator_floor_rx);
-        floor_transmitter.write().unwrap().insert(i, floor_tx);
-        Floor::new(i, control_tx.clone(), floor_rx);
+            .leg_boarded(self.id, elevator.0, deck_name);
+        self.leave_group(false);
     }
 
-    for id in 0..elevators {
-        let (elevator_tx, elevator_rx) = unbounded();
-        passenger_elevator_transmitter
-            .write()
+    // Trägt den Passagier in die Anzeige der Ebene ein, in der er wartet; Ankünfte aus einer früheren Ebene
+    // verwirft er. Falsch, wenn es die Ebene nicht gibt.
+    fn watch(&mut self) -> bool {
+        if self.watching == Some(self.current_floor) {
+            return true;
+        }
+        self.unwatch();
+        while self.arrival_rx.try_recv().is_ok() {}
+        let lanterns = self.lanterns.read().unwrap();
+        let Some(lantern) = lanterns.get(self.current_floor as usize) else {
+            return false;
+        };
+        let to = self
+            .legs
+            .get(self.current_leg)
+            .map_or(self.target_floor, |leg| leg.to);
+        lantern.watch(self.id, self.profile, to, self.arrival_tx.clone());
+        drop(lanterns);
+        self.watching = Some(self.current_floor);
+        true
+    }
+
+    fn unwatch(&mut self) {
+        if let Some(floor) = self.watching.take() {
+            if let Some(lantern) = self.lanterns.read().unwrap().get(floor as usize) {
+                lantern.unwatch(self.id);
+            }
+        }
+    }
+
+    // Nach dem Einsteigen, Abspalten oder Aufgeben fährt der Passagier allein weiter
+    fn leave_group(&mut self, split: bool) {
+        if let Some(group) = self.group.take() {
+            group.leave(self.id);
+            if split {
+                self.metrics.lock().unwrap().group_split(group.id);
+            }
+        }
+    }
+
+    // Geduld erschöpft: zu Fuß zur Zielebene des Teilstücks oder die Fahrt abbrechen.
+    // Gibt `false` zurück, wenn der Passagier aufgibt.
+    fn abandon_leg(&mut self, leg: Leg, abandonment: Abandonment, waited: Duration) -> bool {
+        self.waiting_since = None;
+        self.leave_group(false);
+        self.metrics
+            .lock()
             .unwrap()
-            .push(elevator_tx);
-        passenger_elevator_receiver
-            .write()
+            .leg_abandoned(self.id, abandonment);
+        match abandonment {
+            Abandonment::Stairs => {
+                println!(
+                    "Passenger {}: Waited {:.1}s, taking the stairs from floor {} to floor {}",
+                    self.id,
+                    waited.as_secs_f64(),
+                    self.current_floor,
+                    leg.to
+                );
+                let floors = (leg.to as i32 - self.current_floor as i32).unsigned_abs();
+                self.heartbeat.sleep(STAIRS_TIME_PER_FLOOR * floors);
+                self.current_floor = leg.to;
+                self.state = PassengerState::IdleAtFloor(leg.to);
+                self.metrics.lock().unwrap().leg_completed(self.id);
+                self.current_leg += 1;
+                true
+            }
+            Abandonment::GaveUp => {
+                println!(
+                    "Passenger {}: Waited {:.1}s at floor {}, giving up",
+                    self.id,
+                    waited.as_secs_f64(),
+                    self.current_floor
+                );
+                false
+            }
+        }
+    }
+
+    // Musste vorzeitig in `floor` aussteigen: neue Route ohne die ausgefallenen Fahrstühle.
+    // Gibt `false` zurück, wenn das Ziel nicht mehr erreichbar ist.
+    fn replan_from(&mut self, floor: u8) -> bool {
+        self.current_floor = floor;
+        self.state = PassengerState::IdleAtFloor(floor);
+        self.metrics.lock().unwrap().leg_interrupted(self.id, floor);
+
+        let available: Vec<ElevatorSpec> = self
+            .specs
+            .iter()
+            .enumerate()
+            .filter(|(id, _)| !self.failed_elevators.contains(id))
+            .filter(|(_, spec)| car_accepts(spec, self.profile))
+            .map(|(_, spec)| spec.clone())
+            .collect();
+        let Some(legs) = plan_journey(floor, self.target_floor, &available) else {
+            println!(
+                "Passenger {}: Floor {} is no longer reachable, giving up at floor {}",
+                self.id, self.target_floor, floor
+            );
+            return false;
+        };
+        println!(
+            "Passenger {}: Replanned journey from floor {} in {} leg(s)",
+            self.id,
+            floor,
+            legs.len()
+        );
+        self.metrics
+            .lock()
             .unwrap()
-            .push(elevator_rx);
+            .journey_replanned(self.id, legs.len());
+        self.legs.truncate(self.current_leg);
+        self.legs.extend(legs);
+        true
     }
+}
 
-    // Passagiere initialisieren
-    for i in 0..passengers {
-        let (passenger_tx, passenger_rx) = unbounded();
-        elevator_passenger_transmitter
+// Am Ende der Fahrt, auch nach Aufgeben oder Absturz, bekommt der Passagier keine Nachrichten mehr;
+// so wächst die Tabelle der Kanäle nur mit den gleichzeitig unterwegs befindlichen Passagieren
+impl Drop for Passenger {
+    fn drop(&mut self) {
+        self.unwatch();
+        self.elevator_passenger_transmitter
             .write()
             .unwrap()
-            .push(passenger_tx);
+            .remove(&PassengerId(self.id));
+    }
+}
+
+// Laufendes System; über diese Kanäle greifen Anzeige und Konsole von außen ein
+struct Simulation {
+    config: SimulationConfig,
+    floors: u8,
+    sky_lobby: u8,
+    specs: Arc<Vec<ElevatorSpec>>,
+    arrivals: Mutex<Vec<Arrival>>, // Ankunft je Passagier-ID, auch der über die Konsole hinzugefügten
+    console_inputs: Mutex<Vec<ConsoleInput>>, // Übrige Eingaben der Konsole, für den Snapshot
+    patience: Patience,
+    metrics: Arc<Mutex<Metrics>>,
+    dashboard: Arc<Dashboard>,
+    control_tx: Sender<Request<ControlCommand>>,
+    status_tx: Sender<ElevatorStatus>, // Zieltasten, die von außen im Fahrkorb gedrückt werden
+    elevator_senders: Vec<Sender<Request<ElevatorCommand>>>,
+    floor_transmitter: Arc<RwLock<HashMap<u8, Sender<Request<FloorCommand>>>>>,
+    lanterns: Arc<RwLock<Vec<Lantern>>>,
+    elevator_passenger_transmitter: Arc<RwLock<HashMap<PassengerId, Sender<ElevatorToPassenger>>>>,
+    passenger_elevator_transmitter: Arc<RwLock<Vec<Sender<Request<PassengerToElevator>>>>>,
+    observations: Sender<Observation>, // Zum `InvariantMonitor`
+    supervisor: Supervisor,
+}
+
+impl Simulation {
+    fn start(
+        config: &SimulationConfig,
+        arrivals: &[Arrival],
+        dashboard: Arc<Dashboard>,
+    ) -> Result<Self, String> {
+        config.validate()?;
+        clock::set_watchdog(Some(config.watchdog).filter(|watchdog| !watchdog.is_zero()));
+        // Alle Akteure stehen, bis der Aufbau fertig ist, und starten dann in fester Reihenfolge
+        let _setup = clock::join("Simulation");
+        let floors = config.floors;
+        let specs = config.specs();
+        let elevators = specs.len();
+
+        // Fahrstühle mit derselben Schachtnummer teilen sich einen Schacht (TWIN)
+        let mut shared_shafts: BTreeMap<usize, Arc<Shaft>> = BTreeMap::new();
+        let shafts: Vec<Option<Arc<Shaft>>> = config
+            .elevators
+            .iter()
+            .map(|elevator| {
+                elevator.shaft.map(|shaft| {
+                    Arc::clone(
+                        shared_shafts
+                            .entry(shaft)
+                            .or_insert_with(|| Arc::new(Shaft::new(shaft, config.min_separation))),
+                    )
+                })
+            })
+            .collect();
+        let start_hour = config.start_hour; // Uhrzeit beim Start, für Parkregeln und Zutrittszeiten
+        let freight_policy = config.freight_policy;
+
+        // Jede Fahrt muss mit den Fahrstühlen erreichbar sein, die die Last aufnehmen
+        let mut journeys = Vec::new();
+        for (id, arrival) in arrivals.iter().enumerate() {
+            let legs = plan_arrival(&specs, arrival).ok_or_else(|| {
+                format!(
+                    "passenger {} ({}): floor {} is not reachable from floor {}",
+                    id, arrival.profile, arrival.target, arrival.origin
+                )
+            })?;
+            journeys.push(legs);
+        }
+
+        let access = Arc::new(AccessControl::new(
+            config.secured_floors.clone(),
+            start_hour,
+        ));
+        let (control_tx, control_rx) = unbounded();
+        let (status_tx, status_rx) = unbounded();
+
+        let metrics = Arc::new(Mutex::new(Metrics::new()));
+
+        // Abgestürzte Steuerung, Fahrstühle und Etagen werden neu gestartet, hängende Akteure abgeschottet
+        let supervisor = Supervisor::new(
+            SupervisorConfig {
+                heartbeat_timeout: Duration::from_secs(15),
+                control_system: FailurePolicy::Restart { max_restarts: 3 },
+                elevator: FailurePolicy::Restart { max_restarts: 2 },
+                floor: FailurePolicy::Restart { max_restarts: 3 },
+                passenger: FailurePolicy::Fence,
+            },
+            status_tx.clone(),
+            Arc::clone(&metrics),
+        );
+
+        // Sicherheitsregeln prüfen, bevor sich der erste Fahrstuhl bewegt
+        let (observation_tx, observation_rx) = unbounded();
+        dashboard.subscribe(observation_tx.clone());
+        InvariantMonitor::spawn(
+            specs.clone(),
+            config
+                .elevators
+                .iter()
+                .map(|elevator| elevator.shaft)
+                .collect(),
+            config.min_separation,
+            config.hall_call_limit,
+            Arc::clone(&metrics),
+            observation_rx,
+        );
+        let mut simulation = Self {
+            config: config.clone(),
+            floors,
+            sky_lobby: config.sky_lobby,
+            specs: Arc::new(specs.clone()),
+            arrivals: Mutex::new(Vec::new()),
+            console_inputs: Mutex::new(Vec::new()),
+            patience: config.patience(),
+            metrics,
+            dashboard,
+            control_tx,
+            status_tx,
+            elevator_senders: Vec::new(),
+            floor_transmitter: Arc::new(RwLock::new(HashMap::new())),
+            lanterns: Arc::new(RwLock::new(Vec::new())),
+            elevator_passenger_transmitter: Arc::new(RwLock::new(HashMap::new())),
+            passenger_elevator_transmitter: Arc::new(RwLock::new(Vec::new())),
+            observations: observation_tx,
+            supervisor,
+        };
+        let passenger_elevator_receiver: Arc<RwLock<Vec<Receiver<Request<PassengerToElevator>>>>> =
+            Arc::new(RwLock::new(Vec::new()));
+
+        // Etagen initialisieren
+        for i in 0..floors {
+            let (floor_tx, floor_rx) = unbounded();
+            simulation
+                .lanterns
+                .write()
+                .unwrap()
+                .push(Lantern::default());
+            simulation
+                .floor_transmitter
+                .write()
+                .unwrap()
+                .insert(i, floor_tx);
+            Floor::spawn(
+                i,
+                simulation.control_tx.clone(),
+                floor_rx,
+                &simulation.supervisor,
+            );
+        }
+
+        for _ in 0..elevators {
+            let (elevator_tx, elevator_rx) = unbounded();
+            simulation
+                .passenger_elevator_transmitter
+                .write()
+                .unwrap()
+                .push(elevator_tx);
+            passenger_elevator_receiver
+                .write()
+                .unwrap()
+                .push(elevator_rx);
+        }
 
-        // Zufällige Startetage zwischen 0 und floors - 1 auswählen
-        let random_start_floor = rand::thread_rng().gen_range(0..floors) as u8;
-        let random_target_floor = loop {
-            let floor = rand::thread_rng().gen_range(0..floors) as u8;
-            if floor != random_start_floor {
-                break floor;
+        // Gruppen: gemeinsame Start- und Zielebene, ein Fahrkorb für alle
+        let mut groups: BTreeMap<usize, Vec<(usize, PassengerProfile)>> = BTreeMap::new();
+        for (id, arrival) in arrivals.iter().enumerate() {
+            if let Some(group) = arrival.group {
+                groups.entry(group).or_default().push((id, arrival.profile));
             }
+        }
+        let mut shared_groups: BTreeMap<usize, Arc<Group>> = BTreeMap::new();
+        for (group_id, members) in groups {
+            println!(
+                "Group {}: {} passengers arriving together",
+                group_id,
+                members.len()
+            );
+            simulation.metrics.lock().unwrap().group_arrived(
+                group_id,
+                &members.iter().map(|&(id, _)| id).collect::<Vec<_>>(),
+            );
+            shared_groups.insert(group_id, Group::new(group_id, members));
+        }
+
+        // Passagiere initialisieren
+        for (arrival, legs) in arrivals.iter().zip(journeys) {
+            let group = arrival
+                .group
+                .map(|group| Arc::clone(&shared_groups[&group]));
+            simulation.add_passenger(arrival, legs, group, arrival.at);
+        }
+
+        // Fahrstühle initialisieren
+        for (id, spec) in specs.iter().enumerate() {
+            let (elevator_tx, elevator_rx) = unbounded();
+            simulation.elevator_senders.push(elevator_tx);
+            Elevator::spawn(
+                id,
+                elevator_rx,
+                simulation.status_tx.clone(),
+                Arc::clone(&simulation.lanterns),
+                Arc::clone(&simulation.elevator_passenger_transmitter),
+                Arc::clone(&passenger_elevator_receiver),
+                spec.clone(),
+                shafts[id].clone(),
+                Arc::clone(&simulation.metrics),
+                Arc::clone(&access),
+                freight_policy,
+                Arc::clone(&simulation.dashboard),
+                simulation.observations.clone(),
+                &simulation.supervisor,
+            );
+        }
+
+        // Fehlerskript für Resilienztests aus der Konfiguration; im Beispiel fährt das Shuttle zusätzlich
+        // nach 5 Sekunden nur noch mit halber Geschwindigkeit.
+        let mut fault_script = config.faults.clone();
+        if config.demo_script {
+            fault_script.push(FaultInjection {
+                after: Duration::from_secs(5),
+                elevator: 0,
+                fault: Fault::Slow(2.0),
+            });
+        }
+        if !fault_script.is_empty() {
+            faults::spawn_injector(fault_script, simulation.elevator_senders.clone());
+        }
+
+        // Im Brandfall zur Lobby, bei Alarm in der Lobby zur Sky-Lobby
+        let fire_config = FireServiceConfig {
+            designated_floor: 0,
+            alternate_floor: config.sky_lobby,
         };
-        Passenger::new(
-            i,
-            random_start_floor,
-            Arc::clone(&floor_transmitter),
-            Arc::clone(&elevator_floor_receiver),
-            passenger_rx,
-            Arc::clone(&passenger_elevator_transmitter),
-            random_target_floor
+
+        // Control System initialisieren
+        let _control_system = ControlSystem::new(
+            simulation.elevator_senders.clone(),
+            control_rx,
+            status_rx,
+            specs,
+            shafts,
+            config.parking_policy()?,
+            start_hour,
+            fire_config,
+            Arc::clone(&simulation.metrics),
+            freight_policy,
+            &simulation.supervisor,
         );
+
+        if config.demo_script {
+            // Betriebsarten: Liftführer im unteren, Umzug im oberen Lokalfahrstuhl, zum Schluss eine VIP-Fahrt.
+            // Stromausfall: das Notstromaggregat reicht für einen Fahrstuhl, danach Brandschutzübung mit Alarm in
+            // Ebene 2; die Feuerwehr bedient den unteren Lokalfahrstuhl mit Dauerkontakt-Tastern
+            // (der erste Druck ist zu kurz, die Tür schließt wieder)
+            scenario::spawn_script(
+                vec![
+                    (
+                        Duration::from_secs(2),
+                        ControlCommand::SetMode(ElevatorId(1), OperatingMode::Attendant),
+                    ),
+                    (
+                        Duration::from_secs(2),
+                        ControlCommand::SetMode(ElevatorId(2), OperatingMode::Independent),
+                    ),
+                    (
+                        Duration::from_secs(4),
+                        ControlCommand::Attendant(
+                            ElevatorId(1),
+                            AttendantInput::CarCall(FloorId(3)),
+                        ),
+                    ),
+                    (
+                        Duration::from_secs(5),
+                        ControlCommand::Attendant(ElevatorId(1), AttendantInput::Start),
+                    ),
+                    (
+                        Duration::from_secs(10),
+                        ControlCommand::SetMode(ElevatorId(1), OperatingMode::Automatic),
+                    ),
+                    (
+                        Duration::from_secs(10),
+                        ControlCommand::SetMode(ElevatorId(2), OperatingMode::Automatic),
+                    ),
+                    (
+                        Duration::from_secs(54),
+                        ControlCommand::SetMode(ElevatorId(2), OperatingMode::Vip(6)),
+                    ),
+                    (Duration::from_secs(12), ControlCommand::EmergencyPower(1)),
+                    (Duration::from_secs(28), ControlCommand::NormalPower),
+                    (
+                        Duration::from_secs(35),
+                        ControlCommand::FireAlarm(FloorId(2)),
+                    ),
+                    (
+                        Duration::from_secs(40),
+                        ControlCommand::Firefighter(FirefighterInput::KeyOn(ElevatorId(1))),
+                    ),
+                    (
+                        Duration::from_secs(41),
+                        ControlCommand::Firefighter(FirefighterInput::Door(
+                            DoorButton::Close,
+                            Duration::from_secs(2),
+                        )),
+                    ),
+                    (
+                        Duration::from_secs(43),
+                        ControlCommand::Firefighter(FirefighterInput::CarCall(FloorId(3))),
+                    ),
+                    (
+                        Duration::from_secs(44),
+                        ControlCommand::Firefighter(FirefighterInput::Door(
+                            DoorButton::Open,
+                            Duration::from_millis(400),
+                        )),
+                    ),
+                    (
+                        Duration::from_secs(46),
+                        ControlCommand::Firefighter(FirefighterInput::Door(
+                            DoorButton::Open,
+                            Duration::from_secs(2),
+                        )),
+                    ),
+                    (
+                        Duration::from_secs(49),
+                        ControlCommand::Firefighter(FirefighterInput::KeyOff(ElevatorId(1))),
+                    ),
+                    (Duration::from_secs(52), ControlCommand::FireAlarmReset),
+                ],
+                simulation.control_tx.clone(),
+            );
+        }
+
+        Ok(simulation)
     }
 
-    // Fahrstühle initialisieren
-    for id in 0..elevators {
-        let (elevator_tx, elevator_rx) = unbounded();
-        elevator_senders.push(elevator_tx);
-        Elevator::new(
+    // Startet einen Passagier, der nach `departure` in seiner Startebene wartet; gibt seine ID zurück.
+    // `arrival` wird für den Snapshot festgehalten, `arrival.at` zählt wie die Uhr der Simulation ab ihrem Start.
+    fn add_passenger(
+        &self,
+        arrival: &Arrival,
+        legs: Vec<Leg>,
+        group: Option<Arc<Group>>,
+        departure: Duration,
+    ) -> usize {
+        let id = {
+            let mut arrivals = self.arrivals.lock().unwrap();
+            arrivals.push(arrival.clone());
+            arrivals.len() - 1
+        };
+        let (passenger_tx, passenger_rx) = unbounded();
+        self.elevator_passenger_transmitter
+            .write()
+            .unwrap()
+            .insert(PassengerId(id), passenger_tx);
+
+        Passenger::spawn(
             id,
-            elevator_rx,
-            status_tx.clone(),
-            Arc::clone(&elevator_floor_transmitter),
-            Arc::clone(&elevator_passenger_transmitter),
-            Arc::clone(&passenger_elevator_receiver),
+            arrival.origin,
+            Arc::clone(&self.floor_transmitter),
+            Arc::clone(&self.lanterns),
+            passenger_rx,
+            Arc::clone(&self.elevator_passenger_transmitter),
+            Arc::clone(&self.passenger_elevator_transmitter),
+            legs,
+            Arc::clone(&self.specs),
+            arrival.badges.clone(),
+            arrival.profile,
+            self.patience,
+            self.config.jitter,
+            self.config.seed.unwrap_or_default(),
+            group,
+            departure,
+            Arc::clone(&self.metrics),
+            self.observations.clone(),
+            &self.supervisor,
         );
+        id
     }
+}
 
-    // Control System initialisieren
-    let control_system = ControlSystem::new(elevator_senders, control_rx, status_rx);
+// Route mit den Fahrstühlen, die die Last des Passagiers aufnehmen
+fn plan_arrival(specs: &[ElevatorSpec], arrival: &Arrival) -> Option<Vec<Leg>> {
+    let usable: Vec<ElevatorSpec> = specs
+        .iter()
+        .filter(|spec| car_accepts(spec, arrival.profile))
+        .cloned()
+        .collect();
+    plan_journey(arrival.origin, arrival.target, &usable)
+}
 
-    //floor_channels.read().unwrap().get(&1).unwrap().send(FloorCommand::Request { floor: 1, direction: Direction::Up }).unwrap();
+// Baut das Gebäude nach `config` auf, lässt die Passagiere aus `arrivals` ankommen und gibt die gesammelten
+// Kennzahlen zurück, sobald die Uhr der Simulation `config.duration` erreicht.
+// `resume`: Snapshot dieses Laufs, bis zu dem der Lauf ohne Protokoll erneut abgespielt wird.
+// `show_dashboard`: Live-Anzeige im Terminal statt des fortlaufenden Protokolls.
+// `snapshot_to`: Datei und Zeitpunkt für einen Snapshot, spätestens am Ende des Laufs.
+fn simulate(
+    config: &SimulationConfig,
+    arrivals: &[Arrival],
+    resume: Option<&Snapshot>,
+    show_dashboard: bool,
+    snapshot_to: Option<(&str, Duration)>,
+) -> Result<Arc<Mutex<Metrics>>, String> {
+    let dashboard = Dashboard::new(
+        config.floors,
+        &config.specs(),
+        &config
+            .elevators
+            .iter()
+            .map(|elevator| elevator.shaft)
+            .collect::<Vec<_>>(),
+    );
+    // Das Protokoll muss umgeleitet sein, bevor die ersten Akteure starten
+    let log_capture = if show_dashboard {
+        Some(LogCapture::start(&dashboard)?)
+    } else {
+        None
+    };
+    // Ohne Anzeige so schnell wie möglich, mit Anzeige in Echtzeit; das erneute Abspielen immer so schnell wie möglich
+    let speed = if show_dashboard { Some(1.0) } else { None };
+    clock::configure(if resume.is_some() { None } else { speed });
+    let simulation = match resume {
+        Some(snapshot) => {
+            let simulation =
+                snapshot.replay(|| Simulation::start(config, arrivals, Arc::clone(&dashboard)))?;
+            println!(
+                "Simulation: replayed the run up to the snapshot at {:.1}s, continuing",
+                snapshot.taken_at.as_secs_f64()
+            );
+            clock::set_speed(speed);
+            simulation
+        }
+        None => Simulation::start(config, arrivals, Arc::clone(&dashboard))?,
+    };
+
+    // Die Fahrgäste kommen nach ihren Ankunftszeiten auf der Uhr der Simulation, nicht nach der Wanduhr.
+    // Läuft bis `until` auf der Uhr der Simulation; `true`, wenn die Anzeige vorher beendet wurde.
+    // Danach steht die Uhr, bis sie erneut weiterlaufen soll.
+    let run_until = |until: Duration| match &log_capture {
+        Some(_) => dashboard.run(&simulation.metrics, until),
+        None => {
+            let elapsed = simulation.metrics.lock().unwrap().clock();
+            clock::run_until(clock::now() + until.saturating_sub(elapsed));
+            Ok(false)
+        }
+    };
+    let mut quit = false;
+    if let Some((path, at)) = snapshot_to {
+        quit = run_until(at.min(config.duration))?;
+        let snapshot = Snapshot::take(&simulation);
+        snapshot.save(path)?;
+        println!(
+            "Simulation: snapshot at {:.1}s written to {}",
+            snapshot.taken_at.as_secs_f64(),
+            path
+        );
+    }
+    if !quit {
+        run_until(config.duration)?;
+    }
 
-    // Beispieleingaben
-    // control_tx
-    //     .send(ControlCommand::Request {
-    //         floor: 2,
-    //         direction: Direction::Up,
-    //     })
-    //     .unwrap();
+    Ok(simulation.metrics)
+}
 
-    // Simulieren von Anfragen
-    // Passenger beachtet nicht dass der Fahrstuhl noch wartet bis die 10 Sekunden um sind
-    thread::sleep(Duration::from_secs(30));
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(error) = cli::run(&args) {
+        eprintln!("error: {}", error);
+        std::process::exit(2);
+    }
 }
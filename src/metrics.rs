@@ -0,0 +1,1037 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::access::AccessDenied;
+use crate::clock::Instant;
+use crate::energy::{EnergyModel, TripEnergy};
+use crate::events::{Event, Subject};
+use crate::faults::Fault;
+use crate::invariants::Violation;
+use crate::journey::Leg;
+use crate::patience::Abandonment;
+use crate::profile::PassengerProfile;
+
+// Zeitstempel eines einzelnen Teilstücks, jeweils Zeit seit Start der Simulation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LegRecord {
+    leg: Leg,
+    elevator: Option<(usize, String)>, // Fahrstuhl-ID und Deck
+    requested_at: Duration,
+    boarded_at: Option<Duration>,
+    alighted_at: Option<Duration>,
+    interrupted_at: Option<u8>, // Ebene, in der der Passagier wegen eines Ausfalls aussteigen musste
+    abandoned: Option<(Abandonment, Duration)>, // Warten abgebrochen: Treppe genommen oder aufgegeben
+}
+
+impl LegRecord {
+    fn wait_time(&self) -> Option<Duration> {
+        self.boarded_at.map(|boarded| boarded - self.requested_at)
+    }
+
+    // Wartezeit, bis der Passagier eingestiegen ist oder das Warten abgebrochen hat
+    fn wait_time_including_abandoned(&self) -> Option<Duration> {
+        self.wait_time()
+            .or_else(|| self.abandoned.map(|(_, at)| at - self.requested_at))
+    }
+
+    fn ride_time(&self) -> Option<Duration> {
+        match (self.boarded_at, self.alighted_at) {
+            (Some(boarded), Some(alighted)) => Some(alighted - boarded),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JourneyRecord {
+    profile: PassengerProfile,
+    refusals: usize, // Zurückgewiesen, weil im Deck nicht genug Platz war
+    planned_legs: usize,
+    started_at: Duration,
+    finished_at: Option<Duration>,
+    legs: Vec<LegRecord>,
+}
+
+impl JourneyRecord {
+    // Der Passagier hat nach zu langem Warten aufgegeben
+    fn gave_up(&self) -> bool {
+        self.finished_at.is_none()
+            && matches!(self.legs.last(), Some(record) if matches!(record.abandoned, Some((Abandonment::GaveUp, _))))
+    }
+}
+
+// Ausfall eines Fahrstuhls mit den betroffenen Passagieren
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FailureRecord {
+    elevator_id: usize,
+    fault: Fault,
+    floor: u8,
+    at: Duration, // Zeit seit Start der Simulation
+    passengers: Vec<usize>,
+}
+
+// Abgelehnter Fahrbefehl an einer gesicherten Ebene
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DenialRecord {
+    passenger_id: usize,
+    elevator_id: usize,
+    floor: u8,  // Ebene, in der der Passagier den Fahrkorb verlassen hat
+    target: u8, // Gesicherte Zielebene
+    reason: AccessDenied,
+    at: Duration,
+}
+
+// Gemeinsam angekommene Fahrgäste
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GroupRecord {
+    members: Vec<usize>,
+    splits: usize, // Mitglieder, die keinen Platz mehr im Fahrkorb der Gruppe fanden
+}
+
+// Energiebilanz eines Fahrstuhls über den gesamten Lauf
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EnergyRecord {
+    model: EnergyModel,
+    trips: usize,
+    floors_travelled: u32,
+    consumed_kwh: f64,
+    regenerated_kwh: f64,
+}
+
+// Stand eines Passagiers auf seiner Fahrt, siehe `Metrics::progress`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Progress {
+    NotStarted,
+    Waiting { leg: Leg, waited: Duration },
+    Riding(Leg),
+    Walking(Leg), // Nimmt für dieses Teilstück die Treppe
+    AtFloor(u8),  // Ausgestiegen, das nächste Teilstück ist noch nicht angefragt
+    Done,         // Angekommen, aufgegeben oder an einer gesicherten Ebene abgewiesen
+}
+
+// Ausgang einer Fahrt am Ende des Laufs, siehe `Metrics::outcome`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    Completed,
+    Abandoned,  // Nach zu langem Warten aufgegeben
+    Failed,     // Ausfall, abgewiesener Fahrbefehl, abgeschotteter Akteur oder Regelverletzung
+    Unfinished, // Noch unterwegs
+}
+
+// Kennzahlen eines Laufs für die maschinenlesbare Ausgabe (JSON und CSV)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Summary {
+    pub passengers: usize,
+    pub completed: usize,
+    pub abandoned: usize,
+    pub legs_by_stairs: usize,
+    pub average_journey_secs: Option<f64>,
+    pub average_wait_secs: Option<f64>,
+    pub average_wait_including_abandoned_secs: Option<f64>,
+    pub refused_boardings: usize,
+    pub group_splits: usize,
+    pub failures: usize,
+    pub access_denials: usize,
+    pub invariant_violations: usize,
+    pub unfinished: usize,     // Noch unterwegs, ohne Ausfall als Grund
+    pub failed: usize, // Wegen eines Ausfalls, einer Abweisung oder einer Regelverletzung nicht angekommen
+    pub stalled_actors: usize, // Akteure, die der Supervisor wegen fehlender Heartbeats abgeschottet hat
+    pub energy_kwh: f64,
+}
+
+impl Summary {
+    pub const CSV_HEADER: &'static str = "passengers,completed,abandoned,legs_by_stairs,average_journey_secs,average_wait_secs,average_wait_including_abandoned_secs,refused_boardings,group_splits,failures,access_denials,invariant_violations,unfinished,failed,stalled_actors,energy_kwh";
+
+    pub fn csv_row(&self) -> String {
+        let secs = |value: Option<f64>| value.map_or(String::new(), |secs| format!("{:.3}", secs));
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{:.6}",
+            self.passengers,
+            self.completed,
+            self.abandoned,
+            self.legs_by_stairs,
+            secs(self.average_journey_secs),
+            secs(self.average_wait_secs),
+            secs(self.average_wait_including_abandoned_secs),
+            self.refused_boardings,
+            self.group_splits,
+            self.failures,
+            self.access_denials,
+            self.invariant_violations,
+            self.unfinished,
+            self.failed,
+            self.stalled_actors,
+            self.energy_kwh
+        )
+    }
+}
+
+// Sammelt Fahrtzeiten aller Passagiere und den Energieverbrauch der Fahrstühle,
+// gemeinsam genutzt über Arc<Mutex<Metrics>>. Alle Zeitpunkte zählen ab dem Start der Simulation,
+// ein Snapshot hält die Kennzahlen daher unabhängig von der Uhr des Prozesses fest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Metrics {
+    #[serde(skip, default = "Instant::now")]
+    started: Instant,
+    journeys: BTreeMap<usize, JourneyRecord>,
+    energy: BTreeMap<usize, EnergyRecord>,
+    failures: Vec<FailureRecord>,
+    denials: Vec<DenialRecord>,
+    groups: BTreeMap<usize, GroupRecord>,
+    power_events: Vec<(Duration, String)>, // Ablauf im Notstrombetrieb, Zeit seit Start der Simulation
+    events: Vec<(Duration, Event)>,        // Ereignisprotokoll in der Reihenfolge der Meldungen
+    #[serde(default)]
+    violations: Vec<(Duration, Violation)>, // Vom `InvariantMonitor` gemeldet
+    #[serde(default)]
+    fenced_elevators: BTreeSet<usize>, // Vom Supervisor abgeschottet
+    #[serde(default)]
+    fenced_passengers: BTreeSet<usize>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            journeys: BTreeMap::new(),
+            energy: BTreeMap::new(),
+            failures: Vec::new(),
+            denials: Vec::new(),
+            groups: BTreeMap::new(),
+            power_events: Vec::new(),
+            events: Vec::new(),
+            violations: Vec::new(),
+            fenced_elevators: BTreeSet::new(),
+            fenced_passengers: BTreeSet::new(),
+        }
+    }
+
+    // Simulierte Zeit seit dem Start
+    pub fn clock(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn register_elevator(&mut self, elevator_id: usize, model: EnergyModel) {
+        self.energy.insert(
+            elevator_id,
+            EnergyRecord {
+                model,
+                trips: 0,
+                floors_travelled: 0,
+                consumed_kwh: 0.0,
+                regenerated_kwh: 0.0,
+            },
+        );
+    }
+
+    pub fn trip_completed(&mut self, elevator_id: usize, from: u8, to: u8, energy: TripEnergy) {
+        self.record(Event::Trip {
+            elevator: elevator_id,
+            from,
+            to,
+        });
+        if let Some(record) = self.energy.get_mut(&elevator_id) {
+            record.trips += 1;
+            record.floors_travelled += (to as i32 - from as i32).unsigned_abs();
+            record.consumed_kwh += energy.consumed_kwh;
+            record.regenerated_kwh += energy.regenerated_kwh;
+        }
+    }
+
+    pub fn journey_started(
+        &mut self,
+        passenger_id: usize,
+        legs: &[Leg],
+        profile: PassengerProfile,
+    ) {
+        let now = self.clock();
+        self.record(Event::JourneyStarted {
+            passenger: passenger_id,
+            from: legs.first().map_or(0, |leg| leg.from),
+            to: legs.last().map_or(0, |leg| leg.to),
+            profile,
+            legs: legs.len(),
+        });
+        self.journeys.insert(
+            passenger_id,
+            JourneyRecord {
+                profile,
+                refusals: 0,
+                planned_legs: legs.len(),
+                started_at: now,
+                finished_at: None,
+                legs: Vec::new(),
+            },
+        );
+    }
+
+    // Passagier steht (wieder) in der Warteschlange einer Ebene
+    pub fn leg_requested(&mut self, passenger_id: usize, leg: Leg) {
+        let now = self.clock();
+        if let Some(journey) = self.journeys.get_mut(&passenger_id) {
+            // Wiederholte Anfragen für dasselbe Teilstück zählen zur ursprünglichen Wartezeit
+            if matches!(journey.legs.last(), Some(record) if record.leg == leg && record.boarded_at.is_none())
+            {
+                return;
+            }
+            self.events.push((
+                now,
+                Event::LegRequested {
+                    passenger: passenger_id,
+                    leg,
+                },
+            ));
+            journey.legs.push(LegRecord {
+                leg,
+                elevator: None,
+                requested_at: now,
+                boarded_at: None,
+                alighted_at: None,
+                interrupted_at: None,
+                abandoned: None,
+            });
+        }
+    }
+
+    pub fn leg_boarded(&mut self, passenger_id: usize, elevator_id: usize, deck: &str) {
+        let now = self.clock();
+        if let Some(record) = self.current_leg(passenger_id) {
+            record.elevator = Some((elevator_id, deck.to_string()));
+            record.boarded_at = Some(now);
+            self.record(Event::Boarded {
+                passenger: passenger_id,
+                elevator: elevator_id,
+                deck: deck.to_string(),
+            });
+        }
+    }
+
+    pub fn leg_completed(&mut self, passenger_id: usize) {
+        let now = self.clock();
+        if let Some(record) = self.current_leg(passenger_id) {
+            record.alighted_at = Some(now);
+            let floor = record.leg.to;
+            self.record(Event::LegCompleted {
+                passenger: passenger_id,
+                floor,
+            });
+        }
+        if let Some(journey) = self.journeys.get_mut(&passenger_id) {
+            let completed = journey
+                .legs
+                .iter()
+                .filter(|l| l.alighted_at.is_some())
+                .count();
+            if completed == journey.planned_legs {
+                journey.finished_at = Some(journey.legs.last().unwrap().alighted_at.unwrap());
+            }
+        }
+    }
+
+    pub fn boarding_refused(&mut self, passenger_id: usize) {
+        if let Some(journey) = self.journeys.get_mut(&passenger_id) {
+            journey.refusals += 1;
+        }
+    }
+
+    // Fahrstuhl ist ausgefallen, der Passagier musste in `floor` aussteigen
+    pub fn leg_interrupted(&mut self, passenger_id: usize, floor: u8) {
+        if let Some(record) = self.current_leg(passenger_id) {
+            record.interrupted_at = Some(floor);
+            self.record(Event::LegInterrupted {
+                passenger: passenger_id,
+                floor,
+            });
+        }
+    }
+
+    // Passagier hat zu lange gewartet; bei `Stairs` folgt `leg_completed`, sobald er die Zielebene erreicht
+    pub fn leg_abandoned(&mut self, passenger_id: usize, abandonment: Abandonment) {
+        let now = self.clock();
+        if let Some(record) = self.current_leg(passenger_id) {
+            record.abandoned = Some((abandonment, now));
+            self.record(Event::LegAbandoned {
+                passenger: passenger_id,
+                abandonment,
+            });
+        }
+    }
+
+    // Neue Route ab der aktuellen Ebene; bereits abgeschlossene Teilstücke bleiben erhalten
+    pub fn journey_replanned(&mut self, passenger_id: usize, remaining_legs: usize) {
+        let now = self.clock();
+        if let Some(journey) = self.journeys.get_mut(&passenger_id) {
+            let completed = journey
+                .legs
+                .iter()
+                .filter(|l| l.alighted_at.is_some())
+                .count();
+            journey.planned_legs = completed + remaining_legs;
+            if remaining_legs == 0 {
+                // Der Passagier wurde direkt in seiner Zielebene herausgelassen
+                journey.finished_at = Some(now);
+            }
+            self.events.push((
+                now,
+                Event::JourneyReplanned {
+                    passenger: passenger_id,
+                    remaining_legs,
+                },
+            ));
+        }
+    }
+
+    pub fn elevator_failed(
+        &mut self,
+        elevator_id: usize,
+        fault: Fault,
+        floor: u8,
+        passengers: &[usize],
+    ) {
+        self.record(Event::ElevatorFailed {
+            elevator: elevator_id,
+            fault,
+            floor,
+            passengers: passengers.to_vec(),
+        });
+        self.failures.push(FailureRecord {
+            elevator_id,
+            fault,
+            floor,
+            at: self.clock(),
+            passengers: passengers.to_vec(),
+        });
+    }
+
+    pub fn access_denied(
+        &mut self,
+        passenger_id: usize,
+        elevator_id: usize,
+        floor: u8,
+        target: u8,
+        reason: AccessDenied,
+    ) {
+        self.record(Event::AccessDenied {
+            passenger: passenger_id,
+            elevator: elevator_id,
+            target,
+            reason,
+        });
+        self.denials.push(DenialRecord {
+            passenger_id,
+            elevator_id,
+            floor,
+            target,
+            reason,
+            at: self.clock(),
+        });
+    }
+
+    pub fn group_arrived(&mut self, group_id: usize, members: &[usize]) {
+        self.groups.insert(
+            group_id,
+            GroupRecord {
+                members: members.to_vec(),
+                splits: 0,
+            },
+        );
+    }
+
+    pub fn group_split(&mut self, group_id: usize) {
+        if let Some(group) = self.groups.get_mut(&group_id) {
+            group.splits += 1;
+        }
+        self.record(Event::GroupSplit { group: group_id });
+    }
+
+    pub fn power_event(&mut self, event: String) {
+        self.record(Event::Power {
+            message: event.clone(),
+        });
+        self.power_events.push((self.clock(), event));
+    }
+
+    pub fn actor_stalled(&mut self, actor: String) {
+        self.record(Event::ActorStalled { actor });
+    }
+
+    pub fn elevator_fenced(&mut self, elevator_id: usize) {
+        self.fenced_elevators.insert(elevator_id);
+    }
+
+    pub fn passenger_fenced(&mut self, passenger_id: usize) {
+        self.fenced_passengers.insert(passenger_id);
+    }
+
+    pub fn invariant_violated(&mut self, violation: Violation) {
+        self.record(Event::InvariantViolated(violation.clone()));
+        self.violations.push((self.clock(), violation));
+    }
+
+    fn record(&mut self, event: Event) {
+        let now = self.clock();
+        self.events.push((now, event));
+    }
+
+    // Ereignisprotokoll, eine Zeile je Ereignis, nach Betroffenem sortiert (siehe `Subject`)
+    pub fn event_log(&self) -> Vec<String> {
+        let mut events: Vec<&(Duration, Event)> = self.events.iter().collect();
+        events.sort_by_key(|(_, event)| event.subject());
+        events
+            .into_iter()
+            .map(|(at, event)| {
+                format!("{:>7.1}s  {}: {}", at.as_secs_f64(), event.subject(), event)
+            })
+            .collect()
+    }
+
+    // Ereignisprotokoll und Kennzahlen als Text für `--format events` und die Golden-Dateien.
+    // Der Energieverbrauch hängt über den Bereitschaftsverbrauch von der Laufzeit ab, daher nur Fahrten und Ebenen.
+    pub fn event_report(&self) -> String {
+        let summary = self.summary();
+        let secs =
+            |value: Option<f64>| value.map_or("-".to_string(), |secs| format!("{:.1}s", secs));
+        let mut lines = vec!["===== Events =====".to_string()];
+        lines.extend(self.event_log());
+        lines.push("===== Summary =====".to_string());
+        lines.push(format!("passengers {}", summary.passengers));
+        lines.push(format!("completed {}", summary.completed));
+        lines.push(format!("abandoned {}", summary.abandoned));
+        lines.push(format!("unfinished {}", summary.unfinished));
+        lines.push(format!("failed {}", summary.failed));
+        lines.push(format!("legs by stairs {}", summary.legs_by_stairs));
+        lines.push(format!(
+            "average journey {}",
+            secs(summary.average_journey_secs)
+        ));
+        lines.push(format!("average wait {}", secs(summary.average_wait_secs)));
+        lines.push(format!("refused boardings {}", summary.refused_boardings));
+        lines.push(format!("group splits {}", summary.group_splits));
+        lines.push(format!("failures {}", summary.failures));
+        lines.push(format!("access denials {}", summary.access_denials));
+        lines.push(format!(
+            "invariant violations {}",
+            summary.invariant_violations
+        ));
+        for (elevator_id, record) in &self.energy {
+            lines.push(format!(
+                "elevator {}: {} trip(s) over {} floor(s)",
+                elevator_id, record.trips, record.floors_travelled
+            ));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    pub fn summary(&self) -> Summary {
+        let legs: Vec<&LegRecord> = self
+            .journeys
+            .values()
+            .flat_map(|journey| journey.legs.iter())
+            .collect();
+        let secs = |durations: &[Duration]| average(durations).map(|d| d.as_secs_f64());
+        let journeys: Vec<Duration> = self
+            .journeys
+            .values()
+            .filter_map(|journey| Some(journey.finished_at? - journey.started_at))
+            .collect();
+        let waits: Vec<Duration> = legs
+            .iter()
+            .filter_map(|record| record.wait_time())
+            .collect();
+        let all_waits: Vec<Duration> = legs
+            .iter()
+            .filter_map(|record| record.wait_time_including_abandoned())
+            .collect();
+        let elapsed = self.clock();
+        let outcomes: Vec<Outcome> = self
+            .journeys
+            .keys()
+            .map(|&passenger_id| self.outcome(passenger_id))
+            .collect();
+        let count = |outcome: Outcome| outcomes.iter().filter(|&&other| other == outcome).count();
+        Summary {
+            passengers: self.journeys.len(),
+            completed: count(Outcome::Completed),
+            abandoned: count(Outcome::Abandoned),
+            legs_by_stairs: legs
+                .iter()
+                .filter(|record| matches!(record.abandoned, Some((Abandonment::Stairs, _))))
+                .count(),
+            average_journey_secs: secs(&journeys),
+            average_wait_secs: secs(&waits),
+            average_wait_including_abandoned_secs: secs(&all_waits),
+            refused_boardings: self.journeys.values().map(|journey| journey.refusals).sum(),
+            group_splits: self.groups.values().map(|group| group.splits).sum(),
+            failures: self.failures.len(),
+            access_denials: self.denials.len(),
+            invariant_violations: self.violations.len(),
+            unfinished: count(Outcome::Unfinished),
+            failed: count(Outcome::Failed),
+            stalled_actors: self
+                .events
+                .iter()
+                .filter(|(_, event)| matches!(event, Event::ActorStalled { .. }))
+                .count(),
+            energy_kwh: self
+                .energy
+                .values()
+                .map(|record| {
+                    record.consumed_kwh - record.regenerated_kwh + record.model.standby_kwh(elapsed)
+                })
+                .sum(),
+        }
+    }
+
+    // Teilstücke, für die Passagiere gerade in ihrer Ebene warten (für die Live-Anzeige)
+    pub fn waiting(&self) -> Vec<Leg> {
+        self.journeys
+            .values()
+            .filter_map(|journey| journey.legs.last())
+            .filter(|record| {
+                record.boarded_at.is_none()
+                    && record.abandoned.is_none()
+                    && record.interrupted_at.is_none()
+            })
+            .map(|record| record.leg)
+            .collect()
+    }
+
+    // Wartende mit ihrer bisherigen Wartezeit (für den `InvariantMonitor`)
+    pub fn waiting_since(&self) -> Vec<(usize, Leg, Duration)> {
+        self.journeys
+            .keys()
+            .filter_map(|&passenger_id| match self.progress(passenger_id) {
+                Progress::Waiting { leg, waited } => Some((passenger_id, leg, waited)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Ausgang einer Fahrt; jeder Passagier zählt in genau einer Gruppe der Kennzahlen
+    fn outcome(&self, passenger_id: usize) -> Outcome {
+        let Some(journey) = self.journeys.get(&passenger_id) else {
+            return Outcome::Unfinished;
+        };
+        if journey.finished_at.is_some() {
+            return Outcome::Completed;
+        }
+        if journey.gave_up() {
+            return Outcome::Abandoned;
+        }
+        // Im Fahrkorb eines ausgefallenen oder abgeschotteten Fahrstuhls eingeschlossen
+        let trapped = journey.legs.last().is_some_and(|record| {
+            match (&record.elevator, record.boarded_at, record.alighted_at) {
+                (Some((elevator_id, _)), Some(boarded), None) => {
+                    self.fenced_elevators.contains(elevator_id)
+                        || self.failures.iter().any(|failure| {
+                            failure.elevator_id == *elevator_id && failure.at >= boarded
+                        })
+                }
+                _ => false,
+            }
+        });
+        let failed = trapped
+            || self.progress(passenger_id) == Progress::Done
+            || self.fenced_passengers.contains(&passenger_id)
+            || self
+                .failures
+                .iter()
+                .any(|failure| failure.passengers.contains(&passenger_id))
+            || self.violations.iter().any(|(_, violation)| {
+                // Ein unbedienter Ruf ist kein Ausfall, der Passagier wartet noch
+                !matches!(violation, Violation::HallCallUnserved { .. })
+                    && violation.subject() == Subject::Passenger(passenger_id)
+            });
+        if failed {
+            Outcome::Failed
+        } else {
+            Outcome::Unfinished
+        }
+    }
+
+    // Wo ein Passagier auf seiner Fahrt gerade steht, abgeleitet aus den Zeitstempeln (für Snapshots)
+    pub fn progress(&self, passenger_id: usize) -> Progress {
+        let Some(journey) = self.journeys.get(&passenger_id) else {
+            return Progress::NotStarted;
+        };
+        if journey.finished_at.is_some() || journey.gave_up() {
+            return Progress::Done;
+        }
+        let Some(record) = journey.legs.last() else {
+            return Progress::NotStarted;
+        };
+        if let Some(floor) = record.interrupted_at {
+            // Nach einem abgewiesenen Fahrbefehl endet die Fahrt, nach einem Ausfall geht sie weiter
+            let denied = self.denials.iter().any(|denial| {
+                denial.passenger_id == passenger_id && denial.at >= record.requested_at
+            });
+            return if denied {
+                Progress::Done
+            } else {
+                Progress::AtFloor(floor)
+            };
+        }
+        match (record.boarded_at, record.alighted_at, record.abandoned) {
+            (_, Some(_), _) => Progress::AtFloor(record.leg.to),
+            (_, None, Some((Abandonment::Stairs, _))) => Progress::Walking(record.leg),
+            (Some(_), None, _) => Progress::Riding(record.leg),
+            _ => Progress::Waiting {
+                leg: record.leg,
+                waited: self.clock().saturating_sub(record.requested_at),
+            },
+        }
+    }
+
+    fn current_leg(&mut self, passenger_id: usize) -> Option<&mut LegRecord> {
+        self.journeys
+            .get_mut(&passenger_id)
+            .and_then(|journey| journey.legs.last_mut())
+    }
+
+    pub fn print_report(&self) {
+        println!("===== Journey report =====");
+        let mut totals = Vec::new();
+        // Wartezeit und Fahrzeit je Teilstück-Index (1. Teilstück, 2. Teilstück, ...)
+        let mut per_leg: BTreeMap<usize, (Vec<Duration>, Vec<Duration>)> = BTreeMap::new();
+
+        for (passenger_id, journey) in &self.journeys {
+            match journey.finished_at {
+                Some(finished) => {
+                    let total = finished - journey.started_at;
+                    totals.push(total);
+                    println!(
+                        "Passenger {}: {} leg(s), total journey time {:.1}s",
+                        passenger_id,
+                        journey.planned_legs,
+                        total.as_secs_f64()
+                    );
+                }
+                None => println!(
+                    "Passenger {}: journey {} ({}/{} legs completed)",
+                    passenger_id,
+                    match self.outcome(*passenger_id) {
+                        Outcome::Abandoned => "abandoned",
+                        Outcome::Failed => "failed",
+                        _ => "unfinished",
+                    },
+                    journey
+                        .legs
+                        .iter()
+                        .filter(|l| l.alighted_at.is_some())
+                        .count(),
+                    journey.planned_legs
+                ),
+            }
+
+            for (index, record) in journey.legs.iter().enumerate() {
+                let elevator = record
+                    .elevator
+                    .as_ref()
+                    .map_or("-".to_string(), |(id, deck)| format!("{} ({})", id, deck));
+                let interrupted = record.interrupted_at.map_or(String::new(), |floor| {
+                    format!(", interrupted at floor {}", floor)
+                });
+                let interrupted = match record.abandoned {
+                    Some((Abandonment::Stairs, _)) => format!("{}, took the stairs", interrupted),
+                    Some((Abandonment::GaveUp, _)) => format!("{}, gave up", interrupted),
+                    None => interrupted,
+                };
+                println!(
+                    "    leg {}: floor {} -> {} by elevator {}, wait {}, ride {}{}",
+                    index + 1,
+                    record.leg.from,
+                    record.leg.to,
+                    elevator,
+                    format_duration(record.wait_time()),
+                    format_duration(record.ride_time()),
+                    interrupted
+                );
+                let entry = per_leg.entry(index).or_default();
+                if let Some(wait) = record.wait_time() {
+                    entry.0.push(wait);
+                }
+                if let Some(ride) = record.ride_time() {
+                    entry.1.push(ride);
+                }
+            }
+        }
+
+        println!(
+            "Completed journeys: {}/{}, average journey time {}",
+            totals.len(),
+            self.journeys.len(),
+            format_duration(average(&totals))
+        );
+        for (index, (waits, rides)) in &per_leg {
+            println!(
+                "Leg {}: average wait {}, average ride {}",
+                index + 1,
+                format_duration(average(waits)),
+                format_duration(average(rides))
+            );
+        }
+
+        self.print_accessibility_report();
+        self.print_abandonment_report();
+        self.print_group_report();
+        self.print_energy_report(totals.len());
+        self.print_fault_report();
+        self.print_access_report();
+        self.print_power_report();
+        self.print_invariant_report();
+    }
+
+    // Wartezeiten je Profil, um Benachteiligung von Rollstuhlfahrern usw. zu erkennen
+    fn print_accessibility_report(&self) {
+        println!("===== Accessibility report =====");
+        let mut per_profile: BTreeMap<PassengerProfile, (usize, usize, usize, Vec<Duration>)> =
+            BTreeMap::new();
+        for journey in self.journeys.values() {
+            let entry = per_profile.entry(journey.profile).or_default();
+            entry.0 += 1;
+            if journey.finished_at.is_some() {
+                entry.1 += 1;
+            }
+            entry.2 += journey.refusals;
+            entry
+                .3
+                .extend(journey.legs.iter().filter_map(LegRecord::wait_time));
+        }
+        for (profile, (passengers, completed, refusals, waits)) in &per_profile {
+            println!(
+                "{:?}: {} passenger(s), {} completed, average wait {}, refused boardings {}",
+                profile,
+                passengers,
+                completed,
+                format_duration(average(waits)),
+                refusals
+            );
+        }
+    }
+
+    // Abgebrochene Wartezeiten zählen mit, sonst sähe eine Zuteilung gut aus, die schwierige Rufe nie bedient
+    fn print_abandonment_report(&self) {
+        let legs: Vec<&LegRecord> = self
+            .journeys
+            .values()
+            .flat_map(|journey| journey.legs.iter())
+            .collect();
+        let abandoned: Vec<Duration> = legs
+            .iter()
+            .filter(|record| record.abandoned.is_some())
+            .filter_map(|record| record.wait_time_including_abandoned())
+            .collect();
+        let all_waits: Vec<Duration> = legs
+            .iter()
+            .filter_map(|record| record.wait_time_including_abandoned())
+            .collect();
+        println!("===== Abandonment report =====");
+        println!(
+            "Abandoned journeys: {}/{}, legs by stairs: {}",
+            self.journeys
+                .values()
+                .filter(|journey| journey.gave_up())
+                .count(),
+            self.journeys.len(),
+            legs.iter()
+                .filter(|record| matches!(record.abandoned, Some((Abandonment::Stairs, _))))
+                .count()
+        );
+        println!(
+            "Average wait before leaving the queue {}, average wait including abandoned legs {}",
+            format_duration(average(&abandoned)),
+            format_duration(average(&all_waits))
+        );
+    }
+
+    // Ob Gruppen gemeinsam gefahren sind und wie lange ihre Mitglieder gewartet haben
+    fn print_group_report(&self) {
+        if self.groups.is_empty() {
+            return;
+        }
+        println!("===== Group report =====");
+        for (group_id, group) in &self.groups {
+            let journeys: Vec<&JourneyRecord> = group
+                .members
+                .iter()
+                .filter_map(|id| self.journeys.get(id))
+                .collect();
+            // Erstes Teilstück: mit welchen Fahrstühlen die Gruppe losgefahren ist
+            let mut cars: Vec<usize> = journeys
+                .iter()
+                .filter_map(|journey| journey.legs.first()?.elevator.as_ref().map(|(id, _)| *id))
+                .collect();
+            cars.sort_unstable();
+            cars.dedup();
+            let waits: Vec<Duration> = journeys
+                .iter()
+                .filter_map(|journey| journey.legs.first()?.wait_time())
+                .collect();
+            println!(
+                "Group {}: {} member(s), {} completed, split {} time(s), first leg by elevator(s) {:?}, average wait {}",
+                group_id,
+                group.members.len(),
+                journeys.iter().filter(|journey| journey.finished_at.is_some()).count(),
+                group.splits,
+                cars,
+                format_duration(average(&waits))
+            );
+        }
+    }
+
+    fn print_fault_report(&self) {
+        if self.failures.is_empty() {
+            return;
+        }
+        println!("===== Fault report =====");
+        for failure in &self.failures {
+            println!(
+                "Elevator {}: {:?} at floor {} after {:.1}s, affected passengers {:?}",
+                failure.elevator_id,
+                failure.fault,
+                failure.floor,
+                failure.at.as_secs_f64(),
+                failure.passengers
+            );
+        }
+    }
+
+    fn print_access_report(&self) {
+        if self.denials.is_empty() {
+            return;
+        }
+        println!("===== Access report =====");
+        for denial in &self.denials {
+            println!(
+                "Passenger {}: floor {} denied in elevator {} at floor {} after {:.1}s, {}",
+                denial.passenger_id,
+                denial.target,
+                denial.elevator_id,
+                denial.floor,
+                denial.at.as_secs_f64(),
+                denial.reason
+            );
+        }
+    }
+
+    // Nachweis der Reihenfolge im Notstrombetrieb
+    fn print_power_report(&self) {
+        if self.power_events.is_empty() {
+            return;
+        }
+        println!("===== Emergency power sequence =====");
+        for (at, event) in &self.power_events {
+            println!("{:>6.1}s  {}", at.as_secs_f64(), event);
+        }
+    }
+
+    fn print_invariant_report(&self) {
+        if self.violations.is_empty() {
+            return;
+        }
+        println!("===== Invariant report =====");
+        for (at, violation) in &self.violations {
+            println!(
+                "{:>6.1}s  {}: {}",
+                at.as_secs_f64(),
+                violation.subject(),
+                violation
+            );
+        }
+    }
+
+    fn print_energy_report(&self, delivered: usize) {
+        println!("===== Energy report =====");
+        let elapsed = self.clock();
+        let mut total_kwh = 0.0;
+        for (elevator_id, record) in &self.energy {
+            let standby_kwh = record.model.standby_kwh(elapsed);
+            let net_kwh = record.consumed_kwh - record.regenerated_kwh + standby_kwh;
+            total_kwh += net_kwh;
+            println!(
+                "Elevator {}: {} trip(s) over {} floor(s), traction {:.4} kWh, regenerated {:.4} kWh, standby {:.4} kWh, net {:.4} kWh",
+                elevator_id,
+                record.trips,
+                record.floors_travelled,
+                record.consumed_kwh,
+                record.regenerated_kwh,
+                standby_kwh,
+                net_kwh
+            );
+        }
+        println!("Total energy: {:.4} kWh", total_kwh);
+        if delivered > 0 {
+            println!(
+                "Energy per delivered passenger: {:.4} kWh",
+                total_kwh / delivered as f64
+            );
+        } else {
+            println!("Energy per delivered passenger: - (no passenger delivered)");
+        }
+    }
+}
+
+fn average(durations: &[Duration]) -> Option<Duration> {
+    if durations.is_empty() {
+        None
+    } else {
+        Some(durations.iter().sum::<Duration>() / durations.len() as u32)
+    }
+}
+
+fn format_duration(duration: Option<Duration>) -> String {
+    duration.map_or("-".to_string(), |d| format!("{:.1}s", d.as_secs_f64()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(metrics: &mut Metrics, passenger_id: usize, leg: Leg) {
+        metrics.journey_started(passenger_id, &[leg], PassengerProfile::Standard);
+        metrics.leg_requested(passenger_id, leg);
+    }
+
+    #[test]
+    fn every_passenger_counts_in_exactly_one_bucket() {
+        let mut metrics = Metrics::new();
+        let leg = Leg { from: 0, to: 5 };
+        for passenger_id in 0..8 {
+            start(&mut metrics, passenger_id, leg);
+        }
+        // 0 angekommen, 1 aufgegeben, 2 wartet noch
+        metrics.leg_boarded(0, 0, "lower");
+        metrics.leg_completed(0);
+        metrics.leg_abandoned(1, Abandonment::GaveUp);
+        // 3 beim Ausfall ausgestiegen, 4 im abgeschotteten Fahrkorb, 5 abgewiesen, 6 abgeschottet
+        metrics.leg_boarded(3, 1, "lower");
+        metrics.leg_interrupted(3, 2);
+        metrics.elevator_failed(1, Fault::OutOfService, 2, &[3]);
+        metrics.leg_boarded(4, 2, "lower");
+        metrics.elevator_fenced(2);
+        metrics.leg_boarded(5, 0, "lower");
+        metrics.access_denied(5, 0, 0, 5, AccessDenied::NotAuthorized);
+        metrics.leg_interrupted(5, 0);
+        metrics.passenger_fenced(6);
+        // 7 fern vom Fahrkorb ausgestiegen
+        metrics.leg_boarded(7, 0, "lower");
+        metrics.invariant_violated(Violation::ExitedAway {
+            passenger: 7,
+            elevator: 0,
+            floor: 5,
+            car_floor: 3,
+        });
+
+        let summary = metrics.summary();
+        assert_eq!(summary.passengers, 8);
+        assert_eq!(summary.completed, 1);
+        assert_eq!(summary.abandoned, 1);
+        assert_eq!(summary.unfinished, 1);
+        assert_eq!(summary.failed, 5);
+        assert_eq!(
+            summary.completed + summary.abandoned + summary.unfinished + summary.failed,
+            summary.passengers
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::FloorId;
+
+// Betriebsarten eines einzelnen Fahrstuhls; außer im Automatikbetrieb bekommt er keine Rufe zugeteilt
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OperatingMode {
+    Automatic,
+    Independent, // Nur Fahrbefehle aus dem Fahrkorb, z. B. für Umzüge
+    Attendant,   // Ein Liftführer bedient den Fahrkorb und schließt die Tür selbst
+    Vip(u8),     // Ohne Halt zur Ebene x, danach wie `Independent`
+}
+
+impl OperatingMode {
+    // Nimmt der Fahrstuhl in dieser Ebene wartende Fahrgäste auf?
+    pub fn answers_hall(&self, floor: u8) -> bool {
+        match self {
+            OperatingMode::Automatic | OperatingMode::Attendant => true,
+            OperatingMode::Independent => false,
+            OperatingMode::Vip(vip_floor) => *vip_floor == floor,
+        }
+    }
+}
+
+// Bedienung durch den Liftführer
+#[derive(Debug, Clone, Copy)]
+pub enum AttendantInput {
+    CarCall(FloorId), // Zielebene, z. B. für einen angezeigten Etagenruf
+    Start,            // Tür schließen und losfahren
+}
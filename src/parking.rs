@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use crate::car::ElevatorSpec;
+use crate::config::hour_of_day;
+
+// Wohin ein Fahrstuhl fährt, wenn er keine Aufträge mehr hat
+#[derive(Debug, Clone)]
+pub enum ParkingPolicy {
+    Stay,            // Bleibt dort stehen, wo er zuletzt gehalten hat
+    Lobby,           // Zurück zur Lobby (bzw. zum untersten Halt seiner Zone)
+    Floor(u8),       // Feste Parkebene, z. B. obere Etagen am Abend
+    SpreadEvenly,    // Freie Fahrstühle gleichmäßig über das Gebäude verteilen
+    PredictedDemand, // Ebenen mit den meisten Rufen in letzter Zeit
+    TimeOfDay(Vec<TimeOfDayRule>, Box<ParkingPolicy>), // Regeln nach Uhrzeit, sonst Standard
+}
+
+// Gilt von `from_hour` (einschließlich) bis `to_hour` (ausschließlich)
+#[derive(Debug, Clone)]
+pub struct TimeOfDayRule {
+    pub from_hour: u8,
+    pub to_hour: u8,
+    pub policy: ParkingPolicy,
+}
+
+pub struct ParkingPlanner {
+    policy: ParkingPolicy,
+    demand: Vec<f64>, // Gewichtete Anzahl Rufe je Ebene, ältere Rufe zählen weniger
+    start_hour: u8,   // Uhrzeit beim Start der Simulation
+}
+
+impl ParkingPlanner {
+    pub fn new(policy: ParkingPolicy, floors: usize, start_hour: u8) -> Self {
+        Self {
+            policy,
+            demand: vec![0.0; floors],
+            start_hour,
+        }
+    }
+
+    pub fn record_call(&mut self, floor: u8) {
+        for demand in self.demand.iter_mut() {
+            *demand *= 0.9;
+        }
+        if let Some(demand) = self.demand.get_mut(floor as usize) {
+            *demand += 1.0;
+        }
+    }
+
+    // Parkposition für `spec`; `other_idle` sind die Parkpositionen der übrigen freien Fahrstühle,
+    // `elapsed` die Zeit der Simulation seit ihrem Start. `None` heißt: stehen bleiben.
+    pub fn parking_floor(
+        &self,
+        spec: &ElevatorSpec,
+        current_floor: u8,
+        other_idle: &[u8],
+        elapsed: Duration,
+    ) -> Option<u8> {
+        self.resolve(&self.policy, spec, current_floor, other_idle, elapsed)
+    }
+
+    fn resolve(
+        &self,
+        policy: &ParkingPolicy,
+        spec: &ElevatorSpec,
+        current_floor: u8,
+        other_idle: &[u8],
+        elapsed: Duration,
+    ) -> Option<u8> {
+        let stops = spec.stops();
+        let floor = match policy {
+            ParkingPolicy::Stay => return None,
+            ParkingPolicy::Lobby => *stops.first()?,
+            ParkingPolicy::Floor(floor) => nearest_stop(&stops, *floor)?,
+            ParkingPolicy::SpreadEvenly => {
+                let (&lowest, &highest) = (stops.first()?, stops.last()?);
+                let cars = other_idle.len() + 1;
+                // Mittelpunkte gleich großer Abschnitte; der Abschnitt, der am weitesten
+                // von den anderen freien Fahrstühlen entfernt ist, gewinnt
+                (0..cars)
+                    .map(|i| {
+                        let span = (highest - lowest) as usize;
+                        lowest + ((2 * i + 1) * span / (2 * cars)) as u8
+                    })
+                    .filter_map(|floor| nearest_stop(&stops, floor))
+                    .max_by_key(|&floor| {
+                        other_idle
+                            .iter()
+                            .map(|&other| (other as i32 - floor as i32).abs())
+                            .min()
+                            .unwrap_or(i32::MAX)
+                    })?
+            }
+            ParkingPolicy::PredictedDemand => stops
+                .iter()
+                .copied()
+                .filter(|floor| !other_idle.contains(floor))
+                .max_by(|&a, &b| {
+                    let demand =
+                        |floor: u8| self.demand.get(floor as usize).copied().unwrap_or(0.0);
+                    demand(a).total_cmp(&demand(b))
+                })
+                .filter(|&floor| self.demand.get(floor as usize).is_some_and(|&d| d > 0.0))?,
+            ParkingPolicy::TimeOfDay(rules, default) => {
+                let hour = hour_of_day(self.start_hour, elapsed);
+                let policy = rules
+                    .iter()
+                    .find(|rule| rule.from_hour <= hour && hour < rule.to_hour)
+                    .map_or(default.as_ref(), |rule| &rule.policy);
+                return self.resolve(policy, spec, current_floor, other_idle, elapsed);
+            }
+        };
+        (floor != current_floor).then_some(floor)
+    }
+}
+
+fn nearest_stop(stops: &[u8], floor: u8) -> Option<u8> {
+    stops
+        .iter()
+        .copied()
+        .min_by_key(|&stop| (stop as i32 - floor as i32).abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn time_of_day_follows_the_simulation_clock() {
+        let evening = TimeOfDayRule {
+            from_hour: 18,
+            to_hour: 24,
+            policy: ParkingPolicy::Floor(9),
+        };
+        let policy = ParkingPolicy::TimeOfDay(vec![evening], Box::new(ParkingPolicy::Lobby));
+        let planner = ParkingPlanner::new(policy, 10, 17);
+        let spec = ElevatorSpec::single_deck((0..10).collect());
+        assert_eq!(
+            planner.parking_floor(&spec, 5, &[], Duration::ZERO),
+            Some(0)
+        );
+        assert_eq!(
+            planner.parking_floor(&spec, 5, &[], HOUR - Duration::from_secs(1)),
+            Some(0)
+        );
+        assert_eq!(planner.parking_floor(&spec, 5, &[], HOUR), Some(9));
+        // Nach Mitternacht gilt wieder die Standardregel
+        assert_eq!(planner.parking_floor(&spec, 5, &[], 7 * HOUR), Some(0));
+    }
+
+    #[test]
+    fn policies_pick_a_stop_of_the_zone() {
+        let spec = ElevatorSpec::single_deck(vec![0, 10, 11, 12, 13, 14]);
+        let planner = |policy| ParkingPlanner::new(policy, 15, 8);
+        assert_eq!(
+            planner(ParkingPolicy::Stay).parking_floor(&spec, 12, &[], Duration::ZERO),
+            None
+        );
+        assert_eq!(
+            planner(ParkingPolicy::Lobby).parking_floor(&spec, 12, &[], Duration::ZERO),
+            Some(0)
+        );
+        assert_eq!(
+            planner(ParkingPolicy::Lobby).parking_floor(&spec, 0, &[], Duration::ZERO),
+            None
+        );
+        assert_eq!(
+            planner(ParkingPolicy::Floor(6)).parking_floor(&spec, 0, &[], Duration::ZERO),
+            Some(10)
+        );
+        // Ein zweiter freier Wagen steht unten, der Abschnitt oben ist weiter entfernt
+        assert_eq!(
+            planner(ParkingPolicy::SpreadEvenly).parking_floor(&spec, 12, &[0], Duration::ZERO),
+            Some(10)
+        );
+    }
+
+    #[test]
+    fn predicted_demand_prefers_recent_calls() {
+        let spec = ElevatorSpec::single_deck((0..5).collect());
+        let mut planner = ParkingPlanner::new(ParkingPolicy::PredictedDemand, 5, 8);
+        assert_eq!(planner.parking_floor(&spec, 0, &[], Duration::ZERO), None);
+        planner.record_call(3);
+        planner.record_call(3);
+        planner.record_call(1);
+        assert_eq!(
+            planner.parking_floor(&spec, 0, &[], Duration::ZERO),
+            Some(3)
+        );
+        // Die Ebene eines anderen freien Wagens scheidet aus
+        assert_eq!(
+            planner.parking_floor(&spec, 0, &[3], Duration::ZERO),
+            Some(1)
+        );
+    }
+}
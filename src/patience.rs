@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::journey::Leg;
+use crate::profile::PassengerProfile;
+
+// Zeit, die ein Fahrgast für eine Ebene im Treppenhaus braucht
+pub const STAIRS_TIME_PER_FLOOR: Duration = Duration::from_secs(4);
+
+// Wie lange ein Fahrgast auf einen Fahrstuhl wartet, bevor er die Treppe nimmt oder die Fahrt abbricht
+#[derive(Debug, Clone, Copy)]
+pub struct Patience {
+    pub stairs_floors: u8, // Teilstücke bis zu so vielen Ebenen geht man notfalls zu Fuß, 0 = nie
+    pub stairs_after: Duration, // Wartezeit, nach der man die Treppe nimmt
+    pub give_up_after: Option<Duration>, // Wartezeit, nach der man aufgibt; `None` wartet beliebig lange
+}
+
+// Was ein Fahrgast tut, wenn seine Geduld erschöpft ist
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Abandonment {
+    Stairs,
+    GaveUp,
+}
+
+impl Patience {
+    // Nächste Grenze der Wartezeit für `leg` und was danach passiert; `None` heißt: weiter warten
+    pub fn limit(&self, leg: Leg, profile: PassengerProfile) -> Option<(Duration, Abandonment)> {
+        let floors = (leg.to as i32 - leg.from as i32).unsigned_abs();
+        let stairs = (profile.can_take_stairs() && floors <= self.stairs_floors as u32)
+            .then_some((self.stairs_after, Abandonment::Stairs));
+        let give_up = self.give_up_after.map(|after| (after, Abandonment::GaveUp));
+        match (stairs, give_up) {
+            (Some(stairs), Some(give_up)) => Some(if stairs.0 <= give_up.0 {
+                stairs
+            } else {
+                give_up
+            }),
+            (stairs, give_up) => stairs.or(give_up),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+
+use crate::car::ElevatorSpec;
+
+// Notstrombetrieb: das Aggregat versorgt nur `budget` Fahrstühle gleichzeitig. Zuerst werden die
+// Fahrstühle nacheinander zur Evakuierungsebene gebracht, danach bleiben nur ausgewählte in Betrieb.
+#[derive(Clone)]
+pub struct EmergencyPower {
+    pub budget: usize,
+    waiting: VecDeque<usize>, // Fahrstühle, die noch evakuiert werden müssen
+    evacuating: Vec<usize>,   // Gerade auf dem Weg zur Evakuierungsebene
+    pub selected: Option<Vec<usize>>, // Nach der Evakuierung in Betrieb; `None`, solange evakuiert wird
+}
+
+impl EmergencyPower {
+    pub fn new(budget: usize, cars: impl IntoIterator<Item = usize>) -> Self {
+        Self {
+            budget,
+            waiting: cars.into_iter().collect(),
+            evacuating: Vec::new(),
+            selected: None,
+        }
+    }
+
+    // Nächster Fahrstuhl, der im Rahmen des Budgets zur Evakuierungsebene fahren darf
+    pub fn next_to_evacuate(&mut self) -> Option<usize> {
+        if self.evacuating.len() >= self.budget {
+            return None;
+        }
+        let id = self.waiting.pop_front()?;
+        self.evacuating.push(id);
+        Some(id)
+    }
+
+    pub fn is_evacuating(&self, id: usize) -> bool {
+        self.evacuating.contains(&id)
+    }
+
+    // Fahrstuhl ist ausgeladen oder ausgefallen und gibt seinen Anteil am Budget frei
+    pub fn release(&mut self, id: usize) -> bool {
+        self.waiting.retain(|&waiting| waiting != id);
+        let was_evacuating = self.is_evacuating(id);
+        self.evacuating.retain(|&evacuating| evacuating != id);
+        was_evacuating
+    }
+
+    pub fn evacuation_done(&self) -> bool {
+        self.waiting.is_empty() && self.evacuating.is_empty()
+    }
+
+    // Solange evakuiert wird, nimmt die Steuerung keine Rufe an
+    pub fn in_evacuation(&self) -> bool {
+        self.selected.is_none()
+    }
+}
+
+// Ergänzt `selected` um Fahrstühle aus `candidates`, bis `budget` erreicht ist. Gewählt wird, wer die
+// meisten noch nicht bedienten Ebenen abdeckt; bei Gleichstand gewinnt die kleinere ID.
+pub fn select_cars(
+    specs: &[ElevatorSpec],
+    selected: &mut Vec<usize>,
+    candidates: &[usize],
+    budget: usize,
+) {
+    let mut covered: Vec<u8> = selected
+        .iter()
+        .flat_map(|&id| specs[id].served_floors.iter().copied())
+        .collect();
+    while selected.len() < budget {
+        let Some(best) = candidates
+            .iter()
+            .copied()
+            .filter(|id| !selected.contains(id))
+            .max_by_key(|&id| {
+                let new_floors = specs[id]
+                    .served_floors
+                    .iter()
+                    .filter(|floor| !covered.contains(floor))
+                    .count();
+                (new_floors, Reverse(id))
+            })
+        else {
+            break;
+        };
+        covered.extend(specs[best].served_floors.iter().copied());
+        selected.push(best);
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+// Platz in einem Deck in Einheiten; eine Person ohne Gepäck braucht 2 Einheiten
+pub const DECK_CAPACITY: u32 = 4;
+
+// So lange bleibt die Tür nach dem Öffnen offen; die barrierefreie Ruftaste verlängert die Zeit
+pub const DOOR_DWELL: Duration = Duration::from_secs(10);
+pub const ACCESSIBLE_DOOR_DWELL: Duration = Duration::from_secs(20);
+
+// Fahrgastprofil: bestimmt Platzbedarf, Zeit zum Ein- und Aussteigen und die nötige Offenhaltezeit.
+// Lieferroboter, Transportwagen und Krankenhausbetten fahren als Lasten ohne Fahrgast.
+// In Konfiguration und Snapshots unter denselben Namen wie im Fahrtenprotokoll, z. B. "group:3"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum PassengerProfile {
+    Standard,
+    Wheelchair,
+    Luggage,
+    Stroller,
+    Group(u8),   // Personen, die gemeinsam ein- und aussteigen
+    Robot,       // Lieferroboter, passt in jeden Fahrstuhl
+    FreightCart, // Transportwagen, nur im Lastenaufzug
+    HospitalBed, // Bett, nur im Lastenaufzug und allein im Fahrkorb
+}
+
+impl PassengerProfile {
+    pub fn space(&self) -> u32 {
+        match self {
+            PassengerProfile::Standard => 2,
+            PassengerProfile::Luggage | PassengerProfile::Stroller | PassengerProfile::Robot => 3,
+            PassengerProfile::Wheelchair
+            | PassengerProfile::FreightCart
+            | PassengerProfile::HospitalBed => 4,
+            PassengerProfile::Group(persons) => 2 * *persons as u32,
+        }
+    }
+
+    // Passt in ein Deck, in dem schon `load` Einheiten belegt sind? Ein leeres Deck nimmt jeden auf
+    pub fn fits(&self, load: u32) -> bool {
+        load == 0 || load + self.space() <= DECK_CAPACITY
+    }
+
+    pub fn transfer_time(&self) -> Duration {
+        match self {
+            PassengerProfile::Standard => Duration::ZERO,
+            PassengerProfile::Luggage | PassengerProfile::Robot => Duration::from_secs(1),
+            PassengerProfile::Stroller | PassengerProfile::FreightCart => Duration::from_secs(2),
+            PassengerProfile::Wheelchair => Duration::from_secs(3),
+            PassengerProfile::HospitalBed => Duration::from_secs(4),
+            PassengerProfile::Group(persons) => Duration::from_millis(500) * *persons as u32,
+        }
+    }
+
+    // Die Tür bleibt nach dem Ein- oder Aussteigen mindestens so lange offen
+    pub fn dwell(&self) -> Duration {
+        match self {
+            PassengerProfile::Standard => Duration::from_secs(2),
+            PassengerProfile::Robot => Duration::from_secs(3),
+            PassengerProfile::Luggage => Duration::from_secs(4),
+            PassengerProfile::Stroller => Duration::from_secs(5),
+            PassengerProfile::FreightCart => Duration::from_secs(6),
+            PassengerProfile::Wheelchair => Duration::from_secs(8),
+            PassengerProfile::HospitalBed => Duration::from_secs(10),
+            PassengerProfile::Group(persons) => Duration::from_secs(2 + *persons as u64),
+        }
+    }
+
+    // Ruft mit der barrierefreien Taste, die eine längere Türzeit anfordert
+    pub fn uses_accessible_button(&self) -> bool {
+        matches!(
+            self,
+            PassengerProfile::Wheelchair
+                | PassengerProfile::Stroller
+                | PassengerProfile::HospitalBed
+        )
+    }
+
+    // Rollstühle, Kinderwagen und Lasten sind auf den Fahrstuhl angewiesen
+    pub fn can_take_stairs(&self) -> bool {
+        matches!(
+            self,
+            PassengerProfile::Standard | PassengerProfile::Luggage | PassengerProfile::Group(_)
+        )
+    }
+
+    // Last ohne Fahrgast, siehe `FreightPolicy`
+    pub fn is_freight(&self) -> bool {
+        matches!(
+            self,
+            PassengerProfile::Robot | PassengerProfile::FreightCart | PassengerProfile::HospitalBed
+        )
+    }
+
+    pub fn needs_freight_car(&self) -> bool {
+        matches!(
+            self,
+            PassengerProfile::FreightCart | PassengerProfile::HospitalBed
+        )
+    }
+
+    // Belegt den ganzen Fahrkorb, auch bei Doppeldeckern
+    pub fn exclusive(&self) -> bool {
+        matches!(self, PassengerProfile::HospitalBed)
+    }
+}
+
+// Namen wie in Konfiguration und Fahrtenprotokoll, z. B. "wheelchair" oder "group:3"
+impl fmt::Display for PassengerProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PassengerProfile::Standard => write!(f, "standard"),
+            PassengerProfile::Wheelchair => write!(f, "wheelchair"),
+            PassengerProfile::Luggage => write!(f, "luggage"),
+            PassengerProfile::Stroller => write!(f, "stroller"),
+            PassengerProfile::Group(persons) => write!(f, "group:{}", persons),
+            PassengerProfile::Robot => write!(f, "robot"),
+            PassengerProfile::FreightCart => write!(f, "freight-cart"),
+            PassengerProfile::HospitalBed => write!(f, "hospital-bed"),
+        }
+    }
+}
+
+impl FromStr for PassengerProfile {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "standard" => Ok(PassengerProfile::Standard),
+            "wheelchair" => Ok(PassengerProfile::Wheelchair),
+            "luggage" => Ok(PassengerProfile::Luggage),
+            "stroller" => Ok(PassengerProfile::Stroller),
+            "robot" => Ok(PassengerProfile::Robot),
+            "freight-cart" => Ok(PassengerProfile::FreightCart),
+            "hospital-bed" => Ok(PassengerProfile::HospitalBed),
+            _ => match name.strip_prefix("group:").map(str::parse) {
+                Some(Ok(persons)) if persons > 0 => Ok(PassengerProfile::Group(persons)),
+                _ => Err(format!("unknown passenger profile '{}'", name)),
+            },
+        }
+    }
+}
+
+impl From<PassengerProfile> for String {
+    fn from(profile: PassengerProfile) -> Self {
+        profile.to_string()
+    }
+}
+
+impl TryFrom<String> for PassengerProfile {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
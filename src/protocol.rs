@@ -0,0 +1,456 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crossbeam_channel::{unbounded, Sender};
+use serde::{Deserialize, Serialize};
+
+use crate::access::AccessDenied;
+use crate::clock::{self, Instant};
+use crate::faults::Fault;
+use crate::fire::{DoorButton, FirefighterInput};
+use crate::modes::{AttendantInput, OperatingMode};
+use crate::profile::PassengerProfile;
+use crate::supervisor::ActorId;
+use crate::Direction;
+
+// Nachrichten zwischen Steuerung, Fahrstühlen, Ebenen und Passagieren. Befehle reisen in einem `Request`
+// mit Version und Korrelations-ID und werden mit `Reply` bestätigt oder abgelehnt; Meldungen und
+// Benachrichtigungen (`ElevatorStatus`, `ElevatorToPassenger`, `ElevatorArrived`) werden nicht bestätigt.
+// Bei jeder inkompatiblen Änderung der Nachrichten wird die Version erhöht.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// Ohne Antwort innerhalb dieser Zeit gilt ein Befehl als verloren; ein Fahrstuhl bestätigt auch während
+// Türzeit und Fahrt, nur ein blockierter Schacht hält ihn bis zu 3 Sekunden auf
+pub const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ElevatorId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FloorId(pub u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PassengerId(pub usize);
+
+impl fmt::Display for ElevatorId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for FloorId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for PassengerId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Eindeutig über alle Absender einer Simulation und aufsteigend in der Reihenfolge des Sendens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CorrelationId(u64);
+
+impl CorrelationId {
+    fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl fmt::Display for CorrelationId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+// Befehl an einen Akteur; die Antwort geht an `reply_to` des Absenders
+pub struct Request<T> {
+    pub version: u32,
+    pub correlation: CorrelationId,
+    pub body: T,
+    reply_to: Sender<Reply>,
+}
+
+impl<T> Request<T> {
+    pub fn new(body: T, reply_to: &Sender<Reply>) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            correlation: CorrelationId::next(),
+            body,
+            reply_to: reply_to.clone(),
+        }
+    }
+
+    // Weiterleiten mit anderem Inhalt, z. B. von der Ebene an die Steuerung; die Antwort geht weiterhin
+    // an den ursprünglichen Absender
+    pub fn forward<U>(self, body: U) -> Request<U> {
+        Request {
+            version: self.version,
+            correlation: self.correlation,
+            body,
+            reply_to: self.reply_to,
+        }
+    }
+
+    pub fn open(self) -> (T, Responder) {
+        let responder = Responder {
+            correlation: self.correlation,
+            reply_to: self.reply_to,
+        };
+        (self.body, responder)
+    }
+}
+
+// Beantwortet genau einen `Request`; ist der Absender nicht mehr da, geht die Antwort verloren
+pub struct Responder {
+    correlation: CorrelationId,
+    reply_to: Sender<Reply>,
+}
+
+impl Responder {
+    pub fn ack(self) {
+        self.reply(Outcome::Ack);
+    }
+
+    pub fn nack(self, reason: impl Into<String>) {
+        self.reply(Outcome::Nack(reason.into()));
+    }
+
+    fn reply(self, outcome: Outcome) {
+        let _ = self.reply_to.send(Reply {
+            correlation: self.correlation,
+            outcome,
+        });
+    }
+}
+
+#[derive(Debug)]
+pub struct Reply {
+    pub correlation: CorrelationId,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug)]
+pub enum Outcome {
+    Ack,
+    Nack(String), // Grund der Ablehnung
+}
+
+// Für Absender ohne eigene Ereignisschleife wie Konsole und Skripte: Befehl senden und auf die Antwort warten
+pub fn send_and_confirm<T>(
+    sender: &Sender<Request<T>>,
+    body: T,
+    recipient: &str,
+) -> Result<(), String> {
+    let (reply_tx, reply_rx) = unbounded();
+    sender
+        .send(Request::new(body, &reply_tx))
+        .map_err(|_| format!("{} is not reachable", recipient))?;
+    match clock::recv_timeout(&reply_rx, REPLY_TIMEOUT) {
+        Ok(Reply {
+            outcome: Outcome::Ack,
+            ..
+        }) => Ok(()),
+        Ok(Reply {
+            outcome: Outcome::Nack(reason),
+            ..
+        }) => Err(format!("{} rejected the command: {}", recipient, reason)),
+        Err(_) => Err(format!(
+            "no reply from {} within {}s, command lost",
+            recipient,
+            REPLY_TIMEOUT.as_secs()
+        )),
+    }
+}
+
+// So viele Korrelations-IDs merken sich `Inbox` und `Outstanding`; was davor liegt, gilt als erledigt
+const WINDOW: usize = 1024;
+
+// Die letzten `WINDOW` IDs und die höchste, die dafür vergessen wurde. Ein Befehl, den mehr als
+// `WINDOW` neuere überholt haben, gilt damit als schon gesehen; der Absender erhält eine Ablehnung
+// und sendet ihn neu, statt dass der Speicher mit der Laufzeit wächst.
+#[derive(Default)]
+struct Window {
+    recent: VecDeque<CorrelationId>,
+    members: HashSet<CorrelationId>,
+    forgotten: Option<CorrelationId>,
+}
+
+impl Window {
+    fn contains(&self, correlation: CorrelationId) -> bool {
+        self.members.contains(&correlation)
+            || self
+                .forgotten
+                .is_some_and(|forgotten| correlation <= forgotten)
+    }
+
+    // `false`, wenn die ID schon im Fenster liegt oder älter ist
+    fn insert(&mut self, correlation: CorrelationId) -> bool {
+        if self.contains(correlation) {
+            return false;
+        }
+        self.recent.push_back(correlation);
+        self.members.insert(correlation);
+        if self.recent.len() > WINDOW {
+            let oldest = self.recent.pop_front().unwrap();
+            self.members.remove(&oldest);
+            self.forgotten = self.forgotten.max(Some(oldest));
+        }
+        true
+    }
+}
+
+// Empfängerseite: lehnt Befehle einer anderen Protokollversion und doppelt zugestellte Befehle ab
+#[derive(Default)]
+pub struct Inbox {
+    seen: Window,
+}
+
+impl Inbox {
+    pub fn admit<T>(&mut self, request: Request<T>) -> Option<(T, Responder)> {
+        let version = request.version;
+        let (body, responder) = request.open();
+        if version != PROTOCOL_VERSION {
+            responder.nack(format!(
+                "protocol version {} is not supported, expected {}",
+                version, PROTOCOL_VERSION
+            ));
+            return None;
+        }
+        if !self.seen.insert(responder.correlation) {
+            let correlation = responder.correlation;
+            responder.nack(format!("duplicate command {}", correlation));
+            return None;
+        }
+        Some((body, responder))
+    }
+}
+
+// Absenderseite: offene Befehle, um verlorene Befehle und doppelte Antworten zu erkennen
+#[derive(Default)]
+pub struct Outstanding {
+    open: BTreeMap<CorrelationId, (ActorId, String, Instant)>, // Empfänger, Befehl, gesendet
+    closed: Window, // Beantwortet oder als verloren gemeldet
+}
+
+impl Outstanding {
+    pub fn sent<T>(&mut self, request: &Request<T>, to: ActorId, command: String) {
+        self.open
+            .insert(request.correlation, (to, command, Instant::now()));
+    }
+
+    // Empfänger und Befehl zu einer Antwort; `Err` für eine Antwort, die nicht erwartet wurde
+    pub fn answered(&mut self, reply: &Reply) -> Result<(ActorId, String), String> {
+        match self.open.remove(&reply.correlation) {
+            Some((to, command, _)) => {
+                self.closed.insert(reply.correlation);
+                Ok((to, command))
+            }
+            None if self.closed.contains(reply.correlation) => {
+                Err(format!("duplicate or late reply to {}", reply.correlation))
+            }
+            None => Err(format!("reply to unknown command {}", reply.correlation)),
+        }
+    }
+
+    // Befehle ohne Antwort nach `REPLY_TIMEOUT`; sie werden nur einmal gemeldet
+    pub fn overdue(&mut self) -> Vec<(ActorId, String)> {
+        let expired: Vec<CorrelationId> = self
+            .open
+            .iter()
+            .filter(|(_, (_, _, sent))| sent.elapsed() >= REPLY_TIMEOUT)
+            .map(|(&correlation, _)| correlation)
+            .collect();
+        expired
+            .into_iter()
+            .map(|correlation| {
+                self.closed.insert(correlation);
+                let (to, command, _) = self.open.remove(&correlation).unwrap();
+                (to, format!("{} {}", command, correlation))
+            })
+            .collect()
+    }
+
+    // Ein ausgefallener Empfänger antwortet nicht mehr; seine offenen Befehle sind kein Verlust
+    pub fn forget(&mut self, to: ActorId) {
+        self.open.retain(|_, (recipient, _, _)| *recipient != to);
+    }
+}
+
+#[derive(Debug)]
+pub enum ElevatorCommand {
+    MoveTo(FloorId),    // Bewege zu Ebene x
+    OpenDoor(Duration), // Tür öffnen und mindestens so lange offen halten
+    CloseDoor,
+    InjectFault(Fault),                    // Fehler für Resilienztests einspielen
+    Recall(FloorId), // Brandfall Phase I: ohne Halt zur Rückrufebene, Tür öffnen und parken
+    FirefighterMove(FloorId), // Phase II: Fahrt ohne Zwischenhalt, die Tür bleibt geschlossen
+    FirefighterDoor(DoorButton, Duration), // Phase II: Türtaster wird so lange gedrückt gehalten
+    Power(bool),     // Notstrom: ohne Versorgung fährt der Fahrstuhl nicht
+    SetMode(OperatingMode),
+}
+
+// `destination` entspricht der Ruftaste einer Fahrstuhlgruppe (z. B. Shuttle oder Lokalzone an der Sky-Lobby),
+// `load` dem Profil des Rufenden, damit Betten und Transportwagen einen passenden Fahrstuhl bekommen
+#[derive(Debug)]
+pub enum ControlCommand {
+    Request {
+        floor: FloorId,
+        direction: Direction,
+        destination: Option<FloorId>,
+        accessible: bool,
+        load: PassengerProfile,
+    },
+    FireAlarm(FloorId), // Brandmelder in Ebene x ausgelöst
+    FireAlarmReset,
+    Firefighter(FirefighterInput),
+    EmergencyPower(usize), // Umschalten auf Notstrom, der für x Fahrstühle gleichzeitig reicht
+    NormalPower,
+    SetMode(ElevatorId, OperatingMode), // Schlüsselschalter am Fahrstuhl
+    Attendant(ElevatorId, AttendantInput),
+}
+
+// Ruftaste in einer Ebene; die Ebene leitet den Ruf mit ihrer eigenen Nummer an die Steuerung weiter
+pub enum FloorCommand {
+    // `accessible`: barrierefreie Ruftaste
+    Request {
+        direction: Direction,
+        destination: Option<FloorId>,
+        accessible: bool,
+        load: PassengerProfile,
+    },
+}
+
+#[derive(Clone, Copy)]
+pub enum ElevatorArrived {
+    Elevator { elevator: ElevatorId, deck: u8 }, // Deck, das vor dieser Ebene hält
+}
+
+#[derive(Debug)]
+pub enum PassengerToElevator {
+    Enter {
+        boarders: Vec<(PassengerId, PassengerProfile)>, // Eine ganze Gruppe steigt gemeinsam ein
+        deck: u8,
+        floor: FloorId, // Wo die Passagiere warten; eine verspätete Anfrage gilt nicht in einer anderen Ebene
+    },
+    PressedButton {
+        passenger: PassengerId,
+        deck: u8,
+        floor: FloorId,
+        badges: Vec<u32>, // Vorgehaltene Ausweise
+    },
+    Exit(PassengerId),
+}
+
+pub enum ElevatorToPassenger {
+    YouEntered { elevator: ElevatorId, deck: u8 },
+    NoRoom, // Kein Platz mehr oder unverträgliche Last, auf den nächsten Fahrkorb warten
+    YouCanExit(FloorId),
+    YouCanChooseFloor,
+    OutOfService(FloorId), // Fahrstuhl fällt aus, Passagier steigt in dieser Ebene aus
+    Evacuate(FloorId),     // Brandfall oder Notstrom: alle steigen in dieser Ebene aus
+    CallRejected(FloorId, AccessDenied), // Zielebene gesichert, der Passagier verlässt den Fahrkorb
+}
+
+pub enum ElevatorStatus {
+    DoorOpened(ElevatorId, FloorId),
+    DoorClosed(ElevatorId, FloorId),
+    ArrivedAtFloor(ElevatorId, FloorId),
+    PassengerCount(ElevatorId, usize, Vec<u32>), // Anzahl Passagiere im Fahrkorb und belegter Platz je Deck
+    PassengerTarget(ElevatorId, Vec<FloorId>),
+    ElevatorReadyToCloseTheDoor(ElevatorId),
+    ElevatorIdle(ElevatorId, FloorId),
+    ShaftBlocked(ElevatorId, ElevatorId, FloorId), // Blockierender Fahrstuhl im selben Schacht, Ziel
+    FaultReported(ElevatorId, Fault, Vec<PassengerId>), // Fehler, betroffene Passagiere
+    Recalled(ElevatorId, FloorId), // Brandfall: steht mit offener Tür in der Rückrufebene
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(correlation: CorrelationId) -> Reply {
+        Reply {
+            correlation,
+            outcome: Outcome::Ack,
+        }
+    }
+
+    #[test]
+    fn inbox_admits_each_command_once() {
+        let (reply_tx, reply_rx) = unbounded();
+        let mut inbox = Inbox::default();
+        let request = Request::new(1, &reply_tx);
+        let duplicate = Request {
+            version: PROTOCOL_VERSION,
+            correlation: request.correlation,
+            body: 1,
+            reply_to: reply_tx.clone(),
+        };
+        let (body, responder) = inbox.admit(request).unwrap();
+        assert_eq!(body, 1);
+        responder.ack();
+        assert!(inbox.admit(duplicate).is_none());
+        assert!(matches!(reply_rx.try_recv().unwrap().outcome, Outcome::Ack));
+        assert!(matches!(
+            reply_rx.try_recv().unwrap().outcome,
+            Outcome::Nack(_)
+        ));
+    }
+
+    #[test]
+    fn inbox_rejects_other_versions() {
+        let (reply_tx, reply_rx) = unbounded();
+        let mut request = Request::new((), &reply_tx);
+        request.version = PROTOCOL_VERSION + 1;
+        assert!(Inbox::default().admit(request).is_none());
+        assert!(matches!(
+            reply_rx.try_recv().unwrap().outcome,
+            Outcome::Nack(_)
+        ));
+    }
+
+    #[test]
+    fn window_stays_bounded() {
+        let mut window = Window::default();
+        let ids: Vec<CorrelationId> = (0..WINDOW + 10).map(|_| CorrelationId::next()).collect();
+        for &id in &ids {
+            assert!(window.insert(id));
+        }
+        assert_eq!(window.recent.len(), WINDOW);
+        assert_eq!(window.members.len(), WINDOW);
+        // Vergessene IDs gelten weiter als gesehen
+        assert!(!window.insert(ids[0]));
+        assert!(!window.insert(ids[WINDOW + 9]));
+        assert!(window.insert(CorrelationId::next()));
+    }
+
+    #[test]
+    fn outstanding_matches_replies() {
+        let (reply_tx, _reply_rx) = unbounded();
+        let mut outstanding = Outstanding::default();
+        let request = Request::new((), &reply_tx);
+        outstanding.sent(&request, ActorId::ControlSystem, "call".to_string());
+        assert_eq!(
+            outstanding.answered(&reply(request.correlation)),
+            Ok((ActorId::ControlSystem, "call".to_string()))
+        );
+        assert!(outstanding.answered(&reply(request.correlation)).is_err());
+        assert!(outstanding.answered(&reply(CorrelationId::next())).is_err());
+
+        let forgotten = Request::new((), &reply_tx);
+        outstanding.sent(&forgotten, ActorId::Elevator(1), "enter".to_string());
+        outstanding.forget(ActorId::Elevator(1));
+        assert!(outstanding.overdue().is_empty());
+        assert!(outstanding.open.is_empty());
+    }
+}
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+
+use crate::clock::{self, Instant};
+use crate::protocol::{self, ControlCommand, Request};
+
+// Spielt Eingaben an die Steuerung zeitgesteuert ein, z. B. eine Brandschutzübung oder einen Stromausfall.
+// Abgelehnte Eingaben werden nur gemeldet, das Skript läuft weiter.
+pub fn spawn_script(
+    mut script: Vec<(Duration, ControlCommand)>,
+    control_tx: Sender<Request<ControlCommand>>,
+) {
+    script.sort_by_key(|(after, _)| *after);
+    clock::spawn("Scenario".to_string(), move || {
+        let start = Instant::now();
+        for (after, command) in script {
+            clock::sleep(after.saturating_sub(start.elapsed()));
+            println!("Scenario: {:?}", command);
+            if let Err(problem) = protocol::send_and_confirm(&control_tx, command, "Control system")
+            {
+                println!("Scenario: {}", problem);
+            }
+        }
+    });
+}
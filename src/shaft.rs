@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::clock::{self, Instant};
+
+// Gemeinsamer Schacht für mehrere Fahrstühle (z. B. TWIN): jeder Wagen belegt einen
+// Bereich von Ebenen, der dem eines anderen Wagens nie näher als `min_separation` Ebenen kommen darf.
+pub struct Shaft {
+    pub id: usize,
+    pub min_separation: u8, // Mindestabstand zweier Wagen in Ebenen, 1 = benachbarte Ebenen sind erlaubt
+    occupied: Mutex<BTreeMap<usize, (u8, u8)>>, // Fahrstuhl-ID -> belegter Bereich (unterste, oberste Ebene)
+    released: Arc<AtomicU64>,                   // Zählt `settle`, damit wartende Wagen neu prüfen
+}
+
+// Liegen die Bereiche `low..=high` und `other_low..=other_high` weniger als `min_separation` Ebenen auseinander?
+pub fn too_close(low: u8, high: u8, other_low: u8, other_high: u8, min_separation: u8) -> bool {
+    (low as u16) < other_high as u16 + min_separation as u16
+        && (other_low as u16) < high as u16 + min_separation as u16
+}
+
+impl Shaft {
+    pub fn new(id: usize, min_separation: u8) -> Self {
+        Self {
+            id,
+            min_separation,
+            occupied: Mutex::new(BTreeMap::new()),
+            released: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    // Anderer Wagen, der dem Bereich `low..=high` zu nahe kommt
+    fn conflict(
+        &self,
+        occupied: &BTreeMap<usize, (u8, u8)>,
+        car: usize,
+        low: u8,
+        high: u8,
+    ) -> Option<usize> {
+        occupied
+            .iter()
+            .filter(|(&other, _)| other != car)
+            .find(|(_, &(other_low, other_high))| {
+                too_close(low, high, other_low, other_high, self.min_separation)
+            })
+            .map(|(&other, _)| other)
+    }
+
+    pub fn is_clear(&self, car: usize, low: u8, high: u8) -> bool {
+        let occupied = self.occupied.lock().unwrap();
+        self.conflict(&occupied, car, low, high).is_none()
+    }
+
+    // Alle anderen Wagen, die dem Bereich `low..=high` zu nahe kommen
+    pub fn blockers(&self, car: usize, low: u8, high: u8) -> Vec<usize> {
+        self.occupied
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(&other, &(other_low, other_high))| {
+                other != car && too_close(low, high, other_low, other_high, self.min_separation)
+            })
+            .map(|(&other, _)| other)
+            .collect()
+    }
+
+    pub fn position(&self, car: usize) -> Option<(u8, u8)> {
+        self.occupied.lock().unwrap().get(&car).copied()
+    }
+
+    // Wagen beim Start im Schacht anmelden
+    pub fn place(&self, car: usize, low: u8, high: u8) {
+        self.occupied.lock().unwrap().insert(car, (low, high));
+    }
+
+    // Fahrbereich reservieren; wartet höchstens `timeout` darauf, dass ein anderer Wagen Platz macht.
+    // Gibt bei Zeitüberschreitung den blockierenden Wagen zurück.
+    pub fn reserve(&self, car: usize, low: u8, high: u8, timeout: Duration) -> Result<(), usize> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let released = self.released.load(Ordering::SeqCst);
+            {
+                let mut occupied = self.occupied.lock().unwrap();
+                match self.conflict(&occupied, car, low, high) {
+                    None => {
+                        occupied.insert(car, (low, high));
+                        return Ok(());
+                    }
+                    Some(blocker) if Instant::now() >= deadline => return Err(blocker),
+                    Some(_) => {}
+                }
+            }
+            let counter = Arc::clone(&self.released);
+            clock::wait(
+                vec![Box::new(move || counter.load(Ordering::SeqCst) != released)],
+                Some(deadline),
+            );
+        }
+    }
+
+    // Nach der Fahrt nur noch die tatsächliche Position belegen und wartende Wagen wecken
+    pub fn settle(&self, car: usize, low: u8, high: u8) {
+        self.occupied.lock().unwrap().insert(car, (low, high));
+        self.released.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separation_counts_floors_between_ranges() {
+        assert!(too_close(4, 4, 4, 4, 1));
+        assert!(!too_close(4, 4, 5, 5, 1));
+        assert!(too_close(4, 4, 5, 5, 2));
+        assert!(!too_close(4, 4, 6, 6, 2));
+        // Doppeldecker belegen zwei Ebenen
+        assert!(too_close(3, 4, 5, 6, 2));
+        assert!(!too_close(5, 6, 3, 4, 1));
+    }
+
+    #[test]
+    fn reserve_waits_for_the_other_car() {
+        let shaft = Shaft::new(0, 1);
+        shaft.place(0, 0, 0);
+        shaft.place(1, 5, 5);
+        assert!(shaft.is_clear(0, 4, 4));
+        assert_eq!(shaft.reserve(0, 0, 5, Duration::from_millis(10)), Err(1));
+        assert_eq!(shaft.position(0), Some((0, 0)));
+        shaft.settle(1, 7, 7);
+        assert_eq!(shaft.reserve(0, 0, 5, Duration::from_millis(10)), Ok(()));
+        assert!(!shaft.is_clear(1, 5, 5));
+    }
+}
@@ -0,0 +1,291 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+use crate::config::SimulationConfig;
+use crate::console::{self, ConsoleInput};
+use crate::dashboard::CarView;
+use crate::journey::Leg;
+use crate::metrics::{Metrics, Progress};
+use crate::trace::Arrival;
+use crate::Simulation;
+
+// Wo ein Passagier zum Zeitpunkt des Snapshots steht
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "kebab-case")]
+pub enum PassengerSnapshot {
+    Pending, // Noch nicht angekommen, siehe `Arrival::at`
+    Waiting { floor: u8, waited: Duration },
+    Riding { elevator: usize, deck: u8, leg: Leg },
+    Leaving { floor: u8 }, // Steht schon in der Zielebene, das Teilstück ist noch nicht abgeschlossen
+    Walking { leg: Leg },  // Nimmt die Treppe
+    AtFloor { floor: u8 }, // Zwischen zwei Teilstücken
+    Done,                  // Angekommen, aufgegeben oder abgewiesen
+}
+
+// Zustand eines laufenden Systems: Fahrkörbe, Passagiere und Kennzahlen, dazu alles, wovon der Lauf abhängt.
+// Der Zustand selbst wird nicht wieder eingelesen: die Uhr der Simulation lässt die Akteure in fester
+// Reihenfolge rechnen, derselbe Aufbau mit demselben Startwert, denselben Ankünften und denselben Eingaben
+// ergibt daher denselben Lauf. Beim Fortsetzen wird er ohne Protokoll bis `taken_at` erneut abgespielt und
+// muss dort genau im gespeicherten Zustand stehen; danach läuft er weiter, wie der ursprüngliche Lauf
+// weitergelaufen wäre.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub taken_at: Duration, // Uhr der Simulation
+    pub config: SimulationConfig,
+    pub arrivals: Vec<Arrival>, // Eine Ankunft je Passagier-ID, auch die noch ausstehenden
+    #[serde(default)]
+    pub inputs: Vec<ConsoleInput>, // Eingaben über die Konsole bis `taken_at`
+    pub cars: Vec<CarView>,
+    pub passengers: Vec<PassengerSnapshot>,
+    pub metrics: Metrics,
+}
+
+impl Snapshot {
+    // Nur, während die Uhr steht, z. B. nach `clock::run_until`; sonst rechnen die Akteure weiter
+    pub fn take(simulation: &Simulation) -> Self {
+        let metrics = simulation.metrics.lock().unwrap().clone();
+        let arrivals = simulation.arrivals.lock().unwrap().clone();
+        let inputs = simulation.console_inputs.lock().unwrap().clone();
+        let cars = simulation.dashboard.cars();
+
+        let passengers = (0..arrivals.len())
+            .map(|id| {
+                let riding = cars.iter().enumerate().find_map(|(elevator, car)| {
+                    car.riders
+                        .iter()
+                        .position(|riders| riders.iter().any(|&(rider, _)| rider == id))
+                        .map(|deck| (elevator, deck as u8))
+                });
+                match (metrics.progress(id), riding) {
+                    (Progress::NotStarted, _) => PassengerSnapshot::Pending,
+                    // Der Fahrstuhl bestätigt den Einstieg, bevor der Passagier ihn meldet
+                    (
+                        Progress::Riding(leg) | Progress::Waiting { leg, .. },
+                        Some((elevator, deck)),
+                    ) => PassengerSnapshot::Riding {
+                        elevator,
+                        deck,
+                        leg,
+                    },
+                    (Progress::Waiting { leg, waited }, None) => PassengerSnapshot::Waiting {
+                        floor: leg.from,
+                        waited,
+                    },
+                    (Progress::Riding(leg), None) => PassengerSnapshot::Leaving { floor: leg.to },
+                    (Progress::Walking(leg), _) => PassengerSnapshot::Walking { leg },
+                    (Progress::AtFloor(floor), _) => PassengerSnapshot::AtFloor { floor },
+                    (Progress::Done, _) => PassengerSnapshot::Done,
+                }
+            })
+            .collect();
+
+        Self {
+            taken_at: metrics.clock(),
+            config: simulation.config.clone(),
+            arrivals,
+            inputs,
+            cars,
+            passengers,
+            metrics,
+        }
+    }
+
+    // Beginnt den Lauf mit `start` neu, spielt ihn ohne Protokoll bis `taken_at` ab, die Eingaben der Konsole
+    // jeweils zu ihrem Zeitpunkt, und prüft, dass er dort im gespeicherten Zustand steht. Danach steht die Uhr,
+    // bis der Aufrufer sie weiterlaufen lässt.
+    pub fn replay(
+        &self,
+        start: impl FnOnce() -> Result<Simulation, String>,
+    ) -> Result<Simulation, String> {
+        let simulation = {
+            let _silence = Silence::start()?;
+            let simulation = start()?;
+            let run_until = |at: Duration| {
+                let elapsed = simulation.metrics.lock().unwrap().clock();
+                clock::run_until(clock::now() + at.saturating_sub(elapsed));
+            };
+            for input in &self.inputs {
+                run_until(input.at);
+                console::replay(&simulation, input).map_err(|error| {
+                    format!(
+                        "cannot replay the console input '{}' at {:.1}s: {}",
+                        input.line,
+                        input.at.as_secs_f64(),
+                        error
+                    )
+                })?;
+            }
+            run_until(self.taken_at);
+            simulation
+        };
+        let replayed = Self::take(&simulation);
+        // Die Dauer darf sich beim Fortsetzen ändern, sie wirkt erst am Ende des Laufs
+        let config = SimulationConfig {
+            duration: self.config.duration,
+            ..replayed.config.clone()
+        };
+        let differing: Vec<&str> = [
+            ("settings", differs(&self.config, &config)),
+            ("traffic", differs(&self.arrivals, &replayed.arrivals)),
+            ("console inputs", differs(&self.inputs, &replayed.inputs)),
+            ("cars", differs(&self.cars, &replayed.cars)),
+            (
+                "passengers",
+                differs(&self.passengers, &replayed.passengers),
+            ),
+            ("metrics", differs(&self.metrics, &replayed.metrics)),
+        ]
+        .into_iter()
+        .filter_map(|(part, differs)| differs.then_some(part))
+        .collect();
+        if !differing.is_empty() {
+            return Err(format!(
+                "the replay does not reach the saved state at {:.1}s ({} differ); the snapshot was edited or written by another version",
+                self.taken_at.as_secs_f64(),
+                differing.join(", ")
+            ));
+        }
+        Ok(simulation)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_json()? + "\n")
+            .map_err(|error| format!("cannot write {}: {}", path, error))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|error| format!("cannot read {}: {}", path, error))?;
+        let snapshot = Self::from_json(&text)
+            .map_err(|error| format!("invalid snapshot {}: {}", path, error))?;
+        snapshot.config.validate()?;
+        if snapshot.passengers.len() != snapshot.arrivals.len()
+            || snapshot.cars.len() != snapshot.config.elevators.len()
+        {
+            return Err(format!(
+                "invalid snapshot {}: passengers or cars do not match the building",
+                path
+            ));
+        }
+        Ok(snapshot)
+    }
+
+    fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|error| error.to_string())
+    }
+
+    fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|error| error.to_string())
+    }
+}
+
+fn differs<T: Serialize>(saved: &T, replayed: &T) -> bool {
+    serde_json::to_value(saved).ok() != serde_json::to_value(replayed).ok()
+}
+
+// Verwirft die Protokollausgabe (stdout), solange der Lauf erneut abgespielt wird; beim Drop schreibt stdout
+// wieder dorthin, wohin es vorher schrieb, ins Terminal oder in die Anzeige
+struct Silence {
+    saved_stdout: i32,
+}
+
+impl Silence {
+    fn start() -> Result<Self, String> {
+        let null = File::create("/dev/null")
+            .map_err(|error| format!("cannot silence the replay: {}", error))?;
+        let _ = io::stdout().flush();
+        // SAFETY: Nur Dateideskriptoren dieses Prozesses; `null` bleibt offen, bis stdout umgeleitet ist
+        let saved_stdout = unsafe {
+            let saved_stdout = libc::dup(libc::STDOUT_FILENO);
+            libc::dup2(null.as_raw_fd(), libc::STDOUT_FILENO);
+            saved_stdout
+        };
+        Ok(Self { saved_stdout })
+    }
+}
+
+impl Drop for Silence {
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+        // SAFETY: `saved_stdout` stammt aus `dup` und wird nur hier geschlossen
+        unsafe {
+            libc::dup2(self.saved_stdout, libc::STDOUT_FILENO);
+            libc::close(self.saved_stdout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dashboard::Dashboard;
+    use crate::energy::EnergyModel;
+    use crate::profile::PassengerProfile;
+
+    #[test]
+    fn save_load_save_keeps_the_file() {
+        let config = SimulationConfig::default();
+        let shafts: Vec<Option<usize>> = config
+            .elevators
+            .iter()
+            .map(|elevator| elevator.shaft)
+            .collect();
+        let cars = Dashboard::new(config.floors, &config.specs(), &shafts).cars();
+        let arrivals = vec![
+            Arrival {
+                at: Duration::from_nanos(1_000_000_007),
+                origin: 0,
+                target: 3,
+                profile: PassengerProfile::Standard,
+                group: None,
+                badges: vec![1001],
+            },
+            Arrival {
+                at: Duration::from_secs(40),
+                origin: 5,
+                target: 1,
+                profile: PassengerProfile::Group(3),
+                group: Some(0),
+                badges: Vec::new(),
+            },
+        ];
+
+        let model = EnergyModel::default();
+        let mut metrics = Metrics::new();
+        metrics.register_elevator(0, model.clone());
+        metrics.journey_started(0, &[Leg { from: 0, to: 3 }], PassengerProfile::Standard);
+        metrics.leg_requested(0, Leg { from: 0, to: 3 });
+        metrics.trip_completed(0, 0, 3, model.trip(0, 3, 2));
+        metrics.trip_completed(0, 3, 1, model.trip(3, 1, 1));
+        metrics.power_event("Power: Grid failed".to_string());
+
+        let snapshot = Snapshot {
+            taken_at: Duration::from_nanos(12_345_678_901),
+            arrivals,
+            inputs: vec![ConsoleInput {
+                at: Duration::from_secs(12),
+                line: "fire on 2".to_string(),
+            }],
+            cars,
+            passengers: vec![
+                PassengerSnapshot::Waiting {
+                    floor: 0,
+                    waited: Duration::from_nanos(1),
+                },
+                PassengerSnapshot::Pending,
+            ],
+            metrics,
+            config,
+        };
+        let saved = snapshot.to_json().unwrap();
+        let loaded = Snapshot::from_json(&saved).unwrap();
+        assert_eq!(loaded.to_json().unwrap(), saved);
+        assert_eq!(loaded.taken_at, snapshot.taken_at);
+        assert!(!differs(&snapshot.metrics, &loaded.metrics));
+    }
+}
@@ -0,0 +1,559 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::access::SecuredFloor;
+use crate::config::{ElevatorConfig, PatienceConfig, SimulationConfig, TENANT_BADGE};
+use crate::faults::{Fault, FaultInjection};
+use crate::freight::FreightPolicy;
+use crate::metrics::Summary;
+use crate::trace::{self, Arrival};
+
+const DRAIN_TIME: Duration = Duration::from_secs(240); // Nach der letzten Ankunft: Frist fürs Abholen und eine Fahrt
+const HANG_MARGIN: Duration = Duration::from_secs(30); // Läuft ein Prozess so viel länger als geplant, hängt er
+const SHRINK_RUNS: usize = 40; // Höchstens so viele Läufe, um einen Fehlschlag zu verkleinern
+const PARKING: [&str; 5] = [
+    "stay",
+    "lobby",
+    "spread-evenly",
+    "predicted-demand",
+    "time-of-day",
+];
+
+// Zufälliges Szenario eines Stresstests. Der Verkehr steht wie bei `replay` als Liste der Ankünfte fest,
+// damit sich beim Verkleinern einzelne Passagiere entfernen lassen.
+#[derive(Debug, Clone)]
+pub struct Case {
+    seed: u64,
+    config: SimulationConfig,
+    arrivals: Vec<Arrival>,
+}
+
+// Welche Eigenschaft ein Lauf verletzt hat; ein verkleinertes Szenario muss dieselbe verletzen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Failure {
+    Crashed,  // Prozess endet mit einem Fehler, ohne Ergebnis
+    Hung,     // Prozess endet nicht, obwohl die Simulation abgelaufen ist
+    Deadlock, // Der Supervisor hat hängende Akteure abgeschottet
+    Safety,   // Der `InvariantMonitor` hat eine Verletzung gemeldet
+    Liveness, // Nicht jeder Passagier ist angekommen, hat aufgegeben oder wurde abgewiesen
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Crashed => write!(f, "crashed"),
+            Failure::Hung => write!(f, "hung"),
+            Failure::Deadlock => write!(f, "deadlock"),
+            Failure::Safety => write!(f, "safety"),
+            Failure::Liveness => write!(f, "liveness"),
+        }
+    }
+}
+
+// `Err` mit der verletzten Eigenschaft und einer kurzen Beschreibung
+type Verdict = Result<(), (Failure, String)>;
+
+// Ergebnis eines Laufs, wie es `replay --format json` schreibt
+#[derive(Deserialize)]
+struct RunOutput {
+    summary: Summary,
+}
+
+impl Case {
+    // Gebäude, Verkehr, Fehler und Zeitversatz aus dem Startwert; `sets` überschreibt Einstellungen wie
+    // `--set` vor dem Erzeugen des Verkehrs
+    pub fn generate(seed: u64, sets: &[(String, String)]) -> Result<Self, String> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut config = random_building(&mut rng);
+        config.seed = Some(seed);
+        for (key, value) in sets {
+            config.set(key, value)?;
+        }
+        let specs = config.specs();
+        // Gruppen fahren zwischen beliebigen Ebenen; nur, was das Gebäude verbindet, bleibt im Szenario
+        let arrivals = trace::generate(&config, &specs, &mut rng)
+            .into_iter()
+            .filter(|arrival| crate::plan_arrival(&specs, arrival).is_some())
+            .collect();
+        Ok(Self {
+            seed,
+            config,
+            arrivals,
+        })
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} floor(s), {} elevator(s), {} passenger(s), {} fault(s), jitter {}ms",
+            self.config.floors,
+            self.config.elevators.len(),
+            self.arrivals.len(),
+            self.config.faults.len(),
+            self.config.jitter.as_millis()
+        )
+    }
+
+    // Als Szenario wie im Golden-Verzeichnis: `NAME.toml` mit dem Gebäude, `NAME.csv` mit dem Verkehr
+    fn save(&self, dir: &Path, name: &str) -> Result<(PathBuf, PathBuf), String> {
+        fs::create_dir_all(dir)
+            .map_err(|error| format!("cannot create {}: {}", dir.display(), error))?;
+        let config = dir.join(format!("{}.toml", name));
+        let trace = dir.join(format!("{}.csv", name));
+        let text = toml::to_string(&self.config)
+            .map_err(|error| format!("cannot write scenario: {}", error))?;
+        fs::write(&config, text)
+            .map_err(|error| format!("cannot write {}: {}", config.display(), error))?;
+        trace::write_csv(&trace.to_string_lossy(), &self.arrivals)?;
+        Ok((config, trace))
+    }
+
+    // Kleinere Varianten, die größten Schritte zuerst: ohne Fehler, ohne Zeitversatz, weniger Passagiere,
+    // weniger Fahrstühle, ohne gesicherte Ebenen und Gruppen, alle Ankünfte beim Start
+    fn smaller(&self) -> Vec<Case> {
+        let mut candidates = Vec::new();
+        let variant = |change: &dyn Fn(&mut Case)| {
+            let mut case = self.clone();
+            change(&mut case);
+            case
+        };
+        if !self.config.faults.is_empty() {
+            candidates.push(variant(&|case| case.config.faults.clear()));
+            for index in 0..self.config.faults.len() {
+                candidates.push(variant(&|case| {
+                    case.config.faults.remove(index);
+                }));
+            }
+        }
+        if !self.config.jitter.is_zero() {
+            candidates.push(variant(&|case| case.config.jitter = Duration::ZERO));
+        }
+        let mut chunk = self.arrivals.len() / 2;
+        while chunk > 0 {
+            for start in (0..self.arrivals.len()).step_by(chunk) {
+                candidates.push(variant(&|case| {
+                    case.arrivals
+                        .drain(start..(start + chunk).min(case.arrivals.len()));
+                }));
+            }
+            chunk /= 2;
+        }
+        for elevator in 0..self.config.elevators.len() {
+            if let Some(case) = self.without_elevator(elevator) {
+                candidates.push(case);
+            }
+        }
+        if !self.config.secured_floors.is_empty() {
+            candidates.push(variant(&|case| case.config.secured_floors.clear()));
+        }
+        if self.arrivals.iter().any(|arrival| arrival.group.is_some()) {
+            candidates.push(variant(&|case| {
+                case.arrivals
+                    .iter_mut()
+                    .for_each(|arrival| arrival.group = None)
+            }));
+        }
+        if self.arrivals.iter().any(|arrival| !arrival.at.is_zero()) {
+            candidates.push(variant(&|case| {
+                case.arrivals
+                    .iter_mut()
+                    .for_each(|arrival| arrival.at = Duration::ZERO)
+            }));
+        }
+        candidates
+    }
+
+    // Ohne den Fahrstuhl, solange das Gebäude danach noch alle Fahrten verbindet
+    fn without_elevator(&self, elevator: usize) -> Option<Case> {
+        if self.config.elevators.len() < 2 {
+            return None;
+        }
+        let mut case = self.clone();
+        case.config.elevators.remove(elevator);
+        case.config
+            .faults
+            .retain(|injection| injection.elevator != elevator);
+        for injection in case
+            .config
+            .faults
+            .iter_mut()
+            .filter(|injection| injection.elevator > elevator)
+        {
+            injection.elevator -= 1;
+        }
+        let specs = case.config.specs();
+        let reachable = case
+            .arrivals
+            .iter()
+            .all(|arrival| crate::plan_arrival(&specs, arrival).is_some());
+        (reachable && case.config.validate().is_ok()).then_some(case)
+    }
+}
+
+fn random_building(rng: &mut StdRng) -> SimulationConfig {
+    let floors = rng.gen_range(3..=10u8);
+    let sky_lobby = floors / 2;
+    let mut elevators = Vec::new();
+    if rng.gen_bool(0.5) {
+        // Lokalfahrstühle über alle Ebenen, einzelne als Doppeldecker oder Lastenaufzug
+        for _ in 0..rng.gen_range(1..=3) {
+            elevators.push(ElevatorConfig {
+                floors: (0..floors).collect(),
+                decks: if rng.gen_bool(0.2) { 2 } else { 1 },
+                freight: rng.gen_bool(0.3),
+                shaft: None,
+            });
+        }
+    } else {
+        // Zwei Zonen mit Umstieg an der Sky-Lobby, oft zwei Wagen in einem Schacht, dazu ein Shuttle
+        let shaft = rng.gen_bool(0.5).then_some(0);
+        elevators.push(ElevatorConfig {
+            floors: (0..=sky_lobby).collect(),
+            freight: rng.gen_bool(0.5),
+            shaft,
+            ..ElevatorConfig::default()
+        });
+        elevators.push(ElevatorConfig {
+            floors: (sky_lobby..floors).collect(),
+            freight: rng.gen_bool(0.3),
+            shaft,
+            ..ElevatorConfig::default()
+        });
+        if rng.gen_bool(0.5) {
+            elevators.push(ElevatorConfig {
+                floors: vec![0, sky_lobby],
+                ..ElevatorConfig::default()
+            });
+        }
+    }
+
+    let arrival_window = Duration::from_secs(rng.gen_range(0..=20));
+    let faults: Vec<FaultInjection> = (0..rng.gen_range(0..=2))
+        .map(|_| FaultInjection {
+            after: Duration::from_secs(rng.gen_range(2..=arrival_window.as_secs() + 20)),
+            elevator: rng.gen_range(0..elevators.len()),
+            fault: match rng.gen_range(0..4) {
+                0 => Fault::OutOfService,
+                1 => Fault::DoorJam,
+                2 => Fault::StuckBetweenFloors,
+                _ => Fault::Slow(rng.gen_range(1.5..3.0)),
+            },
+        })
+        .collect();
+    // Nach einem Ausfall erreicht nicht jeder sein Ziel; ohne Aufgeben würde er bis zum Ende warten
+    let permanent = faults
+        .iter()
+        .any(|injection| !matches!(injection.fault, Fault::Slow(_)));
+    let give_up_after =
+        (permanent || rng.gen_bool(0.5)).then(|| Duration::from_secs(rng.gen_range(30..=60)));
+
+    SimulationConfig {
+        floors,
+        sky_lobby,
+        elevators,
+        passengers: rng.gen_range(1..=12),
+        groups: rng.gen_range(0..=2),
+        group_size: rng.gen_range(2..=4),
+        arrival_window,
+        start_hour: rng.gen_range(0..24),
+        duration: arrival_window + DRAIN_TIME,
+        seed: None,
+        parking: PARKING.choose(rng).unwrap().to_string(),
+        freight_policy: if rng.gen_bool(0.5) {
+            FreightPolicy::Mixed
+        } else {
+            FreightPolicy::Separate
+        },
+        patience: PatienceConfig {
+            stairs_floors: rng.gen_range(0..=2),
+            stairs_after: Duration::from_secs(rng.gen_range(10..=30)),
+            give_up_after,
+        },
+        hall_call_limit: Duration::from_secs(180), // Ein Wagen für viele Fahrgäste braucht mehrere Runden
+        min_separation: 1,
+        secured_floors: if rng.gen_bool(0.3) {
+            vec![SecuredFloor {
+                floor: floors - 1,
+                badges: vec![TENANT_BADGE],
+                open_hours: None,
+            }]
+        } else {
+            Vec::new()
+        },
+        tenant_share: 0.5,
+        faults,
+        jitter: Duration::from_millis(rng.gen_range(0..=500)),
+        demo_script: false,
+        watchdog: SimulationConfig::default().watchdog,
+    }
+}
+
+// Läuft ein Szenario als eigener Prozess (`replay --strict --format json`) und prüft Sicherheit,
+// Verklemmungen und Lebendigkeit; `index` trennt die Dateien gleichzeitig laufender Prozesse
+fn check(executable: &Path, case: &Case, index: usize) -> Verdict {
+    let crashed = |error: String| (Failure::Crashed, error);
+    let base =
+        std::env::temp_dir().join(format!("elevator-stress-{}-{}", std::process::id(), index));
+    let (config, trace) = case.save(&base, "case").map_err(crashed)?;
+    let output_path = base.join("result.json");
+    // Fehlermeldungen in eine Datei, eine volle Pipe würde den Prozess blockieren und wie ein Hänger aussehen
+    let stderr_path = base.join("stderr.txt");
+    let stderr = fs::File::create(&stderr_path)
+        .map_err(|error| crashed(format!("cannot start: {}", error)))?;
+    let mut child = Command::new(executable)
+        .arg("replay")
+        .arg(&trace)
+        .arg("--config")
+        .arg(&config)
+        .args(["--strict", "--format", "json", "--output"])
+        .arg(&output_path)
+        .stdout(Stdio::null())
+        .stderr(stderr)
+        .spawn()
+        .map_err(|error| crashed(format!("cannot start: {}", error)))?;
+
+    let deadline = Instant::now() + case.config.duration + HANG_MARGIN;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(200)),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                let _ = fs::remove_dir_all(&base);
+                return Err((
+                    Failure::Hung,
+                    format!(
+                        "still running {:.0}s after the end of the simulation",
+                        HANG_MARGIN.as_secs_f64()
+                    ),
+                ));
+            }
+            Err(error) => return Err(crashed(error.to_string())),
+        }
+    };
+    let output = fs::read_to_string(&output_path);
+    let stderr = fs::read_to_string(&stderr_path).unwrap_or_default();
+    let _ = fs::remove_dir_all(&base);
+
+    // Mit `--strict` schreibt der Lauf sein Ergebnis, bevor er wegen einer Verletzung fehlschlägt
+    let Some(summary) = output
+        .ok()
+        .and_then(|text| serde_json::from_str::<Vec<RunOutput>>(&text).ok())
+        .and_then(|mut results| results.pop())
+        .map(|result| result.summary)
+    else {
+        return Err(crashed(format!("exit {}: {}", status, stderr.trim())));
+    };
+    if summary.invariant_violations > 0 {
+        return Err((
+            Failure::Safety,
+            format!("{} invariant violation(s)", summary.invariant_violations),
+        ));
+    }
+    if summary.stalled_actors > 0 {
+        return Err((
+            Failure::Deadlock,
+            format!("{} actor(s) stalled", summary.stalled_actors),
+        ));
+    }
+    let missing = case.arrivals.len().saturating_sub(summary.passengers) + summary.unfinished;
+    if missing > 0 {
+        return Err((
+            Failure::Liveness,
+            format!(
+                "{} of {} passenger(s) never reached a final state",
+                missing,
+                case.arrivals.len()
+            ),
+        ));
+    }
+    if !status.success() {
+        return Err(crashed(format!("exit {}", status)));
+    }
+    Ok(())
+}
+
+// Sucht das kleinste Szenario, das noch auf dieselbe Art fehlschlägt. Die Läufe sind nicht deterministisch;
+// ein Kandidat zählt nur, wenn er tatsächlich fehlschlägt, ein einmal gefundener bleibt auch bei späterem Glück.
+// Je Runde laufen bis zu `jobs` Kandidaten gleichzeitig, der erste fehlschlagende ersetzt das Szenario.
+fn shrink(
+    executable: &Path,
+    case: Case,
+    failure: Failure,
+    jobs: usize,
+    slot: usize,
+) -> (Case, String) {
+    let mut smallest = case;
+    let mut detail = String::new();
+    let mut runs = 0;
+    'rounds: while runs < SHRINK_RUNS {
+        let candidates = smallest.smaller();
+        for batch in candidates.chunks(jobs) {
+            let batch = &batch[..batch.len().min(SHRINK_RUNS - runs)];
+            if batch.is_empty() {
+                break 'rounds;
+            }
+            runs += batch.len();
+            let results: Vec<Verdict> = thread::scope(|scope| {
+                let handles: Vec<_> = batch
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, candidate)| {
+                        scope.spawn(move || check(executable, candidate, slot * jobs + offset))
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap())
+                    .collect()
+            });
+            let found = batch
+                .iter()
+                .zip(results)
+                .find_map(|(candidate, result)| match result {
+                    Err((kind, text)) if kind == failure => Some((candidate.clone(), text)),
+                    _ => None,
+                });
+            if let Some((candidate, text)) = found {
+                eprintln!(
+                    "Stress: seed {} shrunk to {}",
+                    candidate.seed,
+                    candidate.describe()
+                );
+                smallest = candidate;
+                detail = text;
+                continue 'rounds;
+            }
+        }
+        break;
+    }
+    (smallest, detail)
+}
+
+// Ein Szenario je Startwert ab `first_seed`; fehlgeschlagene Szenarien werden verkleinert und in `dir`
+// abgelegt, von wo sie sich mit `replay` nachstellen oder als Golden-Szenario übernehmen lassen
+pub fn run(
+    first_seed: u64,
+    cases: u64,
+    sets: &[(String, String)],
+    jobs: usize,
+    dir: &Path,
+) -> Result<(), String> {
+    let executable =
+        std::env::current_exe().map_err(|error| format!("cannot find executable: {}", error))?;
+    let cases: Vec<Case> = (first_seed..first_seed + cases)
+        .map(|seed| Case::generate(seed, sets))
+        .collect::<Result<_, _>>()?;
+    eprintln!(
+        "Stress: {} case(s) from seed {}, {} at a time",
+        cases.len(),
+        first_seed,
+        jobs
+    );
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Verdict>>> = Mutex::new(vec![None; cases.len()]);
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(cases.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(case) = cases.get(index) else {
+                    break;
+                };
+                let result = check(&executable, case, index);
+                eprintln!(
+                    "Stress: seed {} {}",
+                    case.seed,
+                    match &result {
+                        Ok(()) => "ok".to_string(),
+                        Err((failure, _)) => format!("failed ({})", failure),
+                    }
+                );
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    let mut failed = 0;
+    for (slot, (case, result)) in cases.iter().zip(results.into_inner().unwrap()).enumerate() {
+        let Err((failure, detail)) = result.expect("every case ran") else {
+            println!("Stress: seed {} ok ({})", case.seed, case.describe());
+            continue;
+        };
+        failed += 1;
+        println!(
+            "Stress: seed {} failed, {}: {} ({})",
+            case.seed,
+            failure,
+            detail,
+            case.describe()
+        );
+        let (smallest, shrunk_detail) =
+            shrink(&executable, case.clone(), failure, jobs, cases.len() + slot);
+        let name = format!("stress-{}", case.seed);
+        let (config, trace) = smallest.save(dir, &name)?;
+        println!(
+            "Stress: seed {} minimal scenario: {}{}",
+            case.seed,
+            smallest.describe(),
+            if shrunk_detail.is_empty() {
+                String::new()
+            } else {
+                format!(", {}", shrunk_detail)
+            }
+        );
+        println!(
+            "Stress: rerun with: elevator replay {} --config {}",
+            trace.display(),
+            config.display()
+        );
+    }
+    if failed > 0 {
+        return Err(format!(
+            "{} of {} case(s) failed, minimal scenarios in {}",
+            failed,
+            cases.len(),
+            dir.display()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_case_loads_unchanged() {
+        let mut case = Case::generate(7, &[]).unwrap();
+        case.config.jitter = Duration::from_millis(237);
+        let dir = std::env::temp_dir().join(format!("elevator-stress-{}", std::process::id()));
+        let (config, trace) = case.save(&dir, "case").unwrap();
+
+        let loaded = Case {
+            seed: case.seed,
+            config: SimulationConfig::load(&config.to_string_lossy()).unwrap(),
+            arrivals: trace::read_csv(&trace.to_string_lossy()).unwrap(),
+        };
+        assert_eq!(loaded.config.jitter, Duration::from_millis(237));
+        assert_eq!(loaded.arrivals, case.arrivals);
+        assert_eq!(loaded.describe(), case.describe());
+
+        let (again, _) = loaded.save(&dir, "again").unwrap();
+        assert_eq!(
+            fs::read_to_string(&config).unwrap(),
+            fs::read_to_string(&again).unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,345 @@
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+
+use crate::clock::{self, Instant};
+use crate::faults::Fault;
+use crate::metrics::Metrics;
+use crate::protocol::{ElevatorId, ElevatorStatus};
+
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(500);
+
+// Jeder Thread der Simulation ist ein Akteur, den der Supervisor überwacht
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ActorId {
+    ControlSystem,
+    Elevator(usize),
+    Floor(u8),
+    Passenger(usize),
+}
+
+impl fmt::Display for ActorId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActorId::ControlSystem => write!(f, "Control System"),
+            ActorId::Elevator(id) => write!(f, "Elevator {}", id),
+            ActorId::Floor(id) => write!(f, "Floor {}", id),
+            ActorId::Passenger(id) => write!(f, "Passenger {}", id),
+        }
+    }
+}
+
+// Was nach einem Absturz oder Hänger passiert
+#[derive(Debug, Clone, Copy)]
+pub enum FailurePolicy {
+    Restart { max_restarts: u32 }, // Thread neu starten, danach abschotten
+    Fence,                         // Nicht neu starten, nur abschotten
+}
+
+#[derive(Debug, Clone)]
+pub struct SupervisorConfig {
+    pub heartbeat_timeout: Duration, // Ohne Heartbeat so lange gilt ein Akteur als hängend
+    pub control_system: FailurePolicy,
+    pub elevator: FailurePolicy,
+    pub floor: FailurePolicy,
+    pub passenger: FailurePolicy,
+}
+
+impl SupervisorConfig {
+    fn policy(&self, actor: ActorId) -> FailurePolicy {
+        match actor {
+            ActorId::ControlSystem => self.control_system,
+            ActorId::Elevator(_) => self.elevator,
+            ActorId::Floor(_) => self.floor,
+            ActorId::Passenger(_) => self.passenger,
+        }
+    }
+}
+
+// Sendet höchstens alle HEARTBEAT_INTERVAL einen Heartbeat; darf in jeder Schleife aufgerufen werden
+#[derive(Clone)]
+pub struct Heartbeat {
+    actor: ActorId,
+    tx: Sender<ActorId>,
+    last: Option<Instant>,
+}
+
+impl Heartbeat {
+    pub fn beat(&mut self) {
+        if self
+            .last
+            .is_none_or(|last| last.elapsed() >= HEARTBEAT_INTERVAL)
+        {
+            let _ = self.tx.send(self.actor);
+            self.last = Some(Instant::now());
+        }
+    }
+
+    // Wie `clock::sleep`, sendet dabei weiter Heartbeats
+    pub fn sleep(&mut self, duration: Duration) {
+        let until = Instant::now() + duration;
+        while Instant::now() < until {
+            self.beat();
+            clock::sleep_until(until.min(Instant::now() + HEARTBEAT_INTERVAL));
+        }
+    }
+
+    // Wie `clock::recv_deadline`, ohne `until` unbegrenzt; sendet dabei weiter Heartbeats
+    pub fn recv<T: Send + 'static>(
+        &mut self,
+        receiver: &Receiver<T>,
+        until: Option<Instant>,
+    ) -> Result<T, RecvTimeoutError> {
+        loop {
+            self.beat();
+            let step = Instant::now() + HEARTBEAT_INTERVAL;
+            match clock::recv_deadline(receiver, until.map_or(step, |until| until.min(step))) {
+                Err(RecvTimeoutError::Timeout)
+                    if until.is_none_or(|until| Instant::now() < until) => {}
+                result => return result,
+            }
+        }
+    }
+}
+
+type Body = Arc<dyn Fn() + Send + Sync>;
+
+// Ende eines Akteurs: `None` regulär, sonst die Meldung des Panics
+type Exit = (ActorId, Option<String>);
+
+struct Supervised {
+    actor: ActorId,
+    participant: clock::Participant, // Zum Abbrechen, wenn der Akteur abgeschottet wird
+    body: Option<Body>,              // Fehlt bei Akteuren, die sich nicht neu starten lassen
+    restarts: u32,
+    last_heartbeat: Option<Instant>, // Nur Akteure, die Heartbeats senden, werden auf Hänger geprüft
+}
+
+// Startet alle Akteure auf der Uhr der Simulation, erkennt Abstürze und Hänger und
+// startet Akteure nach der konfigurierten Strategie neu oder schottet sie ab
+pub struct Supervisor {
+    actors_tx: Sender<Supervised>,
+    heartbeat_tx: Sender<ActorId>,
+    exits_tx: Sender<Exit>,
+}
+
+impl Supervisor {
+    // `status_tx` meldet abgeschottete Fahrstühle an die Steuerung, damit ihre Rufe neu vergeben werden;
+    // Hänger landen zusätzlich in den Kennzahlen, ein Stresstest wertet sie als Verklemmung
+    pub fn new(
+        config: SupervisorConfig,
+        status_tx: Sender<ElevatorStatus>,
+        metrics: Arc<Mutex<Metrics>>,
+    ) -> Self {
+        let (actors_tx, actors_rx) = unbounded();
+        let (heartbeat_tx, heartbeat_rx) = unbounded();
+        let (exits_tx, exits_rx) = unbounded();
+        let exits = exits_tx.clone();
+        clock::spawn("Supervisor".to_string(), move || {
+            Self::run(
+                config,
+                actors_rx,
+                heartbeat_rx,
+                exits_rx,
+                exits,
+                status_tx,
+                metrics,
+            )
+        });
+        Self {
+            actors_tx,
+            heartbeat_tx,
+            exits_tx,
+        }
+    }
+
+    pub fn heartbeat(&self, actor: ActorId) -> Heartbeat {
+        Heartbeat {
+            actor,
+            tx: self.heartbeat_tx.clone(),
+            last: None,
+        }
+    }
+
+    // Akteur starten, der bei Bedarf mit demselben `body` neu gestartet werden kann
+    pub fn spawn<F>(&self, actor: ActorId, body: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let body: Body = Arc::new(body);
+        let restart = Arc::clone(&body);
+        let participant = start(actor, move || restart(), &self.exits_tx);
+        self.register(actor, participant, Some(body));
+    }
+
+    // Akteur, dessen Zustand beim Start in den Thread wandert und sich nicht neu starten lässt
+    pub fn spawn_once<F>(&self, actor: ActorId, body: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let participant = start(actor, body, &self.exits_tx);
+        self.register(actor, participant, None);
+    }
+
+    fn register(&self, actor: ActorId, participant: clock::Participant, body: Option<Body>) {
+        let supervised = Supervised {
+            actor,
+            participant,
+            body,
+            restarts: 0,
+            last_heartbeat: None,
+        };
+        if self.actors_tx.send(supervised).is_err() {
+            println!("Supervisor: Not running, {} is unsupervised", actor);
+        }
+    }
+
+    fn run(
+        config: SupervisorConfig,
+        actors_rx: Receiver<Supervised>,
+        heartbeat_rx: Receiver<ActorId>,
+        exits_rx: Receiver<Exit>,
+        exits_tx: Sender<Exit>,
+        status_tx: Sender<ElevatorStatus>,
+        metrics: Arc<Mutex<Metrics>>,
+    ) {
+        let mut actors: BTreeMap<ActorId, Supervised> = BTreeMap::new();
+        loop {
+            clock::wait(
+                vec![
+                    clock::ready(&actors_rx),
+                    clock::ready(&heartbeat_rx),
+                    clock::ready(&exits_rx),
+                ],
+                Some(Instant::now() + HEARTBEAT_INTERVAL),
+            );
+            // Erst Anmeldungen, dann Heartbeats und Enden, damit ein kurzlebiger Akteur schon bekannt ist
+            while let Ok(supervised) = actors_rx.try_recv() {
+                actors.insert(supervised.actor, supervised);
+            }
+            while let Ok(actor) = heartbeat_rx.try_recv() {
+                if let Some(supervised) = actors.get_mut(&actor) {
+                    supervised.last_heartbeat = Some(Instant::now());
+                }
+            }
+
+            while let Ok((actor, panicked)) = exits_rx.try_recv() {
+                let Some(supervised) = actors.remove(&actor) else {
+                    continue;
+                };
+                let Some(message) = panicked else {
+                    // Regulär beendet, z. B. ein Fahrstuhl außer Betrieb oder ein Passagier am Ziel
+                    continue;
+                };
+                println!(
+                    "Supervisor: {} panicked after {} restart(s): {}",
+                    actor, supervised.restarts, message
+                );
+                let restarts = supervised.restarts;
+                match (config.policy(actor), supervised.body) {
+                    (FailurePolicy::Restart { max_restarts }, Some(body))
+                        if restarts < max_restarts =>
+                    {
+                        println!(
+                            "Supervisor: Restarting {} (restart {}/{})",
+                            actor,
+                            restarts + 1,
+                            max_restarts
+                        );
+                        let restart = Arc::clone(&body);
+                        let participant = start(actor, move || restart(), &exits_tx);
+                        actors.insert(
+                            actor,
+                            Supervised {
+                                actor,
+                                participant,
+                                body: Some(body),
+                                restarts: restarts + 1,
+                                last_heartbeat: None,
+                            },
+                        );
+                    }
+                    (FailurePolicy::Restart { .. }, None) => {
+                        println!("Supervisor: {} cannot be restarted", actor);
+                        fence(actor, &status_tx, &metrics);
+                    }
+                    _ => fence(actor, &status_tx, &metrics),
+                }
+            }
+
+            // Ein hängender Akteur wird abgeschottet und abgebrochen: sein Thread endet bei seinem nächsten
+            // Warten, statt mit veralteten Kanälen weiterzulaufen
+            let stalled: Vec<(ActorId, Duration)> = actors
+                .values()
+                .filter_map(|supervised| {
+                    let silent = supervised.last_heartbeat?.elapsed();
+                    (silent > config.heartbeat_timeout).then_some((supervised.actor, silent))
+                })
+                .collect();
+            for (actor, silent) in stalled {
+                println!(
+                    "Supervisor: {} stalled, no heartbeat for {:.1}s",
+                    actor,
+                    silent.as_secs_f64()
+                );
+                if let Some(supervised) = actors.remove(&actor) {
+                    supervised.participant.cancel();
+                }
+                metrics.lock().unwrap().actor_stalled(actor.to_string());
+                fence(actor, &status_tx, &metrics);
+            }
+        }
+    }
+}
+
+// Startet den Thread eines Akteurs auf der Uhr der Simulation und meldet sein Ende; ein abgebrochener
+// Akteur meldet sich nicht mehr
+fn start<F>(actor: ActorId, body: F, exits: &Sender<Exit>) -> clock::Participant
+where
+    F: FnOnce() + Send + 'static,
+{
+    let exits = exits.clone();
+    clock::spawn(actor.to_string(), move || {
+        let panicked = match panic::catch_unwind(AssertUnwindSafe(body)) {
+            Ok(()) => None,
+            Err(payload) if clock::is_cancelled(&*payload) => return,
+            Err(payload) => Some(panic_message(&payload)),
+        };
+        let _ = exits.send((actor, panicked));
+    })
+}
+
+// Abgeschottete Fahrstühle bekommen keine Aufträge mehr
+fn fence(actor: ActorId, status_tx: &Sender<ElevatorStatus>, metrics: &Mutex<Metrics>) {
+    println!("Supervisor: Fencing {}", actor);
+    match actor {
+        ActorId::Elevator(id) => {
+            metrics.lock().unwrap().elevator_fenced(id);
+            let _ = status_tx.send(ElevatorStatus::FaultReported(
+                ElevatorId(id),
+                Fault::OutOfService,
+                Vec::new(),
+            ));
+        }
+        ActorId::ControlSystem => {
+            println!("Supervisor: No control system left, requests are no longer dispatched");
+        }
+        ActorId::Passenger(id) => metrics.lock().unwrap().passenger_fenced(id),
+        ActorId::Floor(_) => {}
+    }
+}
+
+fn panic_message(payload: &Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
@@ -0,0 +1,189 @@
+use std::fs;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::car::ElevatorSpec;
+use crate::config::{parse_duration, SimulationConfig, TENANT_BADGE};
+use crate::freight::car_accepts;
+use crate::profile::PassengerProfile;
+
+// Ankunft eines Passagiers; ein Fahrtenprotokoll ist eine Liste davon, eine Zeile je Passagier
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Arrival {
+    pub at: Duration, // Zeit seit Start der Simulation
+    pub origin: u8,
+    pub target: u8,
+    pub profile: PassengerProfile,
+    pub group: Option<usize>, // Gemeinsame Ankunft, siehe `Group`
+    #[serde(default)]
+    pub badges: Vec<u32>, // Zutrittsausweise, leer ohne Ausweis
+}
+
+const HEADER: &str = "at_secs,origin,target,profile,group,badges";
+
+// Zufälliger Verkehr nach der Konfiguration: einzelne Passagiere, danach die Gruppen
+pub fn generate(
+    config: &SimulationConfig,
+    specs: &[ElevatorSpec],
+    rng: &mut StdRng,
+) -> Vec<Arrival> {
+    let mut arrivals = Vec::new();
+    let arrival_time = |rng: &mut StdRng| {
+        if config.arrival_window.is_zero() {
+            Duration::ZERO
+        } else {
+            config.arrival_window.mul_f64(rng.gen_range(0.0..1.0))
+        }
+    };
+
+    for _ in 0..config.passengers {
+        let profile = match rng.gen_range(0..13) {
+            0 => PassengerProfile::Wheelchair,
+            1 => PassengerProfile::Luggage,
+            2 => PassengerProfile::Stroller,
+            3 => PassengerProfile::Group(2),
+            4 => PassengerProfile::Robot,
+            5 => PassengerProfile::FreightCart,
+            6 => PassengerProfile::HospitalBed,
+            _ => PassengerProfile::Standard,
+        };
+        // Zufällige Start- und Zieletage unter den Ebenen, die mit passenden Fahrstühlen erreichbar sind
+        let mut reachable: Vec<u8> = specs
+            .iter()
+            .filter(|spec| car_accepts(spec, profile))
+            .flat_map(|spec| spec.served_floors.iter().copied())
+            .collect();
+        reachable.sort_unstable();
+        reachable.dedup();
+        if reachable.len() < 2 {
+            continue;
+        }
+        let (origin, target) = random_trip(&reachable, rng);
+        arrivals.push(Arrival {
+            at: arrival_time(rng),
+            origin,
+            target,
+            profile,
+            group: None,
+            badges: Vec::new(),
+        });
+    }
+
+    // Gruppenankünfte, z. B. zu einer Besprechung: gemeinsame Start- und Zielebene, ein Fahrkorb für alle.
+    // Die Besucher meldet der Gastgeber an, sie fahren mit seinem Ausweis.
+    let floors: Vec<u8> = (0..config.floors).collect();
+    for group in 0..config.groups {
+        let (origin, target) = random_trip(&floors, rng);
+        let at = arrival_time(rng);
+        for _ in 0..config.group_size {
+            arrivals.push(Arrival {
+                at,
+                origin,
+                target,
+                profile: PassengerProfile::Standard,
+                group: Some(group),
+                badges: vec![TENANT_BADGE],
+            });
+        }
+    }
+
+    // Ausweise erst zum Schluss ziehen, damit Ebenen und Zeiten eines Startwerts gleich bleiben
+    if config.tenant_share > 0.0 {
+        for arrival in arrivals
+            .iter_mut()
+            .filter(|arrival| arrival.group.is_none())
+        {
+            if rng.gen_bool(config.tenant_share) {
+                arrival.badges.push(TENANT_BADGE);
+            }
+        }
+    }
+    arrivals
+}
+
+fn random_trip(floors: &[u8], rng: &mut StdRng) -> (u8, u8) {
+    let origin = floors[rng.gen_range(0..floors.len())];
+    let target = loop {
+        let floor = floors[rng.gen_range(0..floors.len())];
+        if floor != origin {
+            break floor;
+        }
+    };
+    (origin, target)
+}
+
+pub fn write_csv(path: &str, arrivals: &[Arrival]) -> Result<(), String> {
+    let mut text = format!("{}\n", HEADER);
+    for arrival in arrivals {
+        text.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            seconds(arrival.at),
+            arrival.origin,
+            arrival.target,
+            arrival.profile,
+            arrival
+                .group
+                .map_or(String::new(), |group| group.to_string()),
+            arrival
+                .badges
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        ));
+    }
+    fs::write(path, text).map_err(|error| format!("cannot write {}: {}", path, error))
+}
+
+// Sekunden mit mindestens drei Nachkommastellen, aber ohne eine Nanosekunde zu verlieren
+fn seconds(at: Duration) -> String {
+    let fraction = format!("{:09}", at.subsec_nanos());
+    let digits = fraction.trim_end_matches('0').len().max(3);
+    format!("{}.{}", at.as_secs(), &fraction[..digits])
+}
+
+pub fn read_csv(path: &str) -> Result<Vec<Arrival>, String> {
+    let text =
+        fs::read_to_string(path).map_err(|error| format!("cannot read {}: {}", path, error))?;
+    let mut arrivals = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        // Ältere Protokolle haben noch keine Spalte `badges`
+        if line.is_empty() || line.starts_with('#') || (number == 0 && HEADER.starts_with(line)) {
+            continue;
+        }
+        let invalid =
+            |what: &str| format!("{}:{}: invalid {} in '{}'", path, number + 1, what, line);
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if fields.len() < 3 {
+            return Err(invalid("row"));
+        }
+        arrivals.push(Arrival {
+            at: parse_duration(fields[0]).map_err(|_| invalid("time"))?,
+            origin: fields[1].parse().map_err(|_| invalid("origin"))?,
+            target: fields[2].parse().map_err(|_| invalid("target"))?,
+            profile: match fields.get(3) {
+                Some(name) if !name.is_empty() => name.parse().map_err(|_| invalid("profile"))?,
+                _ => PassengerProfile::Standard,
+            },
+            group: match fields.get(4) {
+                Some(group) if !group.is_empty() => {
+                    Some(group.parse().map_err(|_| invalid("group"))?)
+                }
+                _ => None,
+            },
+            // Mehrere Ausweise durch Leerzeichen getrennt
+            badges: match fields.get(5) {
+                Some(badges) => badges
+                    .split_whitespace()
+                    .map(|badge| badge.parse().map_err(|_| invalid("badges")))
+                    .collect::<Result<_, _>>()?,
+                None => Vec::new(),
+            },
+        });
+    }
+    Ok(arrivals)
+}
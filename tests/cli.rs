@@ -0,0 +1,69 @@
+use std::process::Command;
+
+// Auf stdout steht mit --format json nur das Ergebnis, das Protokoll der Akteure steht auf stderr
+#[test]
+fn run_prints_only_json_on_stdout() {
+    let output = Command::new(env!("CARGO_BIN_EXE_elevator"))
+        .args(["run", "--seed", "1", "--duration", "2m", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "run failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(results[0]["seed"], 1);
+    assert!(results[0]["summary"]["passengers"].as_u64().unwrap() > 0);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Simulation: seed 1"));
+}
+
+#[test]
+fn run_prints_only_csv_on_stdout() {
+    let output = Command::new(env!("CARGO_BIN_EXE_elevator"))
+        .args(["run", "--seed", "1", "--duration", "2m", "--format", "csv"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(
+        lines.len(),
+        2,
+        "header and one row expected, got {:?}",
+        lines
+    );
+    assert!(lines[0].starts_with("label,seed,error,"));
+    assert_eq!(lines[1].split(',').count(), lines[0].split(',').count());
+}
+
+// Ein Stress-Startwert, der früher einen Fehler zeigte, muss ohne Befund durchlaufen
+fn stress(seed: u64) {
+    let dir = std::env::temp_dir().join(format!("elevator-stress-{}-{}", std::process::id(), seed));
+    let output = Command::new(env!("CARGO_BIN_EXE_elevator"))
+        .args(["stress", "--seed", &seed.to_string(), "--seeds", "1"])
+        .arg("--dir")
+        .arg(&dir)
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(
+        output.status.success(),
+        "stress seed {} failed:\n{}{}",
+        seed,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+// Der obere TWIN-Wagen kam an seinem außer Betrieb stehenden unteren Wagen nicht vorbei, Passagier 1 blieb darin
+#[test]
+fn stress_seed_1_lets_passengers_out_before_a_car_out_of_service() {
+    stress(1);
+}
+
+// Ein Rollstuhlfahrer wurde zweimal abgewiesen, weil der einzige Wagen schon belegt zu ihm fuhr
+#[test]
+fn stress_seed_121_serves_the_waiting_wheelchair_passenger() {
+    stress(121);
+}
@@ -0,0 +1,16 @@
+use std::process::Command;
+
+// Alle Szenarien in `golden/` müssen genau ihre akzeptierten Läufe ergeben
+#[test]
+fn scenarios_match_their_golden_files() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/golden");
+    let output = Command::new(env!("CARGO_BIN_EXE_elevator"))
+        .args(["golden", "--dir", dir])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "golden scenarios differ: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,167 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+fn elevator(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_elevator"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "elevator {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// Ereignisprotokoll und Kennzahlen; das Protokoll davor zeigt ein fortgesetzter Lauf erst ab dem Snapshot
+fn events(output: &str) -> &str {
+    &output[output
+        .find("===== Events =====")
+        .expect("no event log in the output")..]
+}
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("elevator-snapshot-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+#[test]
+fn restore_continues_like_the_original_run() {
+    let snapshot = scratch("restore.json");
+    let snapshot = snapshot.to_str().unwrap();
+    let original = elevator(&[
+        "run",
+        "--seed",
+        "7",
+        "--duration",
+        "3m",
+        "--format",
+        "events",
+        "--snapshot",
+        snapshot,
+        "--snapshot-at",
+        "1m",
+    ]);
+    let restored = elevator(&[
+        "restore",
+        snapshot,
+        "--duration",
+        "3m",
+        "--format",
+        "events",
+    ]);
+    assert_eq!(events(&restored), events(&original));
+}
+
+#[test]
+fn restore_and_save_again_keeps_the_snapshot() {
+    let first = scratch("first.json");
+    let second = scratch("second.json");
+    elevator(&[
+        "run",
+        "--seed",
+        "3",
+        "--duration",
+        "2m",
+        "--snapshot",
+        first.to_str().unwrap(),
+        "--snapshot-at",
+        "40s",
+    ]);
+    // Ein fortgesetzter Lauf, der sofort wieder gespeichert wird, schreibt denselben Snapshot
+    elevator(&[
+        "restore",
+        first.to_str().unwrap(),
+        "--duration",
+        "2m",
+        "--snapshot",
+        second.to_str().unwrap(),
+        "--snapshot-at",
+        "40s",
+    ]);
+    assert_eq!(
+        fs::read_to_string(&second).unwrap(),
+        fs::read_to_string(&first).unwrap()
+    );
+}
+
+#[test]
+fn restore_rejects_an_edited_snapshot() {
+    let original = scratch("original.json");
+    let edited = scratch("edited.json");
+    elevator(&[
+        "run",
+        "--seed",
+        "5",
+        "--duration",
+        "2m",
+        "--snapshot",
+        original.to_str().unwrap(),
+        "--snapshot-at",
+        "1m",
+    ]);
+    let text = fs::read_to_string(&original).unwrap();
+    let at = text.find("\"trips\":").expect("no trips in the snapshot");
+    fs::write(
+        &edited,
+        format!(
+            "{}\"trips\": 999999,{}",
+            &text[..at],
+            &text[at..].split_once(',').unwrap().1
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_elevator"))
+        .args(["restore", edited.to_str().unwrap(), "--duration", "2m"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("does not reach the saved state"));
+}
+
+#[test]
+fn restore_replays_the_console_inputs() {
+    let first = scratch("console.json");
+    let second = scratch("console-again.json");
+    let mut console = Command::new(env!("CARGO_BIN_EXE_elevator"))
+        .args(["console", "--seed", "9", "--snapshot"])
+        .arg(&first)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    // Die Konsole läuft in Echtzeit; die Pausen geben den Eingaben verschiedene Zeitpunkte
+    let mut stdin = console.stdin.take().unwrap();
+    for line in [
+        "spawn 1 lobby->5",
+        "call 3 down",
+        "fail 1 door-jam",
+        "fire on 2",
+    ] {
+        thread::sleep(Duration::from_millis(500));
+        writeln!(stdin, "{}", line).unwrap();
+    }
+    thread::sleep(Duration::from_millis(500));
+    writeln!(stdin, "quit").unwrap();
+    drop(stdin);
+    assert!(console.wait().unwrap().success());
+    let text = fs::read_to_string(&first).unwrap();
+    assert!(text.contains("\"line\": \"fire on 2\""));
+
+    // Ohne die Eingaben käme das erneute Abspielen nicht beim gespeicherten Zustand an
+    elevator(&[
+        "restore",
+        first.to_str().unwrap(),
+        "--snapshot",
+        second.to_str().unwrap(),
+    ]);
+    assert_eq!(fs::read_to_string(&second).unwrap(), text);
+}